@@ -1,16 +1,31 @@
 import type { NextConfig } from "next";
 
-const nextConfig: NextConfig = {
-  async headers() {
-    return [
-      {
-        source: "/:path*",
-        headers: [
-          { key: "Cache-Control", value: "no-store, must-revalidate" },
-        ],
+// `npm run build:static` exports the app as static files into `out/`, for
+// the desktop build that bundles its frontend (see
+// src-tauri/tauri.static.conf.json). The data and URL opening go through
+// Tauri commands there, so the API routes are left out: route handlers are
+// `.ts` files, and pages and layouts are `.tsx`.
+const staticExport = process.env.NEXT_STATIC_EXPORT === "1";
+
+const nextConfig: NextConfig = staticExport
+  ? {
+      output: "export",
+      pageExtensions: ["tsx"],
+    }
+  : {
+      // The desktop launcher builds into a staging directory and swaps it into
+      // `.next` only once the build succeeds.
+      distDir: process.env.NEXT_DIST_DIR || ".next",
+      async headers() {
+        return [
+          {
+            source: "/:path*",
+            headers: [
+              { key: "Cache-Control", value: "no-store, must-revalidate" },
+            ],
+          },
+        ];
       },
-    ];
-  },
-};
+    };
 
 export default nextConfig;
@@ -0,0 +1,420 @@
+//! Field-level change history for cards, tasks and notes, for questions like
+//! "when did this move to Done" or "what was the old due date". Each change
+//! to one of those records is appended to `data/audit.jsonl` as one line:
+//! when, who, and the old and new value of every field that changed.
+//!
+//! `updatedAt`, `order` and `columnChangedAt` aren't tracked: they change
+//! along with other fields, or when a sibling moves. Column changes carry the
+//! column titles, so they still read well after a column is deleted.
+//!
+//! The log is compacted on launch, and with the `compact_history` command:
+//! past the retention period, a record's edits are merged into one entry per
+//! day, and the history of records deleted by then is dropped.
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::{HashMap, HashSet};
+use std::io::{BufRead, Write};
+use std::path::{Path, PathBuf};
+
+use crate::logging;
+use crate::merge::Record;
+use crate::models::{AppData, EntityKind};
+
+pub const AUDIT_FILE: &str = "data/audit.jsonl";
+
+/// Entries older than this are compacted on launch.
+pub const RETENTION_DAYS: u32 = 90;
+
+const UNTRACKED_FIELDS: &[&str] = &["id", "updatedAt", "order", "columnChangedAt"];
+
+const DAY_MS: i64 = 24 * 60 * 60 * 1000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Action {
+    Created,
+    Updated,
+    Deleted,
+}
+
+/// Who made a change: the OS user on this machine, or `sync` for changes
+/// that came from another device.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Actor {
+    Local,
+    Sync,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FieldChange {
+    pub field: String,
+    /// `None` when the field was unset.
+    pub old: Option<Value>,
+    pub new: Option<Value>,
+    /// Display names for id fields (the column title for `columnId`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub old_label: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub new_label: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditEntry {
+    pub at: i64,
+    pub actor: String,
+    pub entity: EntityKind,
+    pub id: String,
+    pub action: Action,
+    /// For `created`, every field's initial value; for `deleted`, every
+    /// field's last value.
+    pub changes: Vec<FieldChange>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CompactionReport {
+    pub before: usize,
+    pub after: usize,
+}
+
+pub struct AuditLog {
+    path: PathBuf,
+    user: String,
+}
+
+impl AuditLog {
+    /// Open the log for a project, compacting entries past `RETENTION_DAYS`.
+    pub fn open(project_dir: &str) -> Self {
+        let user = std::env::var("USER")
+            .or_else(|_| std::env::var("USERNAME"))
+            .unwrap_or_else(|_| "local".to_string());
+        let log = AuditLog {
+            path: Path::new(project_dir).join(AUDIT_FILE),
+            user,
+        };
+        match log.compact(RETENTION_DAYS) {
+            Ok(report) if report.after < report.before => logging::info(&format!(
+                "Compacted change history: {} entries to {}",
+                report.before, report.after
+            )),
+            Ok(_) => {}
+            Err(e) => logging::warn(&format!("Failed to compact change history: {}", e)),
+        }
+        log
+    }
+
+    /// Append an entry for every card, task and note that differs between
+    /// `prev` and `next`.
+    pub fn record(&self, prev: &AppData, next: &AppData, actor: Actor) -> Result<(), String> {
+        let actor = match actor {
+            Actor::Local => self.user.as_str(),
+            Actor::Sync => "sync",
+        };
+        let at = crate::mutations::now_millis();
+        let columns = ColumnTitles::new(prev, next);
+        let mut entries = Vec::new();
+        diff(&prev.cards, &next.cards, at, actor, &columns, &mut entries);
+        diff(&prev.tasks, &next.tasks, at, actor, &columns, &mut entries);
+        diff(&prev.notes, &next.notes, at, actor, &columns, &mut entries);
+        if entries.is_empty() {
+            return Ok(());
+        }
+
+        let mut lines = String::new();
+        for entry in &entries {
+            lines.push_str(&serde_json::to_string(entry).map_err(|e| e.to_string())?);
+            lines.push('\n');
+        }
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+        }
+        std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .and_then(|mut f| f.write_all(lines.as_bytes()))
+            .map_err(|e| format!("Failed to write {}: {}", self.path.display(), e))
+    }
+
+    /// The history of one record, oldest first.
+    pub fn history(&self, entity: EntityKind, id: &str) -> Result<Vec<AuditEntry>, String> {
+        Ok(self
+            .read()?
+            .into_iter()
+            .filter(|e| e.entity == entity && e.id == id)
+            .collect())
+    }
+
+    /// The history of every record of one kind, oldest first.
+    pub fn entries(&self, entity: EntityKind) -> Result<Vec<AuditEntry>, String> {
+        Ok(self.read()?.into_iter().filter(|e| e.entity == entity).collect())
+    }
+
+    /// Compact entries older than `older_than_days`. The file is only
+    /// rewritten if that changes anything.
+    pub fn compact(&self, older_than_days: u32) -> Result<CompactionReport, String> {
+        let entries = self.read()?;
+        let before = entries.len();
+        let cutoff = crate::mutations::now_millis() - i64::from(older_than_days) * DAY_MS;
+        let compacted = compact(entries, cutoff);
+        let after = compacted.len();
+        if after < before {
+            let mut lines = String::new();
+            for entry in &compacted {
+                lines.push_str(&serde_json::to_string(entry).map_err(|e| e.to_string())?);
+                lines.push('\n');
+            }
+            crate::store::write_atomic(&self.path, lines.as_bytes())?;
+        }
+        Ok(CompactionReport { before, after })
+    }
+
+    fn read(&self) -> Result<Vec<AuditEntry>, String> {
+        let file = match std::fs::File::open(&self.path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(format!("Failed to read {}: {}", self.path.display(), e)),
+        };
+        let mut entries = Vec::new();
+        for line in std::io::BufReader::new(file).lines() {
+            let line = line.map_err(|e| format!("Failed to read {}: {}", self.path.display(), e))?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            // A line cut short by a crash is skipped rather than failing the
+            // whole history.
+            if let Ok(entry) = serde_json::from_str(&line) {
+                entries.push(entry);
+            }
+        }
+        Ok(entries)
+    }
+}
+
+/// Column titles from both versions, so a deleted column still has one.
+struct ColumnTitles<'a>(HashMap<&'a str, &'a str>);
+
+impl<'a> ColumnTitles<'a> {
+    fn new(prev: &'a AppData, next: &'a AppData) -> Self {
+        ColumnTitles(
+            prev.columns
+                .iter()
+                .chain(&next.columns)
+                .map(|c| (c.id.as_str(), c.title.as_str()))
+                .collect(),
+        )
+    }
+
+    fn label(&self, field: &str, value: Option<&Value>) -> Option<String> {
+        if field != "columnId" {
+            return None;
+        }
+        value
+            .and_then(Value::as_str)
+            .and_then(|id| self.0.get(id))
+            .map(|title| title.to_string())
+    }
+}
+
+fn fields(value: Option<Value>) -> Map<String, Value> {
+    match value {
+        Some(Value::Object(map)) => map,
+        _ => Map::new(),
+    }
+}
+
+fn diff<T: Record>(
+    prev: &[T],
+    next: &[T],
+    at: i64,
+    actor: &str,
+    columns: &ColumnTitles,
+    out: &mut Vec<AuditEntry>,
+) {
+    let before: HashMap<&str, &T> = prev.iter().map(|r| (r.id(), r)).collect();
+    let after: HashSet<&str> = next.iter().map(Record::id).collect();
+    let mut push = |id: &str, action: Action, old: Option<&T>, new: Option<&T>| {
+        let old = fields(old.and_then(|r| serde_json::to_value(r).ok()));
+        let new = fields(new.and_then(|r| serde_json::to_value(r).ok()));
+        let mut names: Vec<&String> = old.keys().chain(new.keys()).collect();
+        names.sort();
+        names.dedup();
+        let changes: Vec<FieldChange> = names
+            .into_iter()
+            .filter(|name| !UNTRACKED_FIELDS.contains(&name.as_str()))
+            .filter(|name| old.get(*name) != new.get(*name))
+            .map(|name| FieldChange {
+                field: name.clone(),
+                old: old.get(name).cloned(),
+                new: new.get(name).cloned(),
+                old_label: columns.label(name, old.get(name)),
+                new_label: columns.label(name, new.get(name)),
+            })
+            .collect();
+        if action == Action::Updated && changes.is_empty() {
+            return;
+        }
+        out.push(AuditEntry {
+            at,
+            actor: actor.to_string(),
+            entity: T::KIND,
+            id: id.to_string(),
+            action,
+            changes,
+        });
+    };
+    for record in next {
+        match before.get(record.id()) {
+            None => push(record.id(), Action::Created, None, Some(record)),
+            Some(old) if *old != record => push(record.id(), Action::Updated, Some(old), Some(record)),
+            Some(_) => {}
+        }
+    }
+    for record in prev {
+        if !after.contains(record.id()) {
+            push(record.id(), Action::Deleted, Some(record), None);
+        }
+    }
+}
+
+/// Compact the entries before `cutoff`: drop the history of records deleted
+/// before it, and merge each record's updates into one entry per UTC day.
+/// A merged field keeps its first old and last new value, and is dropped if
+/// those are equal. Entries from `cutoff` on are kept as they are.
+pub fn compact(entries: Vec<AuditEntry>, cutoff: i64) -> Vec<AuditEntry> {
+    let deleted: HashSet<(EntityKind, String)> = entries
+        .iter()
+        .filter(|e| e.at < cutoff && e.action == Action::Deleted)
+        .map(|e| (e.entity, e.id.clone()))
+        .collect();
+    let touched_since: HashSet<(EntityKind, String)> = entries
+        .iter()
+        .filter(|e| e.at >= cutoff)
+        .map(|e| (e.entity, e.id.clone()))
+        .collect();
+
+    let mut out: Vec<AuditEntry> = Vec::new();
+    // Index in `out` of each record's open day of merged updates.
+    let mut open: HashMap<(EntityKind, String), (i64, usize)> = HashMap::new();
+    for entry in entries {
+        let key = (entry.entity, entry.id.clone());
+        if entry.at >= cutoff {
+            out.push(entry);
+            continue;
+        }
+        if deleted.contains(&key) && !touched_since.contains(&key) {
+            continue;
+        }
+        let day = entry.at.div_euclid(DAY_MS);
+        if entry.action != Action::Updated {
+            open.remove(&key);
+            out.push(entry);
+            continue;
+        }
+        match open.get(&key) {
+            Some(&(open_day, i)) if open_day == day => merge_into(&mut out[i], entry),
+            _ => {
+                open.insert(key, (day, out.len()));
+                out.push(entry);
+            }
+        }
+    }
+    out.retain(|e| e.action != Action::Updated || !e.changes.is_empty());
+    out
+}
+
+fn merge_into(into: &mut AuditEntry, later: AuditEntry) {
+    into.at = later.at;
+    if !into.actor.split(", ").any(|a| a == later.actor) {
+        into.actor = format!("{}, {}", into.actor, later.actor);
+    }
+    for change in later.changes {
+        match into.changes.iter_mut().find(|c| c.field == change.field) {
+            Some(existing) => {
+                existing.new = change.new;
+                existing.new_label = change.new_label;
+            }
+            None => into.changes.push(change),
+        }
+    }
+    into.changes.retain(|c| c.old != c.new);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn update(at: i64, id: &str, field: &str, old: Value, new: Value) -> AuditEntry {
+        AuditEntry {
+            at,
+            actor: "me".into(),
+            entity: EntityKind::Card,
+            id: id.into(),
+            action: Action::Updated,
+            changes: vec![FieldChange {
+                field: field.into(),
+                old: Some(old),
+                new: Some(new),
+                old_label: None,
+                new_label: None,
+            }],
+        }
+    }
+
+    fn lifecycle(at: i64, id: &str, action: Action) -> AuditEntry {
+        AuditEntry {
+            at,
+            actor: "me".into(),
+            entity: EntityKind::Card,
+            id: id.into(),
+            action,
+            changes: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn merges_old_updates_per_day() {
+        let entries = vec![
+            update(1_000, "a", "title", json!("A"), json!("B")),
+            update(2_000, "a", "title", json!("B"), json!("C")),
+            update(3_000, "a", "dueDate", json!(1), json!(2)),
+            update(4_000, "a", "dueDate", json!(2), json!(1)),
+            update(DAY_MS + 1, "a", "title", json!("C"), json!("D")),
+            update(10 * DAY_MS, "a", "title", json!("D"), json!("E")),
+            update(10 * DAY_MS + 1, "a", "title", json!("E"), json!("F")),
+        ];
+        let out = compact(entries, 5 * DAY_MS);
+        assert_eq!(out.len(), 4);
+        // The due date change cancelled out.
+        assert_eq!(out[0].changes.len(), 1);
+        assert_eq!(out[0].changes[0].old, Some(json!("A")));
+        assert_eq!(out[0].changes[0].new, Some(json!("C")));
+        assert_eq!(out[0].at, 4_000);
+        assert_eq!(out[1].changes[0].new, Some(json!("D")));
+        // Recent entries are untouched.
+        assert_eq!(out[2].changes[0].new, Some(json!("E")));
+        assert_eq!(out[3].changes[0].new, Some(json!("F")));
+        // Compacting again changes nothing.
+        assert_eq!(compact(out.clone(), 5 * DAY_MS), out);
+    }
+
+    #[test]
+    fn drops_history_of_records_deleted_before_the_cutoff() {
+        let entries = vec![
+            lifecycle(1_000, "gone", Action::Created),
+            update(2_000, "gone", "title", json!("A"), json!("B")),
+            lifecycle(3_000, "gone", Action::Deleted),
+            lifecycle(4_000, "kept", Action::Created),
+            lifecycle(5_000, "back", Action::Deleted),
+            lifecycle(DAY_MS * 10, "back", Action::Created),
+        ];
+        let out = compact(entries, DAY_MS * 5);
+        let ids: Vec<&str> = out.iter().map(|e| e.id.as_str()).collect();
+        assert_eq!(ids, ["kept", "back", "back"]);
+    }
+}
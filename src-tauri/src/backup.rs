@@ -0,0 +1,379 @@
+//! Rolling backups of the app data. Snapshots are the data as JSON — the same
+//! format as `data/app-data.json`, whichever storage backend is active — in
+//! `data/backups/`, named by UTC time and the reason they were taken:
+//!
+//! ```text
+//! data/backups/app-data-20260114T093012Z-launch.json
+//! ```
+//!
+//! Snapshots are taken on launch, on an interval while the app runs, and
+//! before every destructive command. Old snapshots are pruned with a
+//! grandfather-father-son policy configured in `backup-config.json` in the
+//! project directory.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+
+use crate::logging;
+use crate::models::AppData;
+use crate::store::write_atomic;
+
+pub const CONFIG_FILE: &str = "backup-config.json";
+pub const BACKUP_DIR: &str = "data/backups";
+const PREFIX: &str = "app-data-";
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct BackupConfig {
+    /// Minutes between periodic snapshots; 0 disables them.
+    pub interval_minutes: u64,
+    /// Always keep this many of the newest snapshots, whatever their age.
+    pub keep_recent: usize,
+    /// Keep the newest snapshot from each of the last N hours...
+    pub keep_hourly: usize,
+    /// ...days...
+    pub keep_daily: usize,
+    /// ...and weeks that have one.
+    pub keep_weekly: usize,
+}
+
+impl Default for BackupConfig {
+    fn default() -> Self {
+        BackupConfig {
+            interval_minutes: 30,
+            keep_recent: 10,
+            keep_hourly: 24,
+            keep_daily: 14,
+            keep_weekly: 12,
+        }
+    }
+}
+
+impl BackupConfig {
+    pub fn load(project_dir: &str) -> Self {
+        let path = Path::new(project_dir).join(CONFIG_FILE);
+        let Ok(content) = std::fs::read_to_string(&path) else {
+            return BackupConfig::default();
+        };
+        serde_json::from_str(&content).unwrap_or_else(|e| {
+            logging::warn(&format!("Ignoring invalid {}: {}", CONFIG_FILE, e));
+            BackupConfig::default()
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupInfo {
+    pub name: String,
+    /// Milliseconds since the epoch, like the timestamps in `AppData`.
+    pub created_at: i64,
+    pub reason: String,
+    pub size: u64,
+}
+
+pub struct BackupManager {
+    dir: PathBuf,
+    config: BackupConfig,
+}
+
+impl BackupManager {
+    pub fn new(project_dir: &str) -> Self {
+        BackupManager {
+            dir: Path::new(project_dir).join(BACKUP_DIR),
+            config: BackupConfig::load(project_dir),
+        }
+    }
+
+    pub fn config(&self) -> &BackupConfig {
+        &self.config
+    }
+
+    /// Write `contents` (a serialized data document, see
+    /// `DataStore::contents`) into the backup directory and prune old
+    /// snapshots.
+    pub fn snapshot(&self, reason: &str, contents: &[u8]) -> Result<BackupInfo, String> {
+        std::fs::create_dir_all(&self.dir)
+            .map_err(|e| format!("Failed to create {}: {}", self.dir.display(), e))?;
+
+        let secs = now_secs();
+        let reason = sanitize_reason(reason);
+        let mut name = format!("{}{}-{}.json", PREFIX, format_timestamp(secs), reason);
+        // Two snapshots in the same second (e.g. back-to-back deletes) get a
+        // numeric suffix rather than overwriting each other.
+        let mut n = 1;
+        while self.dir.join(&name).exists() {
+            n += 1;
+            name = format!("{}{}-{}-{}.json", PREFIX, format_timestamp(secs), reason, n);
+        }
+
+        // Written like the data file, so a crash can't leave a truncated
+        // backup in the restore list.
+        write_atomic(&self.dir.join(&name), contents)
+            .map_err(|e| format!("Failed to write backup {}: {}", name, e))?;
+        logging::info(&format!("Backup created: {}", name));
+
+        self.prune();
+        Ok(BackupInfo {
+            name,
+            created_at: secs as i64 * 1000,
+            reason,
+            size: contents.len() as u64,
+        })
+    }
+
+    /// Snapshot only if `contents` differs from the newest snapshot.
+    pub fn snapshot_if_changed(&self, reason: &str, contents: &[u8]) -> Result<Option<BackupInfo>, String> {
+        if let Some(latest) = self.list().first() {
+            if std::fs::read(self.dir.join(&latest.name)).ok().as_deref() == Some(contents) {
+                return Ok(None);
+            }
+        }
+        self.snapshot(reason, contents).map(Some)
+    }
+
+    /// All snapshots, newest first.
+    pub fn list(&self) -> Vec<BackupInfo> {
+        let Ok(entries) = std::fs::read_dir(&self.dir) else {
+            return Vec::new();
+        };
+        let mut backups: Vec<BackupInfo> = entries
+            .filter_map(|e| e.ok())
+            .filter_map(|e| {
+                let name = e.file_name().to_string_lossy().to_string();
+                let (secs, reason) = parse_name(&name)?;
+                let size = e.metadata().map(|m| m.len()).unwrap_or(0);
+                Some(BackupInfo {
+                    name,
+                    created_at: secs as i64 * 1000,
+                    reason,
+                    size,
+                })
+            })
+            .collect();
+        backups.sort_by(|a, b| b.created_at.cmp(&a.created_at).then(b.name.cmp(&a.name)));
+        backups
+    }
+
+    /// Read a snapshot. `name` must be one returned by `list`.
+    pub fn load(&self, name: &str) -> Result<AppData, String> {
+        if parse_name(name).is_none() || name.contains(['/', '\\']) {
+            return Err(format!("Not a backup: {}", name));
+        }
+        let content = std::fs::read_to_string(self.dir.join(name))
+            .map_err(|e| format!("Failed to read backup {}: {}", name, e))?;
+        serde_json::from_str(&content).map_err(|e| format!("Backup {} is invalid: {}", name, e))
+    }
+
+    /// Delete snapshots the retention policy no longer covers.
+    pub fn prune(&self) {
+        let backups = self.list();
+        let keep = retained(&backups, &self.config);
+        for backup in backups.iter().filter(|b| !keep.contains(&b.name)) {
+            match std::fs::remove_file(self.dir.join(&backup.name)) {
+                Ok(()) => logging::info(&format!("Pruned backup: {}", backup.name)),
+                Err(e) => logging::warn(&format!("Failed to prune backup {}: {}", backup.name, e)),
+            }
+        }
+    }
+}
+
+/// Names of the snapshots to keep. `backups` must be sorted newest first.
+fn retained(backups: &[BackupInfo], config: &BackupConfig) -> BTreeSet<String> {
+    let mut keep: BTreeSet<String> = backups
+        .iter()
+        .take(config.keep_recent)
+        .map(|b| b.name.clone())
+        .collect();
+
+    let hour = |b: &BackupInfo| b.created_at / 3_600_000;
+    let day = |b: &BackupInfo| b.created_at / 86_400_000;
+    // 1970-01-01 was a Thursday; shift so weeks start on Monday.
+    let week = |b: &BackupInfo| (b.created_at / 86_400_000 + 3) / 7;
+
+    for (bucket, limit) in [
+        (&hour as &dyn Fn(&BackupInfo) -> i64, config.keep_hourly),
+        (&day, config.keep_daily),
+        (&week, config.keep_weekly),
+    ] {
+        let mut seen = BTreeSet::new();
+        for backup in backups {
+            if seen.len() >= limit {
+                break;
+            }
+            // Newest first, so the first snapshot seen in a bucket is the
+            // newest one in it.
+            if seen.insert(bucket(backup)) {
+                keep.insert(backup.name.clone());
+            }
+        }
+    }
+    keep
+}
+
+fn sanitize_reason(reason: &str) -> String {
+    let cleaned: String = reason
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_lowercase() } else { '-' })
+        .collect();
+    let cleaned = cleaned.trim_matches('-').to_string();
+    if cleaned.is_empty() {
+        String::from("manual")
+    } else {
+        cleaned
+    }
+}
+
+/// `app-data-20260114T093012Z-launch.json` -> (epoch secs, "launch").
+fn parse_name(name: &str) -> Option<(u64, String)> {
+    let rest = name.strip_prefix(PREFIX)?.strip_suffix(".json")?;
+    let (stamp, reason) = rest.split_once('-')?;
+    Some((parse_timestamp(stamp)?, reason.to_string()))
+}
+
+fn now_secs() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+/// Format epoch seconds as compact ISO 8601 UTC, e.g. `20260114T093012Z`.
+fn format_timestamp(secs: u64) -> String {
+    let days = (secs / 86_400) as i64;
+    let rem = secs % 86_400;
+    let (y, m, d) = civil_from_days(days);
+    format!(
+        "{:04}{:02}{:02}T{:02}{:02}{:02}Z",
+        y,
+        m,
+        d,
+        rem / 3600,
+        (rem % 3600) / 60,
+        rem % 60
+    )
+}
+
+fn parse_timestamp(stamp: &str) -> Option<u64> {
+    if stamp.len() != 16 || !stamp.is_ascii() || &stamp[8..9] != "T" || !stamp.ends_with('Z') {
+        return None;
+    }
+    let num = |range: std::ops::Range<usize>| {
+        let digits = stamp.get(range)?;
+        digits.bytes().all(|b| b.is_ascii_digit()).then(|| digits.parse::<i64>().ok())?
+    };
+    let days = days_from_civil(num(0..4)?, num(4..6)?, num(6..8)?);
+    let secs = days * 86_400 + num(9..11)? * 3600 + num(11..13)? * 60 + num(13..15)?;
+    u64::try_from(secs).ok()
+}
+
+// Howard Hinnant's civil calendar algorithms (proleptic Gregorian, UTC).
+
+pub fn civil_from_days(z: i64) -> (i64, u32, u32) {
+    let z = z + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let d = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let m = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let y = yoe + era * 400 + if m <= 2 { 1 } else { 0 };
+    (y, m, d)
+}
+
+pub fn days_from_civil(y: i64, m: i64, d: i64) -> i64 {
+    let y = if m <= 2 { y - 1 } else { y };
+    let era = y.div_euclid(400);
+    let yoe = y.rem_euclid(400);
+    let mp = if m > 2 { m - 3 } else { m + 9 };
+    let doy = (153 * mp + 2) / 5 + d - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn backup(stamp: &str) -> BackupInfo {
+        BackupInfo {
+            name: format!("{}{}-launch.json", PREFIX, stamp),
+            created_at: parse_timestamp(stamp).unwrap() as i64 * 1000,
+            reason: "launch".into(),
+            size: 0,
+        }
+    }
+
+    #[test]
+    fn keeps_the_newest_per_hour_day_and_week() {
+        let backups: Vec<BackupInfo> = [
+            "20260302T101500Z", // Monday
+            "20260302T100500Z",
+            "20260302T090000Z",
+            "20260301T230000Z", // Sunday, the previous week and month
+            "20260301T120000Z",
+            "20260228T120000Z",
+            "20260222T120000Z",
+            "20260215T120000Z",
+        ]
+        .into_iter()
+        .map(backup)
+        .collect();
+        let config = BackupConfig {
+            keep_recent: 1,
+            keep_hourly: 2,
+            keep_daily: 3,
+            keep_weekly: 3,
+            ..BackupConfig::default()
+        };
+
+        let keep = retained(&backups, &config);
+        let expected: BTreeSet<String> = [
+            "20260302T101500Z",
+            "20260302T090000Z",
+            "20260301T230000Z",
+            "20260228T120000Z",
+            "20260222T120000Z",
+        ]
+        .into_iter()
+        .map(|s| backup(s).name)
+        .collect();
+        assert_eq!(keep, expected);
+
+        let none = BackupConfig {
+            keep_recent: 0,
+            keep_hourly: 0,
+            keep_daily: 0,
+            keep_weekly: 0,
+            ..BackupConfig::default()
+        };
+        assert!(retained(&backups, &none).is_empty());
+    }
+
+    #[test]
+    fn timestamps_round_trip() {
+        assert_eq!(days_from_civil(1970, 1, 1), 0);
+        assert_eq!(days_from_civil(2000, 3, 1), 11_017);
+        assert_eq!(civil_from_days(-1), (1969, 12, 31));
+        for days in (-800_000..800_000).step_by(97) {
+            let (y, m, d) = civil_from_days(days);
+            assert_eq!(days_from_civil(y, m as i64, d as i64), days);
+        }
+
+        for stamp in ["19700101T000000Z", "20240229T235959Z", "20260114T093012Z", "21000301T000000Z"] {
+            let secs = parse_timestamp(stamp).unwrap();
+            assert_eq!(format_timestamp(secs), stamp);
+        }
+        assert_eq!(parse_timestamp("20260114T093012Z"), Some(1_768_383_012));
+        for stamp in ["20260114T093012", "20260114X093012Z", "2026-114T093012Z", "20260114T0930١2Z"] {
+            assert_eq!(parse_timestamp(stamp), None, "{}", stamp);
+        }
+        assert_eq!(
+            parse_name("app-data-20260114T093012Z-pre-migration-v2.json"),
+            Some((1_768_383_012, "pre-migration-v2".into()))
+        );
+        assert_eq!(parse_name("app-data-20260114T093012Z.json"), None);
+    }
+}
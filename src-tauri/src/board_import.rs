@@ -0,0 +1,1012 @@
+//! Importers for boards exported from Trello and Jira.
+//!
+//! An export is converted into a single-project document shaped like a
+//! project bundle and added with `bundle::import` as a copy, so it gets new
+//! ids and a unique project name like any other imported project.
+//!
+//! - Trello (board JSON from "Print and export"): the board becomes the
+//!   project, open lists become columns and cards become cards; archived
+//!   cards stay archived. Each checklist becomes a note on its card, and the
+//!   card's comments are gathered into one note. Labels named `P0` to `P4`
+//!   set the priority.
+//! - Jira (the JSON of a search, `{"issues": [...]}`): each status becomes a
+//!   column, ordered by status category (to do, in progress, done). Issues
+//!   become cards and sub-tasks become tasks linked to their parent's card.
+//!   An issue's comments are gathered into one note.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+
+use crate::bundle::{self, ImportMode, ImportReport};
+use crate::error::StoreError;
+use crate::models::{
+    AppData, ArchiveReason, KanbanCard, KanbanColumn, Note, Project, Task, TaskPriority, TaskStatus,
+};
+use crate::mutations::now_millis;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Source {
+    Trello,
+    Jira,
+}
+
+impl Source {
+    /// Guess the source from the export's top-level keys.
+    pub fn detect(doc: &Value) -> Option<Source> {
+        if doc.get("lists").is_some() && doc.get("cards").is_some() {
+            Some(Source::Trello)
+        } else if doc.get("issues").is_some() {
+            Some(Source::Jira)
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BoardImportReport {
+    pub source: Source,
+    /// Nothing was written; the counts are what an import would create.
+    pub dry_run: bool,
+    #[serde(flatten)]
+    pub import: ImportReport,
+    pub column_titles: Vec<String>,
+    /// Things in the export that were left out or could not be read.
+    pub warnings: Vec<String>,
+}
+
+/// A converted export: one project, as `bundle::import` takes it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Converted {
+    pub data: AppData,
+    pub warnings: Vec<String>,
+}
+
+pub fn convert(content: &str, source: Option<Source>) -> Result<(Source, Converted), StoreError> {
+    let doc: Value = serde_json::from_str(content)
+        .map_err(|e| StoreError::invalid(format!("Not a JSON export: {}", e)))?;
+    let source = source
+        .or_else(|| Source::detect(&doc))
+        .ok_or_else(|| StoreError::invalid("Not a Trello board or Jira issue export"))?;
+    let converted = match source {
+        Source::Trello => convert_trello(doc)?,
+        Source::Jira => convert_jira(doc)?,
+    };
+    Ok((source, converted))
+}
+
+/// Add a converted export to `data` as a new project.
+pub fn import(
+    data: &mut AppData,
+    source: Source,
+    converted: Converted,
+    dry_run: bool,
+) -> Result<BoardImportReport, StoreError> {
+    let column_titles = converted
+        .data
+        .columns
+        .iter()
+        .map(|c| c.title.clone())
+        .collect();
+    let import = bundle::import(data, converted.data, ImportMode::Copy)?;
+    Ok(BoardImportReport {
+        source,
+        dry_run,
+        import,
+        column_titles,
+        warnings: converted.warnings,
+    })
+}
+
+// Trello
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TrelloBoard {
+    #[serde(default)]
+    id: String,
+    #[serde(default)]
+    name: String,
+    #[serde(default)]
+    desc: String,
+    #[serde(default)]
+    lists: Vec<TrelloList>,
+    #[serde(default)]
+    cards: Vec<TrelloCard>,
+    #[serde(default)]
+    checklists: Vec<TrelloChecklist>,
+    #[serde(default)]
+    actions: Vec<TrelloAction>,
+}
+
+#[derive(Deserialize)]
+struct TrelloList {
+    id: String,
+    name: String,
+    #[serde(default)]
+    closed: bool,
+    #[serde(default)]
+    pos: f64,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TrelloCard {
+    id: String,
+    name: String,
+    #[serde(default)]
+    desc: String,
+    id_list: String,
+    #[serde(default)]
+    closed: bool,
+    #[serde(default)]
+    pos: f64,
+    due: Option<String>,
+    date_last_activity: Option<String>,
+    #[serde(default)]
+    labels: Vec<TrelloLabel>,
+}
+
+#[derive(Deserialize)]
+struct TrelloLabel {
+    #[serde(default)]
+    name: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TrelloChecklist {
+    id: String,
+    name: String,
+    id_card: String,
+    #[serde(default)]
+    check_items: Vec<TrelloCheckItem>,
+}
+
+#[derive(Deserialize)]
+struct TrelloCheckItem {
+    name: String,
+    #[serde(default)]
+    state: String,
+    #[serde(default)]
+    pos: f64,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TrelloAction {
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default)]
+    date: String,
+    #[serde(default)]
+    data: Value,
+    member_creator: Option<Value>,
+}
+
+fn convert_trello(doc: Value) -> Result<Converted, StoreError> {
+    let board: TrelloBoard = serde_json::from_value(doc)
+        .map_err(|e| StoreError::invalid(format!("Invalid Trello export: {}", e)))?;
+    let now = now_millis();
+    let mut out = Output::new(
+        &board.id,
+        &board.name,
+        &board.desc,
+        "Trello import",
+        trello_created(&board.id).unwrap_or(now),
+    );
+
+    let mut lists: Vec<&TrelloList> = board.lists.iter().collect();
+    lists.sort_by(|a, b| a.pos.total_cmp(&b.pos));
+    for list in &lists {
+        if list.closed {
+            let cards = board.cards.iter().filter(|c| c.id_list == list.id).count();
+            out.warn(format!(
+                "Skipped archived list \"{}\" and its {} cards",
+                list.name, cards
+            ));
+        } else {
+            out.column(
+                &list.id,
+                &list.name,
+                trello_created(&list.id).unwrap_or(now),
+            );
+        }
+    }
+
+    let mut cards: Vec<&TrelloCard> = board.cards.iter().collect();
+    cards.sort_by(|a, b| a.pos.total_cmp(&b.pos));
+    for card in cards {
+        if !out.has_column(&card.id_list) {
+            if lists.iter().all(|l| l.id != card.id_list) {
+                out.warn(format!(
+                    "Skipped card \"{}\": its list is not in the export",
+                    card.name
+                ));
+            }
+            continue;
+        }
+        let created_at = trello_created(&card.id).unwrap_or(now);
+        let updated_at = card
+            .date_last_activity
+            .as_deref()
+            .and_then(parse_timestamp)
+            .unwrap_or(created_at);
+        let due_date = out.due_date(&card.name, card.due.as_deref());
+        let priority = card.labels.iter().find_map(|l| parse_priority(&l.name));
+        out.card(KanbanCard {
+            id: card.id.clone(),
+            title: card.name.clone(),
+            description: non_empty(&card.desc),
+            priority,
+            column_id: card.id_list.clone(),
+            order: 0,
+            created_at,
+            updated_at,
+            column_changed_at: updated_at,
+            due_date,
+            archived: card.closed.then_some(true),
+            archived_at: card.closed.then_some(updated_at),
+            archive_reason: card.closed.then_some(ArchiveReason::Archived),
+            linked_task_ids: None,
+            linked_note_ids: None,
+        });
+    }
+
+    for checklist in &board.checklists {
+        let mut items: Vec<&TrelloCheckItem> = checklist.check_items.iter().collect();
+        items.sort_by(|a, b| a.pos.total_cmp(&b.pos));
+        let content = items
+            .iter()
+            .map(|i| {
+                format!(
+                    "- [{}] {}",
+                    if i.state == "complete" { "x" } else { " " },
+                    i.name
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        out.note(
+            &checklist.id,
+            &checklist.id_card,
+            &checklist.name,
+            content,
+            None,
+        );
+    }
+
+    let mut comments: HashMap<String, Vec<(String, String, String)>> = HashMap::new();
+    for action in board.actions.iter().filter(|a| a.kind == "commentCard") {
+        let Some(card_id) = action.data.pointer("/card/id").and_then(Value::as_str) else {
+            continue;
+        };
+        let text = action
+            .data
+            .get("text")
+            .and_then(Value::as_str)
+            .unwrap_or_default();
+        let author = action
+            .member_creator
+            .as_ref()
+            .and_then(|m| m.get("fullName").or_else(|| m.get("username")))
+            .and_then(Value::as_str)
+            .unwrap_or("Unknown");
+        comments.entry(card_id.to_string()).or_default().push((
+            action.date.clone(),
+            author.to_string(),
+            text.to_string(),
+        ));
+    }
+    out.comment_notes(comments);
+
+    Ok(out.finish())
+}
+
+/// Trello ids start with the creation time in seconds, as hex.
+fn trello_created(id: &str) -> Option<i64> {
+    let seconds = i64::from_str_radix(id.get(..8)?, 16).ok()?;
+    Some(seconds * 1000)
+}
+
+// Jira
+
+#[derive(Deserialize)]
+struct JiraExport {
+    #[serde(default)]
+    issues: Vec<JiraIssue>,
+}
+
+#[derive(Deserialize)]
+struct JiraIssue {
+    #[serde(default)]
+    id: String,
+    key: String,
+    #[serde(default)]
+    fields: JiraFields,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+struct JiraFields {
+    #[serde(default)]
+    summary: String,
+    /// Plain text (API v2) or an Atlassian document (API v3).
+    #[serde(default)]
+    description: Value,
+    status: Option<JiraStatus>,
+    priority: Option<JiraNamed>,
+    duedate: Option<String>,
+    created: Option<String>,
+    updated: Option<String>,
+    statuscategorychangedate: Option<String>,
+    issuetype: Option<JiraIssueType>,
+    parent: Option<JiraParent>,
+    project: Option<JiraProject>,
+    resolutiondate: Option<String>,
+    comment: Option<JiraComments>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct JiraStatus {
+    name: String,
+    status_category: Option<JiraStatusCategory>,
+}
+
+#[derive(Deserialize)]
+struct JiraStatusCategory {
+    key: String,
+}
+
+#[derive(Deserialize)]
+struct JiraNamed {
+    #[serde(default)]
+    name: String,
+}
+
+#[derive(Deserialize)]
+struct JiraIssueType {
+    #[serde(default)]
+    subtask: bool,
+}
+
+#[derive(Deserialize)]
+struct JiraParent {
+    key: String,
+}
+
+#[derive(Deserialize)]
+struct JiraProject {
+    #[serde(default)]
+    key: String,
+    #[serde(default)]
+    name: String,
+}
+
+#[derive(Deserialize)]
+struct JiraComments {
+    #[serde(default)]
+    comments: Vec<JiraComment>,
+}
+
+#[derive(Deserialize)]
+struct JiraComment {
+    author: Option<Value>,
+    #[serde(default)]
+    body: Value,
+    #[serde(default)]
+    created: String,
+}
+
+fn convert_jira(doc: Value) -> Result<Converted, StoreError> {
+    let export: JiraExport = serde_json::from_value(doc)
+        .map_err(|e| StoreError::invalid(format!("Invalid Jira export: {}", e)))?;
+    if export.issues.is_empty() {
+        return Err(StoreError::invalid("The Jira export has no issues"));
+    }
+    let now = now_millis();
+    let project = export.issues.iter().find_map(|i| i.fields.project.as_ref());
+    let created = export
+        .issues
+        .iter()
+        .filter_map(|i| i.fields.created.as_deref().and_then(parse_timestamp))
+        .min()
+        .unwrap_or(now);
+    let mut out = Output::new(
+        project.map_or("jira", |p| p.key.as_str()),
+        project.map_or("", |p| p.name.as_str()),
+        "",
+        "Jira import",
+        created,
+    );
+
+    // One column per status, to do first and done last.
+    let mut statuses: Vec<(u8, &str)> = Vec::new();
+    for issue in export.issues.iter().filter(|i| !is_subtask(i)) {
+        let status = status_of(issue);
+        if !statuses.iter().any(|(_, s)| *s == status.1) {
+            statuses.push(status);
+        }
+    }
+    statuses.sort_by_key(|(category, _)| *category);
+    for (_, name) in &statuses {
+        out.column(&format!("status:{}", name), name, created);
+    }
+
+    // Issues are usually exported newest first; cards go oldest first.
+    let mut issues: Vec<&JiraIssue> = export.issues.iter().collect();
+    issues.sort_by_key(|i| {
+        i.fields
+            .created
+            .as_deref()
+            .and_then(parse_timestamp)
+            .unwrap_or(now)
+    });
+    let card_ids: HashMap<&str, String> = issues
+        .iter()
+        .filter(|i| !is_subtask(i))
+        .map(|i| (i.key.as_str(), issue_id(i)))
+        .collect();
+    for issue in issues {
+        let fields = &issue.fields;
+        let title = if fields.summary.is_empty() {
+            issue.key.clone()
+        } else {
+            format!("{} {}", issue.key, fields.summary)
+        };
+        let created_at = fields
+            .created
+            .as_deref()
+            .and_then(parse_timestamp)
+            .unwrap_or(now);
+        let updated_at = fields
+            .updated
+            .as_deref()
+            .and_then(parse_timestamp)
+            .unwrap_or(created_at);
+        let due_date = out.due_date(&issue.key, fields.duedate.as_deref());
+        let priority = fields
+            .priority
+            .as_ref()
+            .and_then(|p| jira_priority(&p.name));
+        let description = non_empty(&document_text(&fields.description));
+        let (category, status) = status_of(issue);
+
+        if is_subtask(issue) {
+            let parent = fields.parent.as_ref().map(|p| p.key.as_str());
+            let card_id = parent.and_then(|key| card_ids.get(key)).cloned();
+            if card_id.is_none() {
+                out.warn(format!(
+                    "Sub-task {} has no parent in the export; imported unlinked",
+                    issue.key
+                ));
+            }
+            out.task(Task {
+                id: issue_id(issue),
+                project_id: String::new(),
+                title,
+                description,
+                status: match category {
+                    0 => TaskStatus::Todo,
+                    1 => TaskStatus::InProgress,
+                    _ => TaskStatus::Done,
+                },
+                priority: priority.unwrap_or(TaskPriority::P2),
+                due_date,
+                card_id,
+                links: None,
+                comments: None,
+                order: None,
+                created_at,
+                updated_at,
+            });
+        } else {
+            let column_changed_at = fields
+                .statuscategorychangedate
+                .as_deref()
+                .or(fields.resolutiondate.as_deref())
+                .and_then(parse_timestamp)
+                .unwrap_or(updated_at);
+            out.card(KanbanCard {
+                id: issue_id(issue),
+                title,
+                description,
+                priority,
+                column_id: format!("status:{}", status),
+                order: 0,
+                created_at,
+                updated_at,
+                column_changed_at,
+                due_date,
+                archived: None,
+                archived_at: None,
+                archive_reason: None,
+                linked_task_ids: None,
+                linked_note_ids: None,
+            });
+        }
+    }
+
+    let mut comments: HashMap<String, Vec<(String, String, String)>> = HashMap::new();
+    for issue in &export.issues {
+        let Some(list) = &issue.fields.comment else {
+            continue;
+        };
+        // Comments on a sub-task go on its parent's card.
+        let card_id = if is_subtask(issue) {
+            issue
+                .fields
+                .parent
+                .as_ref()
+                .and_then(|p| card_ids.get(p.key.as_str()))
+        } else {
+            card_ids.get(issue.key.as_str())
+        };
+        let Some(card_id) = card_id else {
+            continue;
+        };
+        for comment in &list.comments {
+            let author = comment
+                .author
+                .as_ref()
+                .and_then(|a| a.get("displayName"))
+                .and_then(Value::as_str)
+                .unwrap_or("Unknown");
+            let mut text = document_text(&comment.body);
+            if is_subtask(issue) {
+                text = format!("({}) {}", issue.key, text);
+            }
+            comments.entry(card_id.clone()).or_default().push((
+                comment.created.clone(),
+                author.to_string(),
+                text,
+            ));
+        }
+    }
+    out.comment_notes(comments);
+
+    Ok(out.finish())
+}
+
+fn is_subtask(issue: &JiraIssue) -> bool {
+    issue.fields.issuetype.as_ref().is_some_and(|t| t.subtask)
+}
+
+fn issue_id(issue: &JiraIssue) -> String {
+    if issue.id.is_empty() {
+        issue.key.clone()
+    } else {
+        issue.id.clone()
+    }
+}
+
+/// The issue's status name, with its category's place on the board.
+fn status_of(issue: &JiraIssue) -> (u8, &str) {
+    match &issue.fields.status {
+        Some(status) => {
+            let category = match status.status_category.as_ref().map(|c| c.key.as_str()) {
+                Some("indeterminate") => 1,
+                Some("done") => 2,
+                _ => 0,
+            };
+            (category, status.name.as_str())
+        }
+        None => (0, "To Do"),
+    }
+}
+
+fn jira_priority(name: &str) -> Option<TaskPriority> {
+    match name.to_lowercase().as_str() {
+        "highest" | "blocker" => Some(TaskPriority::P0),
+        "high" | "critical" => Some(TaskPriority::P1),
+        "medium" | "major" => Some(TaskPriority::P2),
+        "low" | "minor" => Some(TaskPriority::P3),
+        "lowest" | "trivial" => Some(TaskPriority::P4),
+        other => parse_priority(other),
+    }
+}
+
+/// The text of a plain string or an Atlassian document, one line per
+/// paragraph.
+fn document_text(value: &Value) -> String {
+    fn collect(node: &Value, out: &mut String) {
+        if let Some(text) = node.get("text").and_then(Value::as_str) {
+            out.push_str(text);
+        }
+        if node.get("type").and_then(Value::as_str) == Some("hardBreak") {
+            out.push('\n');
+        }
+        for child in node
+            .get("content")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+        {
+            collect(child, out);
+            let block = matches!(
+                child.get("type").and_then(Value::as_str),
+                Some("paragraph" | "heading" | "listItem" | "codeBlock" | "blockquote")
+            );
+            if block && !out.ends_with('\n') {
+                out.push('\n');
+            }
+        }
+    }
+    match value {
+        Value::String(text) => text.trim().to_string(),
+        Value::Object(_) => {
+            let mut out = String::new();
+            collect(value, &mut out);
+            out.trim().to_string()
+        }
+        _ => String::new(),
+    }
+}
+
+// Shared
+
+/// The project being built from an export.
+struct Output {
+    data: AppData,
+    warnings: Vec<String>,
+}
+
+impl Output {
+    fn new(id: &str, name: &str, description: &str, default_name: &str, created_at: i64) -> Self {
+        let project = Project {
+            id: if id.is_empty() {
+                "import".to_string()
+            } else {
+                id.to_string()
+            },
+            name: if name.trim().is_empty() {
+                default_name.to_string()
+            } else {
+                name.trim().to_string()
+            },
+            description: non_empty(description),
+            created_at,
+            updated_at: now_millis(),
+        };
+        Output {
+            data: AppData {
+                schema_version: crate::migrations::CURRENT_VERSION,
+                current_project_id: Some(project.id.clone()),
+                projects: vec![project],
+                columns: Vec::new(),
+                cards: Vec::new(),
+                tasks: Vec::new(),
+                notes: Vec::new(),
+            },
+            warnings: Vec::new(),
+        }
+    }
+
+    fn project_id(&self) -> String {
+        self.data.projects[0].id.clone()
+    }
+
+    fn warn(&mut self, warning: String) {
+        self.warnings.push(warning);
+    }
+
+    fn has_column(&self, id: &str) -> bool {
+        self.data.columns.iter().any(|c| c.id == id)
+    }
+
+    fn column(&mut self, id: &str, title: &str, created_at: i64) {
+        let column = KanbanColumn {
+            id: id.to_string(),
+            project_id: self.project_id(),
+            title: title.to_string(),
+            order: self.data.columns.len() as i64,
+            created_at,
+        };
+        self.data.columns.push(column);
+    }
+
+    /// Add a card at the end of its column.
+    fn card(&mut self, mut card: KanbanCard) {
+        card.order = self
+            .data
+            .cards
+            .iter()
+            .filter(|c| c.column_id == card.column_id)
+            .count() as i64;
+        self.data.cards.push(card);
+    }
+
+    /// Add a task at the end of the list, linked both ways to its card.
+    fn task(&mut self, mut task: Task) {
+        task.project_id = self.project_id();
+        task.order = Some(self.data.tasks.len() as i64);
+        if let Some(card) = task
+            .card_id
+            .as_ref()
+            .and_then(|id| self.data.cards.iter_mut().find(|c| &c.id == id))
+        {
+            card.linked_task_ids
+                .get_or_insert_with(Vec::new)
+                .push(task.id.clone());
+        }
+        self.data.tasks.push(task);
+    }
+
+    /// Add a note linked both ways to `card_id`, if the card was imported.
+    fn note(&mut self, id: &str, card_id: &str, title: &str, content: String, at: Option<i64>) {
+        let Some(card) = self.data.cards.iter_mut().find(|c| c.id == card_id) else {
+            return;
+        };
+        card.linked_note_ids
+            .get_or_insert_with(Vec::new)
+            .push(id.to_string());
+        let at = at.unwrap_or(card.updated_at);
+        let note = Note {
+            id: id.to_string(),
+            project_id: self.data.projects[0].id.clone(),
+            title: title.to_string(),
+            content,
+            card_id: Some(card_id.to_string()),
+            links: None,
+            created_at: at,
+            updated_at: at,
+        };
+        self.data.notes.push(note);
+    }
+
+    /// One note per card with its comments, oldest first, as
+    /// `(timestamp, author, text)`.
+    fn comment_notes(&mut self, comments: HashMap<String, Vec<(String, String, String)>>) {
+        let mut comments: Vec<_> = comments.into_iter().collect();
+        comments.sort();
+        for (card_id, mut list) in comments {
+            list.sort();
+            let content = list
+                .iter()
+                .map(|(at, author, text)| {
+                    format!("**{}**, {}:\n{}", author, at.get(..10).unwrap_or(at), text)
+                })
+                .collect::<Vec<_>>()
+                .join("\n\n");
+            let at = list
+                .iter()
+                .filter_map(|(at, _, _)| parse_timestamp(at))
+                .max();
+            self.note(
+                &format!("comments:{}", card_id),
+                &card_id,
+                "Comments",
+                content,
+                at,
+            );
+        }
+    }
+
+    /// A due date as noon UTC of its day, the way the date pickers store
+    /// dates.
+    fn due_date(&mut self, record: &str, value: Option<&str>) -> Option<i64> {
+        let value = value.filter(|v| !v.is_empty())?;
+        let due = value
+            .get(..10)
+            .and_then(|d| crate::task_import::parse_date(d).ok().flatten());
+        if due.is_none() {
+            self.warn(format!("Ignored due date \"{}\" of {}", value, record));
+        }
+        due
+    }
+
+    fn finish(self) -> Converted {
+        Converted {
+            data: self.data,
+            warnings: self.warnings,
+        }
+    }
+}
+
+fn non_empty(text: &str) -> Option<String> {
+    let text = text.trim();
+    (!text.is_empty()).then(|| text.to_string())
+}
+
+/// `P0` to `P4`, as label names.
+fn parse_priority(name: &str) -> Option<TaskPriority> {
+    match name.trim().to_lowercase().as_str() {
+        "p0" => Some(TaskPriority::P0),
+        "p1" => Some(TaskPriority::P1),
+        "p2" => Some(TaskPriority::P2),
+        "p3" => Some(TaskPriority::P3),
+        "p4" => Some(TaskPriority::P4),
+        _ => None,
+    }
+}
+
+/// An ISO 8601 timestamp (`2026-01-14T09:30:00.000Z`, `...+0100`,
+/// `...+01:00`) as epoch millis. A bare date is noon UTC.
+fn parse_timestamp(value: &str) -> Option<i64> {
+    let noon = crate::task_import::parse_date(value.get(..10)?).ok()??;
+    let rest = &value[10..];
+    if rest.is_empty() {
+        return Some(noon);
+    }
+    let rest = rest.strip_prefix(['T', ' '])?;
+    let number = |s: &str| s.parse::<i64>().ok();
+    let (h, m) = (number(rest.get(..2)?)?, number(rest.get(3..5)?)?);
+    let mut rest = &rest[5..];
+    let mut s = 0;
+    if let Some(seconds) = rest.strip_prefix(':') {
+        s = number(seconds.get(..2)?)?;
+        rest = &seconds[2..];
+    }
+    let mut millis = 0;
+    if let Some(fraction) = rest.strip_prefix('.') {
+        let digits = fraction
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(fraction.len());
+        millis = format!("{:0<3}", &fraction[..digits.min(3)]).parse().ok()?;
+        rest = &fraction[digits..];
+    }
+    let offset = match rest {
+        "" | "Z" => 0,
+        _ => {
+            let sign = match rest.as_bytes()[0] {
+                b'+' => 1,
+                b'-' => -1,
+                _ => return None,
+            };
+            let zone = rest[1..].replace(':', "");
+            sign * (number(zone.get(..2)?)? * 60 + number(zone.get(2..4)?)?)
+        }
+    };
+    Some(noon - 12 * 3_600_000 + ((h * 60 + m - offset) * 60 + s) * 1000 + millis)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn parses_timestamps() {
+        assert_eq!(parse_timestamp("2026-01-14"), Some(1_768_392_000_000));
+        assert_eq!(
+            parse_timestamp("2026-01-14T12:00:00.000Z"),
+            Some(1_768_392_000_000)
+        );
+        assert_eq!(
+            parse_timestamp("2026-01-14T13:00:00.5+0100"),
+            Some(1_768_392_000_500)
+        );
+        assert_eq!(
+            parse_timestamp("2026-01-14T07:00-05:00"),
+            Some(1_768_392_000_000)
+        );
+        assert_eq!(parse_timestamp("2026-01-14T7:00"), None);
+        assert_eq!(parse_timestamp("yesterday"), None);
+    }
+
+    #[test]
+    fn converts_trello_boards() {
+        let doc = json!({
+            "id": "65a3b8000000000000000001",
+            "name": "Launch",
+            "lists": [
+                {"id": "l2", "name": "Done", "pos": 2},
+                {"id": "l1", "name": "Doing", "pos": 1},
+                {"id": "l3", "name": "Old", "pos": 3, "closed": true}
+            ],
+            "cards": [
+                {"id": "c2", "name": "Second", "idList": "l1", "pos": 2},
+                {"id": "c1", "name": "First", "idList": "l1", "pos": 1, "due": "2026-01-14T18:00:00.000Z",
+                 "labels": [{"name": "p1"}]},
+                {"id": "c3", "name": "Shipped", "idList": "l2", "pos": 3, "closed": true},
+                {"id": "c4", "name": "Gone", "idList": "l3"}
+            ],
+            "checklists": [{"id": "k1", "name": "Steps", "idCard": "c1", "checkItems": [
+                {"name": "b", "state": "incomplete", "pos": 2},
+                {"name": "a", "state": "complete", "pos": 1}
+            ]}],
+            "actions": [
+                {"type": "commentCard", "date": "2026-01-02T10:00:00.000Z",
+                 "data": {"text": "later", "card": {"id": "c1"}}, "memberCreator": {"fullName": "Sam"}},
+                {"type": "commentCard", "date": "2026-01-01T10:00:00.000Z",
+                 "data": {"text": "first", "card": {"id": "c1"}}, "memberCreator": {"fullName": "Ana"}},
+                {"type": "updateCard", "data": {"card": {"id": "c1"}}}
+            ]
+        });
+        let (source, converted) = convert(&doc.to_string(), None).unwrap();
+        assert_eq!(source, Source::Trello);
+        let data = &converted.data;
+        assert_eq!(data.projects[0].name, "Launch");
+        assert_eq!(data.projects[0].created_at, 0x65a3b800 * 1000);
+        let titles: Vec<&str> = data.columns.iter().map(|c| c.title.as_str()).collect();
+        assert_eq!(titles, ["Doing", "Done"]);
+        let cards: Vec<(&str, i64)> = data
+            .cards
+            .iter()
+            .map(|c| (c.title.as_str(), c.order))
+            .collect();
+        assert_eq!(cards, [("First", 0), ("Second", 1), ("Shipped", 0)]);
+        assert_eq!(data.cards[0].priority, Some(TaskPriority::P1));
+        assert_eq!(data.cards[0].due_date, Some(1_768_392_000_000));
+        assert_eq!(data.cards[2].archive_reason, Some(ArchiveReason::Archived));
+        assert_eq!(data.notes[0].content, "- [x] a\n- [ ] b");
+        assert_eq!(
+            data.notes[1].content,
+            "**Ana**, 2026-01-01:\nfirst\n\n**Sam**, 2026-01-02:\nlater"
+        );
+        assert_eq!(
+            data.cards[0].linked_note_ids.as_ref().map(Vec::len),
+            Some(2)
+        );
+        assert_eq!(
+            converted.warnings,
+            ["Skipped archived list \"Old\" and its 1 cards"]
+        );
+    }
+
+    #[test]
+    fn converts_jira_issues() {
+        let doc = json!({"issues": [
+            {"id": "3", "key": "WEB-3", "fields": {
+                "summary": "Subtask", "issuetype": {"subtask": true}, "parent": {"key": "WEB-1"},
+                "status": {"name": "Done", "statusCategory": {"key": "done"}},
+                "created": "2026-01-03T00:00:00.000+0000"}},
+            {"id": "2", "key": "WEB-2", "fields": {
+                "summary": "Review", "priority": {"name": "Highest"},
+                "status": {"name": "In Review", "statusCategory": {"key": "indeterminate"}},
+                "created": "2026-01-02T00:00:00.000+0000", "duedate": "2026-02-01"}},
+            {"id": "1", "key": "WEB-1", "fields": {
+                "summary": "Build", "project": {"key": "WEB", "name": "Website"},
+                "description": {"type": "doc", "content": [
+                    {"type": "paragraph", "content": [{"type": "text", "text": "Line one"}]},
+                    {"type": "paragraph", "content": [{"type": "text", "text": "Line two"}]}
+                ]},
+                "status": {"name": "Backlog", "statusCategory": {"key": "new"}},
+                "created": "2026-01-01T00:00:00.000+0000",
+                "comment": {"comments": [{"author": {"displayName": "Ana"}, "body": "Looks good",
+                                          "created": "2026-01-05T00:00:00.000+0000"}]}}}
+        ]});
+        let (source, converted) = convert(&doc.to_string(), None).unwrap();
+        assert_eq!(source, Source::Jira);
+        let data = &converted.data;
+        assert_eq!(data.projects[0].name, "Website");
+        let titles: Vec<&str> = data.columns.iter().map(|c| c.title.as_str()).collect();
+        assert_eq!(titles, ["Backlog", "In Review"]);
+        let cards: Vec<&str> = data.cards.iter().map(|c| c.title.as_str()).collect();
+        assert_eq!(cards, ["WEB-1 Build", "WEB-2 Review"]);
+        assert_eq!(
+            data.cards[0].description.as_deref(),
+            Some("Line one\nLine two")
+        );
+        assert_eq!(data.cards[1].priority, Some(TaskPriority::P0));
+        assert_eq!(data.tasks[0].status, TaskStatus::Done);
+        assert_eq!(data.tasks[0].card_id.as_deref(), Some("1"));
+        assert_eq!(data.cards[0].linked_task_ids, Some(vec!["3".to_string()]));
+        assert_eq!(data.notes[0].content, "**Ana**, 2026-01-05:\nLooks good");
+        assert!(converted.warnings.is_empty());
+    }
+
+    #[test]
+    fn imports_as_a_copy() {
+        let doc = json!({"issues": [{"key": "A-1", "fields": {"summary": "One"}}]});
+        let (source, converted) = convert(&doc.to_string(), Some(Source::Jira)).unwrap();
+        let mut data: AppData = serde_json::from_value(json!({})).unwrap();
+        let first = import(&mut data, source, converted.clone(), false).unwrap();
+        let second = import(&mut data, source, converted, false).unwrap();
+        assert_eq!(first.import.project_name, "Jira import");
+        assert_eq!(second.import.project_name, "Jira import (2)");
+        assert_eq!((second.import.columns, second.import.cards), (1, 1));
+        assert_eq!(data.cards.len(), 2);
+        assert_ne!(data.cards[0].id, data.cards[1].id);
+    }
+
+    #[test]
+    fn rejects_unknown_exports() {
+        assert!(convert("{\"boards\": []}", None).is_err());
+        assert!(convert("{\"issues\": []}", None).is_err());
+    }
+}
@@ -0,0 +1,342 @@
+//! Build marker — records what a `.next` build was built from so launches can
+//! skip `next build` when nothing relevant has changed.
+//!
+//! The marker lives at `.next/.build-commit`. The first line is the commit
+//! hash (older launchers wrote only this line); the rest are `key=value`
+//! fingerprint fields:
+//!
+//! ```text
+//! 3f2c9e1...
+//! lock=9a1b2c3d4e5f6071
+//! tree=clean
+//! ```
+
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use crate::{backup, build_slots, folder_sync, get_enhanced_path, http_server, logging, pipeline, sync};
+
+pub const MARKER_FILE: &str = ".build-commit";
+
+/// Creating this file in the project directory forces the next launch to
+/// rebuild. It is removed once the rebuild succeeds.
+pub const FORCE_REBUILD_FILE: &str = ".force-rebuild";
+
+/// Written into a restored `.next` by a rollback: the fingerprint of the
+/// build that failed to serve, so it isn't rebuilt until the code changes.
+pub const FAILED_MARKER_FILE: &str = ".build-failed";
+
+/// Setting this env var to a non-empty value other than `0` forces a rebuild.
+pub const FORCE_REBUILD_ENV: &str = "TOTAL_TPM_FORCE_REBUILD";
+
+/// Paths excluded from the working-tree fingerprint: app data changes on every
+/// edit and never affects the build, and the lockfile is hashed separately
+/// because `npm install` rewrites it on some registries. The rest are build
+/// slots, per-machine config and files the launcher writes on every launch.
+const TREE_EXCLUDES: [&str; 12] = [
+    "data",
+    "package-lock.json",
+    build_slots::CURRENT_DIR,
+    build_slots::STAGING_DIR,
+    build_slots::PREVIOUS_DIR,
+    FORCE_REBUILD_FILE,
+    pipeline::REPORT_FILE,
+    pipeline::CONFIG_FILE,
+    backup::CONFIG_FILE,
+    sync::CONFIG_FILE,
+    folder_sync::CONFIG_FILE,
+    http_server::CONFIG_FILE,
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BuildFingerprint {
+    pub commit: String,
+    pub lock_hash: String,
+    /// `clean`, or a hash of the uncommitted diff against HEAD and of the
+    /// untracked (but not ignored) files.
+    pub tree: String,
+}
+
+impl BuildFingerprint {
+    /// Fingerprint the project as it is right now. Returns `None` when git is
+    /// unavailable, in which case the caller should always rebuild.
+    pub fn current(project_dir: &str) -> Option<Self> {
+        let commit = git_output(project_dir, &["rev-parse", "HEAD"])?.trim().to_string();
+
+        let lock_hash = match std::fs::read(Path::new(project_dir).join("package-lock.json")) {
+            Ok(bytes) => hash_hex(&bytes),
+            Err(_) => String::from("none"),
+        };
+
+        let excludes: Vec<String> = TREE_EXCLUDES.iter().map(|p| format!(":(exclude){}", p)).collect();
+        let mut diff_args = vec!["diff", "HEAD", "--", "."];
+        diff_args.extend(excludes.iter().map(String::as_str));
+        let diff = git_output(project_dir, &diff_args)?;
+        // `git diff` doesn't see new files, so hash their names and contents.
+        let mut untracked_args = vec!["ls-files", "--others", "--exclude-standard", "-z", "--", "."];
+        untracked_args.extend(excludes.iter().map(String::as_str));
+        let untracked = git_output(project_dir, &untracked_args)?;
+        let tree = if diff.trim().is_empty() && untracked.is_empty() {
+            String::from("clean")
+        } else {
+            let mut state = diff.into_bytes();
+            for path in untracked.split('\0').filter(|p| !p.is_empty()) {
+                state.extend_from_slice(b"\0untracked ");
+                state.extend_from_slice(path.as_bytes());
+                state.push(0);
+                if let Ok(bytes) = std::fs::read(Path::new(project_dir).join(path)) {
+                    state.extend_from_slice(&bytes);
+                }
+            }
+            hash_hex(&state)
+        };
+
+        Some(BuildFingerprint {
+            commit,
+            lock_hash,
+            tree,
+        })
+    }
+
+    pub fn short_commit(&self) -> &str {
+        &self.commit[..self.commit.len().min(8)]
+    }
+
+    fn to_marker(&self) -> String {
+        format!("{}\nlock={}\ntree={}\n", self.commit, self.lock_hash, self.tree)
+    }
+
+    fn from_marker(content: &str) -> Option<Self> {
+        let mut lines = content.lines();
+        let commit = lines.next()?.trim().to_string();
+        let mut lock_hash = None;
+        let mut tree = None;
+        for line in lines {
+            match line.split_once('=') {
+                Some(("lock", v)) => lock_hash = Some(v.trim().to_string()),
+                Some(("tree", v)) => tree = Some(v.trim().to_string()),
+                _ => {}
+            }
+        }
+        Some(BuildFingerprint {
+            commit,
+            lock_hash: lock_hash?,
+            tree: tree?,
+        })
+    }
+}
+
+/// Read the marker in `build_dir`. Legacy commit-only markers yield `None`.
+pub fn read_marker(build_dir: &Path) -> Option<BuildFingerprint> {
+    let content = std::fs::read_to_string(build_dir.join(MARKER_FILE)).ok()?;
+    BuildFingerprint::from_marker(&content)
+}
+
+pub fn write_marker(build_dir: &Path, fingerprint: &BuildFingerprint) {
+    match std::fs::write(build_dir.join(MARKER_FILE), fingerprint.to_marker()) {
+        Ok(()) => logging::info(&format!(
+            "Saved build marker: {} (lock={}, tree={})",
+            fingerprint.commit, fingerprint.lock_hash, fingerprint.tree
+        )),
+        Err(e) => logging::warn(&format!("Failed to save build marker: {}", e)),
+    }
+}
+
+/// Record in `build_dir` that the build of `failed` didn't serve.
+pub fn write_failed_marker(build_dir: &Path, failed: &BuildFingerprint) {
+    if let Err(e) = std::fs::write(build_dir.join(FAILED_MARKER_FILE), failed.to_marker()) {
+        logging::warn(&format!("Failed to record failed build: {}", e));
+    }
+}
+
+fn read_failed_marker(build_dir: &Path) -> Option<BuildFingerprint> {
+    let content = std::fs::read_to_string(build_dir.join(FAILED_MARKER_FILE)).ok()?;
+    BuildFingerprint::from_marker(&content)
+}
+
+pub fn force_rebuild_requested(project_dir: &str) -> bool {
+    let env_forced = std::env::var(FORCE_REBUILD_ENV)
+        .map(|v| !v.is_empty() && v != "0")
+        .unwrap_or(false);
+    env_forced || Path::new(project_dir).join(FORCE_REBUILD_FILE).exists()
+}
+
+pub fn clear_force_rebuild(project_dir: &str) {
+    let path = Path::new(project_dir).join(FORCE_REBUILD_FILE);
+    if path.exists() {
+        let _ = std::fs::remove_file(&path);
+    }
+}
+
+/// Decide whether `next build` needs to run. Returns the reason to rebuild,
+/// or `None` when the existing build matches `current`, or when `current` was
+/// built before and rolled back because it didn't serve.
+pub fn rebuild_reason(project_dir: &str, current: Option<&BuildFingerprint>) -> Option<String> {
+    if force_rebuild_requested(project_dir) {
+        return Some(String::from("rebuild forced"));
+    }
+    let Some(current) = current else {
+        return Some(String::from("could not fingerprint working tree"));
+    };
+
+    let build_dir = Path::new(project_dir).join(".next");
+    if !build_dir.join("BUILD_ID").exists() {
+        return Some(String::from("no existing build"));
+    }
+    if read_failed_marker(&build_dir).as_ref() == Some(current) {
+        logging::warn(&format!(
+            "Build of {} failed to serve before; keeping the previous build until the code changes",
+            current.short_commit()
+        ));
+        return None;
+    }
+    let Some(built) = read_marker(&build_dir) else {
+        return Some(String::from("no build marker"));
+    };
+
+    if built.commit != current.commit {
+        Some(format!(
+            "commit changed ({} -> {})",
+            built.short_commit(),
+            current.short_commit()
+        ))
+    } else if built.lock_hash != current.lock_hash {
+        Some(String::from("package-lock.json changed"))
+    } else if built.tree != current.tree {
+        Some(String::from("uncommitted changes differ from last build"))
+    } else {
+        None
+    }
+}
+
+fn git_output(project_dir: &str, args: &[&str]) -> Option<String> {
+    Command::new("git")
+        .args(args)
+        .current_dir(project_dir)
+        .env("PATH", get_enhanced_path())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .output()
+        .ok()
+        .filter(|o| o.status.success())
+        .map(|o| String::from_utf8_lossy(&o.stdout).to_string())
+}
+
+/// 64-bit FNV-1a — stable across Rust releases, unlike `DefaultHasher`.
+fn hash_hex(bytes: &[u8]) -> String {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for b in bytes {
+        hash ^= *b as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    format!("{:016x}", hash)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+
+    fn fingerprint(commit: &str, lock: &str, tree: &str) -> BuildFingerprint {
+        BuildFingerprint {
+            commit: commit.to_string(),
+            lock_hash: lock.to_string(),
+            tree: tree.to_string(),
+        }
+    }
+
+    #[test]
+    fn reads_markers() {
+        let built = fingerprint("3f2c9e1aa", "9a1b2c3d4e5f6071", "clean");
+        assert_eq!(BuildFingerprint::from_marker(&built.to_marker()), Some(built));
+        assert_eq!(
+            BuildFingerprint::from_marker("abc\nother=1\ntree=x\nlock=y\n"),
+            Some(fingerprint("abc", "y", "x"))
+        );
+        // Markers from older launchers only have the commit.
+        assert_eq!(BuildFingerprint::from_marker("3f2c9e1aa\n"), None);
+        assert_eq!(BuildFingerprint::from_marker(""), None);
+    }
+
+    #[test]
+    fn explains_why_to_rebuild() {
+        let dir = TempDir::new("build-marker-reason");
+        let project = dir.as_str();
+        let current = fingerprint("aaaaaaaaaaaa", "lock", "clean");
+        assert_eq!(rebuild_reason(project, None).unwrap(), "could not fingerprint working tree");
+        assert_eq!(rebuild_reason(project, Some(&current)).unwrap(), "no existing build");
+
+        let build_dir = dir.join(".next");
+        std::fs::create_dir_all(&build_dir).unwrap();
+        std::fs::write(build_dir.join("BUILD_ID"), "x").unwrap();
+        assert_eq!(rebuild_reason(project, Some(&current)).unwrap(), "no build marker");
+
+        write_marker(&build_dir, &current);
+        assert_eq!(rebuild_reason(project, Some(&current)), None);
+        let cases = [
+            (fingerprint("bbbbbbbbbbbb", "lock", "clean"), "commit changed (aaaaaaaa -> bbbbbbbb)"),
+            (fingerprint("aaaaaaaaaaaa", "other", "clean"), "package-lock.json changed"),
+            (fingerprint("aaaaaaaaaaaa", "lock", "1234"), "uncommitted changes differ from last build"),
+        ];
+        for (changed, reason) in cases {
+            assert_eq!(rebuild_reason(project, Some(&changed)).unwrap(), reason);
+        }
+
+        std::fs::write(dir.join(FORCE_REBUILD_FILE), "").unwrap();
+        assert_eq!(rebuild_reason(project, Some(&current)).unwrap(), "rebuild forced");
+        clear_force_rebuild(project);
+        assert_eq!(rebuild_reason(project, Some(&current)), None);
+    }
+
+    #[test]
+    fn new_files_change_the_tree() {
+        let dir = TempDir::new("build-marker-tree");
+        let project = dir.as_str();
+        let git = |args: &[&str]| {
+            let status = Command::new("git")
+                .args(["-c", "user.name=t", "-c", "user.email=t@example.com"])
+                .args(args)
+                .current_dir(&dir)
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .status()
+                .unwrap();
+            assert!(status.success(), "git {:?}", args);
+        };
+        git(&["init", "-q"]);
+        std::fs::write(dir.join(".gitignore"), "ignored.txt\n").unwrap();
+        std::fs::write(dir.join("page.tsx"), "a").unwrap();
+        git(&["add", "."]);
+        git(&["commit", "-q", "-m", "init"]);
+        let tree = || BuildFingerprint::current(project).unwrap().tree;
+        assert_eq!(tree(), "clean");
+
+        std::fs::write(dir.join("ignored.txt"), "x").unwrap();
+        std::fs::create_dir_all(dir.join("data")).unwrap();
+        std::fs::write(dir.join("data").join("app-data.json"), "{}").unwrap();
+        assert_eq!(tree(), "clean");
+
+        std::fs::write(dir.join("new.tsx"), "b").unwrap();
+        let added = tree();
+        assert_ne!(added, "clean");
+        std::fs::write(dir.join("new.tsx"), "c").unwrap();
+        assert_ne!(tree(), added);
+
+        // What the launcher writes into the project must not change the tree,
+        // or every launch would rebuild.
+        std::fs::write(dir.join(FORCE_REBUILD_FILE), "").unwrap();
+        let before = BuildFingerprint::current(project).unwrap();
+        let report = pipeline::LaunchReport {
+            started_at: 1,
+            duration_ms: 2,
+            aborted_at: None,
+            stages: Vec::new(),
+        };
+        report.save(project);
+        std::fs::create_dir_all(dir.join(build_slots::PREVIOUS_DIR)).unwrap();
+        std::fs::write(dir.join(build_slots::PREVIOUS_DIR).join("BUILD_ID"), "old").unwrap();
+        std::fs::write(dir.join(backup::CONFIG_FILE), "{}").unwrap();
+        clear_force_rebuild(project);
+        assert_eq!(BuildFingerprint::current(project).unwrap(), before);
+    }
+}
@@ -0,0 +1,252 @@
+//! Build slots — `next build` writes into a staging directory that is only
+//! swapped into `.next` once the build succeeds. The build it replaces is kept
+//! in `.next-previous` as a fallback if the new one will not serve.
+//!
+//! `next.config.ts` reads `NEXT_DIST_DIR` so the build can be pointed at the
+//! staging directory; `next start` runs without it and serves `.next`.
+//!
+//! Swapping is two renames, not one atomic step, so a crash in between can
+//! leave no `.next`. `recover_interrupted_swap` finishes or undoes the swap
+//! at the next launch.
+
+use std::path::{Path, PathBuf};
+
+use crate::build_marker;
+use crate::logging;
+
+pub const DIST_DIR_ENV: &str = "NEXT_DIST_DIR";
+pub const CURRENT_DIR: &str = ".next";
+pub const STAGING_DIR: &str = ".next-staging";
+pub const PREVIOUS_DIR: &str = ".next-previous";
+
+fn slot(project_dir: &str, name: &str) -> PathBuf {
+    Path::new(project_dir).join(name)
+}
+
+/// A build directory is usable if `next build` got far enough to write BUILD_ID.
+pub fn is_usable(dir: &Path) -> bool {
+    dir.join("BUILD_ID").is_file()
+}
+
+/// The commit a build directory was built from, per its build marker.
+pub fn build_commit(dir: &Path) -> Option<String> {
+    let content = std::fs::read_to_string(dir.join(build_marker::MARKER_FILE)).ok()?;
+    content.lines().next().map(|l| l.trim().to_string())
+}
+
+/// Describe a build directory for status messages: its short commit, or
+/// "unknown commit" for builds without a marker.
+pub fn describe(dir: &Path) -> String {
+    match build_commit(dir) {
+        Some(commit) => commit[..commit.len().min(8)].to_string(),
+        None => String::from("unknown commit"),
+    }
+}
+
+pub fn current_usable(project_dir: &str) -> bool {
+    is_usable(&slot(project_dir, CURRENT_DIR))
+}
+
+pub fn previous_usable(project_dir: &str) -> bool {
+    is_usable(&slot(project_dir, PREVIOUS_DIR))
+}
+
+/// Remove any staging directory left over from an interrupted build and
+/// return the path `next build` should write into.
+pub fn prepare_staging(project_dir: &str) -> Result<PathBuf, String> {
+    let staging = slot(project_dir, STAGING_DIR);
+    if staging.exists() {
+        logging::info("Removing stale staging build...");
+        std::fs::remove_dir_all(&staging)
+            .map_err(|e| format!("Failed to remove {}: {}", STAGING_DIR, e))?;
+    }
+    Ok(staging)
+}
+
+/// Swap a successful staging build into `.next`, keeping the build it
+/// replaces as `.next-previous`.
+pub fn promote_staging(project_dir: &str) -> Result<(), String> {
+    let staging = slot(project_dir, STAGING_DIR);
+    let current = slot(project_dir, CURRENT_DIR);
+    let previous = slot(project_dir, PREVIOUS_DIR);
+
+    if !is_usable(&staging) {
+        return Err(format!("{} has no BUILD_ID", STAGING_DIR));
+    }
+
+    let had_current = current.exists();
+    if had_current {
+        if previous.exists() {
+            std::fs::remove_dir_all(&previous)
+                .map_err(|e| format!("Failed to remove {}: {}", PREVIOUS_DIR, e))?;
+        }
+        std::fs::rename(&current, &previous)
+            .map_err(|e| format!("Failed to move {} aside: {}", CURRENT_DIR, e))?;
+    }
+
+    if let Err(e) = std::fs::rename(&staging, &current) {
+        // Put the old build back so the server still has something to serve.
+        if had_current {
+            let _ = std::fs::rename(&previous, &current);
+        }
+        return Err(format!("Failed to promote {}: {}", STAGING_DIR, e));
+    }
+
+    if had_current {
+        logging::info(&format!(
+            "Promoted new build {} (kept {} as fallback)",
+            describe(&current),
+            describe(&previous)
+        ));
+    } else {
+        logging::info(&format!("Promoted new build {}", describe(&current)));
+    }
+    Ok(())
+}
+
+/// Replace `.next` with the last known-good build. The build being replaced
+/// is discarded, so a second rollback cannot land on it; if it was a complete
+/// build, its fingerprint is recorded so it isn't rebuilt until the code
+/// changes. Returns the short commit of the restored build.
+pub fn restore_previous(project_dir: &str) -> Result<String, String> {
+    let current = slot(project_dir, CURRENT_DIR);
+    let previous = slot(project_dir, PREVIOUS_DIR);
+
+    if !is_usable(&previous) {
+        return Err(String::from("No previous build to roll back to"));
+    }
+    let failed = if is_usable(&current) {
+        build_marker::read_marker(&current)
+    } else {
+        None
+    };
+    if current.exists() {
+        std::fs::remove_dir_all(&current)
+            .map_err(|e| format!("Failed to remove {}: {}", CURRENT_DIR, e))?;
+    }
+    std::fs::rename(&previous, &current)
+        .map_err(|e| format!("Failed to restore {}: {}", PREVIOUS_DIR, e))?;
+
+    if let Some(failed) = failed {
+        build_marker::write_failed_marker(&current, &failed);
+    }
+
+    let restored = describe(&current);
+    logging::info(&format!("Rolled back to previous build {}", restored));
+    Ok(restored)
+}
+
+/// Finish a swap that was interrupted between its renames: with no `.next`,
+/// move in the complete staging build, or else the previous one.
+pub fn recover_interrupted_swap(project_dir: &str) {
+    let current = slot(project_dir, CURRENT_DIR);
+    if current.exists() {
+        return;
+    }
+    for (name, dir) in [
+        (STAGING_DIR, slot(project_dir, STAGING_DIR)),
+        (PREVIOUS_DIR, slot(project_dir, PREVIOUS_DIR)),
+    ] {
+        if !is_usable(&dir) {
+            continue;
+        }
+        match std::fs::rename(&dir, &current) {
+            Ok(()) => logging::warn(&format!(
+                "{} was missing; moved {} build {} into its place",
+                CURRENT_DIR,
+                name,
+                describe(&current)
+            )),
+            Err(e) => logging::warn(&format!(
+                "Failed to recover {} from {}: {}",
+                CURRENT_DIR, name, e
+            )),
+        }
+        return;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::build_marker::BuildFingerprint;
+    use crate::test_util::TempDir;
+
+    fn fingerprint(commit: &str) -> BuildFingerprint {
+        BuildFingerprint {
+            commit: commit.to_string(),
+            lock_hash: String::from("lock"),
+            tree: String::from("clean"),
+        }
+    }
+
+    /// Build `commit` into the staging slot the way `next build` would.
+    fn build(project: &str, commit: &str) {
+        let staging = prepare_staging(project).unwrap();
+        std::fs::create_dir_all(&staging).unwrap();
+        std::fs::write(staging.join("BUILD_ID"), commit).unwrap();
+        build_marker::write_marker(&staging, &fingerprint(commit));
+    }
+
+    fn commit_in(project: &str, name: &str) -> Option<String> {
+        build_commit(&slot(project, name))
+    }
+
+    #[test]
+    fn promotes_and_restores_builds() {
+        let dir = TempDir::new("build-slots-promote");
+        let project = dir.as_str();
+        build(project, "aaaa");
+        promote_staging(project).unwrap();
+        assert_eq!(commit_in(project, CURRENT_DIR).as_deref(), Some("aaaa"));
+        assert!(!previous_usable(project));
+
+        build(project, "bbbb");
+        promote_staging(project).unwrap();
+        assert_eq!(commit_in(project, CURRENT_DIR).as_deref(), Some("bbbb"));
+        assert_eq!(commit_in(project, PREVIOUS_DIR).as_deref(), Some("aaaa"));
+        assert!(!slot(project, STAGING_DIR).exists());
+
+        // `bbbb` doesn't serve.
+        assert_eq!(restore_previous(project).unwrap(), "aaaa");
+        assert_eq!(commit_in(project, CURRENT_DIR).as_deref(), Some("aaaa"));
+        assert!(!previous_usable(project));
+        assert!(restore_previous(project).is_err());
+
+        // Not rebuilt until the code changes.
+        assert_eq!(
+            build_marker::rebuild_reason(project, Some(&fingerprint("bbbb"))),
+            None
+        );
+        assert!(build_marker::rebuild_reason(project, Some(&fingerprint("cccc"))).is_some());
+
+        // A new build replaces the record along with the old `.next`.
+        build(project, "cccc");
+        promote_staging(project).unwrap();
+        assert!(build_marker::rebuild_reason(project, Some(&fingerprint("bbbb"))).is_some());
+    }
+
+    #[test]
+    fn recovers_an_interrupted_swap() {
+        let dir = TempDir::new("build-slots-recover");
+        let project = dir.as_str();
+        build(project, "aaaa");
+        promote_staging(project).unwrap();
+        build(project, "bbbb");
+
+        // Crashed after moving `.next` aside, before moving staging in.
+        std::fs::rename(slot(project, CURRENT_DIR), slot(project, PREVIOUS_DIR)).unwrap();
+        recover_interrupted_swap(project);
+        assert_eq!(commit_in(project, CURRENT_DIR).as_deref(), Some("bbbb"));
+        assert_eq!(commit_in(project, PREVIOUS_DIR).as_deref(), Some("aaaa"));
+
+        // Crashed during a rollback, after removing `.next`.
+        std::fs::remove_dir_all(slot(project, CURRENT_DIR)).unwrap();
+        recover_interrupted_swap(project);
+        assert_eq!(commit_in(project, CURRENT_DIR).as_deref(), Some("aaaa"));
+
+        // Nothing to do with `.next` in place.
+        recover_interrupted_swap(project);
+        assert_eq!(commit_in(project, CURRENT_DIR).as_deref(), Some("aaaa"));
+    }
+}
@@ -0,0 +1,452 @@
+//! Project bundles: one project with its columns, cards, tasks and notes in a
+//! standalone JSON file, for handing a project to someone else or moving it
+//! between machines without copying the whole data file.
+//!
+//! A bundle is shaped like `app-data.json` with a single project, plus a
+//! `format` marker and a `bundleVersion`. It carries the data's
+//! `schemaVersion` too, so bundles from older versions of the app are
+//! migrated on import like an old data file would be.
+//!
+//! Importing as a copy gives every record a new id, so a bundle can be
+//! imported any number of times. Importing as a replacement keeps the ids and
+//! replaces the project with the same id, if there is one; only ids that
+//! clash with records of other projects are changed.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use crate::error::StoreError;
+use crate::merge::Record;
+use crate::migrations;
+use crate::models::{AppData, EntityKind};
+use crate::mutations::generate_id;
+
+pub const FORMAT: &str = "total-tpm-project";
+pub const BUNDLE_VERSION: u32 = 1;
+/// Where bundles are written when no path is given.
+pub const EXPORT_DIR: &str = "data/exports";
+pub const EXTENSION: &str = "tpm.json";
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct BundleHeader<'a> {
+    format: &'a str,
+    bundle_version: u32,
+    exported_at: i64,
+    #[serde(flatten)]
+    data: &'a AppData,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ImportMode {
+    /// Import with new ids, alongside whatever is there.
+    #[default]
+    Copy,
+    /// Replace the project with the same id, keeping the bundle's ids.
+    Replace,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportReport {
+    pub project_id: String,
+    pub project_name: String,
+    pub columns: usize,
+    pub cards: usize,
+    pub tasks: usize,
+    pub notes: usize,
+    /// Whether an existing project was replaced.
+    pub replaced: bool,
+    /// Links to records outside the project, which were dropped.
+    pub dropped_links: usize,
+}
+
+/// The project and everything in it, as a standalone document.
+pub fn extract(data: &AppData, project_id: &str) -> Result<AppData, StoreError> {
+    let project = data
+        .projects
+        .iter()
+        .find(|p| p.id == project_id)
+        .ok_or_else(|| StoreError::not_found(EntityKind::Project, project_id))?;
+    let columns: Vec<_> = data.columns.iter().filter(|c| c.project_id == project_id).cloned().collect();
+    let column_ids: HashSet<&str> = columns.iter().map(|c| c.id.as_str()).collect();
+    Ok(AppData {
+        schema_version: migrations::CURRENT_VERSION,
+        projects: vec![project.clone()],
+        cards: data
+            .cards
+            .iter()
+            .filter(|c| column_ids.contains(c.column_id.as_str()))
+            .cloned()
+            .collect(),
+        tasks: data.tasks.iter().filter(|t| t.project_id == project_id).cloned().collect(),
+        notes: data.notes.iter().filter(|n| n.project_id == project_id).cloned().collect(),
+        current_project_id: Some(project_id.to_string()),
+        columns,
+    })
+}
+
+/// Serialize a project as a bundle.
+pub fn export(data: &AppData, project_id: &str) -> Result<String, StoreError> {
+    let project = extract(data, project_id)?;
+    let header = BundleHeader {
+        format: FORMAT,
+        bundle_version: BUNDLE_VERSION,
+        exported_at: crate::mutations::now_millis(),
+        data: &project,
+    };
+    serde_json::to_string_pretty(&header)
+        .map_err(|e| StoreError::io(format!("Failed to serialize project: {}", e)))
+}
+
+/// `data/exports/<project name>-<millis>.tpm.json`.
+pub fn default_path(project_dir: &Path, project_name: &str) -> PathBuf {
+    project_dir
+        .join(EXPORT_DIR)
+        .join(format!("{}-{}.{}", slug(project_name), crate::mutations::now_millis(), EXTENSION))
+}
+
+/// A project name as a file name: `Website Relaunch!` -> `website-relaunch`.
+pub fn slug(project_name: &str) -> String {
+    let slug: String = project_name
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c.to_ascii_lowercase() } else { '-' })
+        .collect();
+    let slug = slug.split('-').filter(|s| !s.is_empty()).collect::<Vec<_>>().join("-");
+    if slug.is_empty() {
+        "project".to_string()
+    } else {
+        slug
+    }
+}
+
+/// Parse a bundle, migrating it to the current schema.
+pub fn parse(content: &str) -> Result<AppData, StoreError> {
+    let doc: Value = serde_json::from_str(content)
+        .map_err(|e| StoreError::invalid(format!("Not a project bundle: {}", e)))?;
+    if doc.get("format").and_then(Value::as_str) != Some(FORMAT) {
+        return Err(StoreError::invalid("Not a project bundle"));
+    }
+    let version = doc.get("bundleVersion").and_then(Value::as_u64).unwrap_or(0);
+    if version > u64::from(BUNDLE_VERSION) {
+        return Err(StoreError::invalid(format!(
+            "Bundle version {} is newer than this version of the app supports ({}). Update the app to import it.",
+            version, BUNDLE_VERSION
+        )));
+    }
+    let migrated = migrations::migrate(doc)?;
+    let bundle: AppData = serde_json::from_value(migrated.doc)
+        .map_err(|e| StoreError::invalid(format!("Invalid project bundle: {}", e)))?;
+    if bundle.projects.len() != 1 {
+        return Err(StoreError::invalid(format!(
+            "A project bundle holds one project, this one has {}",
+            bundle.projects.len()
+        )));
+    }
+    Ok(bundle)
+}
+
+/// Add the bundle's project to `data`.
+pub fn import(data: &mut AppData, mut bundle: AppData, mode: ImportMode) -> Result<ImportReport, StoreError> {
+    let old_project_id = bundle.projects[0].id.clone();
+    let replaced = mode == ImportMode::Replace && data.projects.iter().any(|p| p.id == old_project_id);
+    let was_current = data.current_project_id.as_deref() == Some(old_project_id.as_str());
+    if replaced {
+        data.delete_project(&old_project_id)?;
+    }
+
+    // Only the bundle's own project's records are taken. They are picked
+    // before assigning ids, so links to anything else count as outside the
+    // bundle and are dropped.
+    bundle.columns.retain(|c| c.project_id == old_project_id);
+    let column_ids: HashSet<String> = bundle.columns.iter().map(|c| c.id.clone()).collect();
+    bundle.cards.retain(|c| column_ids.contains(&c.column_id));
+    bundle.tasks.retain(|t| t.project_id == old_project_id);
+    bundle.notes.retain(|n| n.project_id == old_project_id);
+
+    let mut ids = IdMap::new(mode);
+    ids.assign(&mut bundle.projects, &data.projects, |p| &mut p.id);
+    ids.assign(&mut bundle.columns, &data.columns, |c| &mut c.id);
+    ids.assign(&mut bundle.cards, &data.cards, |c| &mut c.id);
+    ids.assign(&mut bundle.tasks, &data.tasks, |t| &mut t.id);
+    ids.assign(&mut bundle.notes, &data.notes, |n| &mut n.id);
+
+    let mut project = bundle.projects.remove(0);
+    for c in &mut bundle.columns {
+        c.project_id = project.id.clone();
+    }
+    for card in &mut bundle.cards {
+        if let Some(column_id) = ids.get(EntityKind::Column, &card.column_id) {
+            card.column_id = column_id;
+        }
+        if let Some(list) = &mut card.linked_task_ids {
+            *list = list.iter().filter_map(|id| ids.link(EntityKind::Task, id)).collect();
+        }
+        if let Some(list) = &mut card.linked_note_ids {
+            *list = list.iter().filter_map(|id| ids.link(EntityKind::Note, id)).collect();
+        }
+    }
+    for t in &mut bundle.tasks {
+        t.project_id = project.id.clone();
+        t.card_id = t.card_id.as_deref().and_then(|id| ids.link(EntityKind::Card, id));
+    }
+    for n in &mut bundle.notes {
+        n.project_id = project.id.clone();
+        n.card_id = n.card_id.as_deref().and_then(|id| ids.link(EntityKind::Card, id));
+    }
+
+    if !replaced {
+        project.name = unique_name(data, &project.name);
+    }
+    let report = ImportReport {
+        project_id: project.id.clone(),
+        project_name: project.name.clone(),
+        columns: bundle.columns.len(),
+        cards: bundle.cards.len(),
+        tasks: bundle.tasks.len(),
+        notes: bundle.notes.len(),
+        replaced,
+        dropped_links: ids.dropped,
+    };
+    data.projects.push(project);
+    data.columns.extend(bundle.columns);
+    data.cards.extend(bundle.cards);
+    data.tasks.extend(bundle.tasks);
+    data.notes.extend(bundle.notes);
+    if data.current_project_id.is_none() || (replaced && was_current) {
+        data.current_project_id = Some(report.project_id.clone());
+    }
+    Ok(report)
+}
+
+/// The ids the bundle's records get, by kind and old id.
+struct IdMap {
+    mode: ImportMode,
+    ids: HashMap<(EntityKind, String), String>,
+    dropped: usize,
+}
+
+impl IdMap {
+    fn new(mode: ImportMode) -> Self {
+        IdMap {
+            mode,
+            ids: HashMap::new(),
+            dropped: 0,
+        }
+    }
+
+    /// Give each record in `records` its id in `existing`'s collection.
+    fn assign<T: Record>(&mut self, records: &mut [T], existing: &[T], id: fn(&mut T) -> &mut String) {
+        let mut taken: HashSet<String> = existing.iter().map(|r| r.id().to_string()).collect();
+        for record in records {
+            let id = id(record);
+            let new = if self.mode == ImportMode::Copy || taken.contains(id.as_str()) {
+                generate_id()
+            } else {
+                id.clone()
+            };
+            taken.insert(new.clone());
+            let old = std::mem::replace(id, new.clone());
+            self.ids.entry((T::KIND, old)).or_insert(new);
+        }
+    }
+
+    fn get(&self, kind: EntityKind, old: &str) -> Option<String> {
+        self.ids.get(&(kind, old.to_string())).cloned()
+    }
+
+    /// The new id of a linked record, counting links to records outside the
+    /// bundle.
+    fn link(&mut self, kind: EntityKind, old: &str) -> Option<String> {
+        let new = self.get(kind, old);
+        if new.is_none() {
+            self.dropped += 1;
+        }
+        new
+    }
+}
+
+/// `name`, or `name (2)`, `name (3)`, ... if a project is already called that.
+fn unique_name(data: &AppData, name: &str) -> String {
+    let taken = |n: &str| data.projects.iter().any(|p| p.name == n);
+    if !taken(name) {
+        return name.to_string();
+    }
+    (2..)
+        .map(|i| format!("{} ({})", name, i))
+        .find(|n| !taken(n))
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mutations::{NewCard, NewNote, NewProject, NewTask};
+
+    /// A project with a card in its first column, a task linked to the card
+    /// and a note.
+    fn project(data: &mut AppData, name: &str) -> String {
+        let project_id = data
+            .create_project(NewProject {
+                name: name.into(),
+                description: None,
+            })
+            .unwrap()
+            .id;
+        let column_id = data.columns.iter().find(|c| c.project_id == project_id).unwrap().id.clone();
+        let card_id = data
+            .create_card(NewCard {
+                column_id,
+                title: format!("{} card", name),
+                description: None,
+                priority: None,
+                due_date: None,
+            })
+            .unwrap()
+            .id;
+        data.create_task(NewTask {
+            project_id: project_id.clone(),
+            title: format!("{} task", name),
+            description: None,
+            status: None,
+            priority: None,
+            due_date: None,
+            card_id: Some(card_id),
+            links: None,
+            comments: None,
+        })
+        .unwrap();
+        data.create_note(NewNote {
+            project_id: project_id.clone(),
+            title: format!("{} note", name),
+            content: None,
+            card_id: None,
+            links: None,
+        })
+        .unwrap();
+        project_id
+    }
+
+    fn round_trip(data: &AppData, project_id: &str) -> AppData {
+        parse(&export(data, project_id).unwrap()).unwrap()
+    }
+
+    fn names(data: &AppData) -> Vec<&str> {
+        data.projects.iter().map(|p| p.name.as_str()).collect()
+    }
+
+    #[test]
+    fn copies_can_be_imported_twice() {
+        let mut data = AppData::default();
+        let id = project(&mut data, "Website");
+        let bundle = round_trip(&data, &id);
+        let before = data.clone();
+
+        let first = import(&mut data, bundle.clone(), ImportMode::Copy).unwrap();
+        let second = import(&mut data, bundle, ImportMode::Copy).unwrap();
+        assert_eq!(names(&data), ["Website", "Website (2)", "Website (3)"]);
+        assert_eq!((second.columns, second.cards, second.tasks, second.notes), (3, 1, 1, 1));
+        assert!(!first.replaced && !second.replaced);
+        assert_eq!(first.dropped_links, 0);
+        assert!(crate::integrity::check(&data).is_clean());
+
+        // Every record is new, and the copies link to their own card.
+        let ids: HashSet<&str> = data.tasks.iter().map(|t| t.id.as_str()).collect();
+        assert_eq!(ids.len(), 3);
+        for task in &data.tasks[1..] {
+            let card = &data.cards[data.card_index(task.card_id.as_deref().unwrap()).unwrap()];
+            assert_eq!(data.card_project_id(card), Some(task.project_id.as_str()));
+            assert_ne!(Some(task.project_id.as_str()), before.current_project_id.as_deref());
+        }
+        assert_eq!(data.current_project_id, before.current_project_id);
+    }
+
+    #[test]
+    fn replacing_keeps_ids_unless_they_clash() {
+        let mut data = AppData::default();
+        let id = project(&mut data, "Website");
+        let other = project(&mut data, "Other");
+        let mut bundle = round_trip(&data, &id);
+        bundle.projects[0].name = "Website v2".into();
+        // A task whose id another project already uses.
+        let clash = data.tasks.iter().find(|t| t.project_id == other).unwrap().id.clone();
+        let old_task = std::mem::replace(&mut bundle.tasks[0].id, clash.clone());
+        bundle.cards[0].linked_task_ids = Some(vec![clash.clone()]);
+        data.current_project_id = Some(id.clone());
+
+        let report = import(&mut data, bundle.clone(), ImportMode::Replace).unwrap();
+        assert!(report.replaced);
+        assert_eq!(report.project_id, id);
+        assert_eq!(names(&data), ["Other", "Website v2"]);
+        assert_eq!(data.current_project_id.as_deref(), Some(id.as_str()));
+        assert!(crate::integrity::check(&data).is_clean());
+
+        let columns = data.columns.iter().filter(|c| c.project_id == id);
+        assert!(columns.zip(&bundle.columns).all(|(a, b)| a.id == b.id));
+        assert_eq!(data.cards.iter().find(|c| c.id == bundle.cards[0].id).unwrap().column_id, bundle.cards[0].column_id);
+        // The clashing task got a new id, and the card's link followed it.
+        let task = data.tasks.iter().find(|t| t.project_id == id).unwrap();
+        assert!(task.id != clash && task.id != old_task);
+        let card = &data.cards[data.card_index(&bundle.cards[0].id).unwrap()];
+        assert_eq!(card.linked_task_ids, Some(vec![task.id.clone()]));
+        assert_eq!(data.tasks.iter().filter(|t| t.id == clash).count(), 1);
+    }
+
+    #[test]
+    fn links_outside_the_project_are_dropped() {
+        let mut data = AppData::default();
+        let id = project(&mut data, "Website");
+        let other = project(&mut data, "Other");
+
+        // A hand-edited bundle with another project's records in it.
+        let mut bundle = round_trip(&data, &id);
+        let foreign = round_trip(&data, &other);
+        bundle.columns.extend(foreign.columns);
+        bundle.cards.extend(foreign.cards.clone());
+        bundle.tasks.extend(foreign.tasks.clone());
+        bundle.cards[0].linked_task_ids = Some(vec![foreign.tasks[0].id.clone(), "gone".into()]);
+        bundle.cards[0].linked_note_ids = Some(vec![bundle.notes[0].id.clone()]);
+        bundle.tasks[0].card_id = Some(foreign.cards[0].id.clone());
+
+        let report = import(&mut AppData::default(), bundle, ImportMode::Copy).unwrap();
+        assert_eq!((report.columns, report.cards, report.tasks, report.notes), (3, 1, 1, 1));
+        assert_eq!(report.dropped_links, 3);
+    }
+
+    #[test]
+    fn names_are_made_unique() {
+        let mut data = AppData::default();
+        for name in ["A", "A (2)", "B"] {
+            data.create_project(NewProject {
+                name: name.into(),
+                description: None,
+            })
+            .unwrap();
+        }
+        assert_eq!(unique_name(&data, "A"), "A (3)");
+        assert_eq!(unique_name(&data, "B"), "B (2)");
+        assert_eq!(unique_name(&data, "C"), "C");
+        assert_eq!(slug("Website Relaunch!"), "website-relaunch");
+        assert_eq!(slug("!!"), "project");
+    }
+
+    #[test]
+    fn rejects_what_it_cannot_import() {
+        let mut data = AppData::default();
+        let id = project(&mut data, "Website");
+        let mut doc: Value = serde_json::from_str(&export(&data, &id).unwrap()).unwrap();
+
+        doc["bundleVersion"] = Value::from(BUNDLE_VERSION + 1);
+        let err = parse(&doc.to_string()).unwrap_err();
+        assert!(matches!(&err, StoreError::Invalid { message } if message.contains("newer")), "{:?}", err);
+
+        doc["bundleVersion"] = Value::from(BUNDLE_VERSION);
+        doc["format"] = Value::from("something-else");
+        assert!(parse(&doc.to_string()).is_err());
+        assert!(parse(&serde_json::to_string(&data).unwrap()).is_err());
+        assert!(parse("[").is_err());
+    }
+}
@@ -0,0 +1,850 @@
+//! Tauri command handlers for the data store. Registered in `run()`.
+
+use std::collections::HashSet;
+use std::sync::Mutex;
+use tauri::{Emitter, Manager};
+
+use crate::analytics::{ColumnDwell, CumulativeFlow, CycleTimes, Flow, WeeklyThroughput, DAY_MS};
+use crate::audit::{AuditEntry, CompactionReport};
+use crate::backup::{BackupInfo, BackupManager};
+use crate::board_import::{BoardImportReport, Source};
+use crate::bundle::{ImportMode, ImportReport};
+use crate::dataset::{AggregateQuery, AggregateResult, DatasetSummary, Workspace};
+use crate::error::StoreError;
+use crate::integrity::{self, IntegrityReport};
+use crate::journal::HistoryEntry;
+use crate::logging;
+use crate::models::{AppData, AppDataPatch, ArchiveReason, EntityKind, KanbanCard, KanbanColumn, Note, Project, Task};
+use crate::mutations::{
+    CardUpdate, NewCard, NewNote, NewProject, NewTask, NoteUpdate, ProjectUpdate, TaskUpdate,
+};
+use crate::report::{ReportFormat, ReportKind};
+use crate::search::{SearchQuery, SearchResult};
+use crate::store::DataStore;
+use crate::sync::{GitSync, SyncReport};
+use crate::task_import::{ColumnMapping, CsvImportReport, CsvPreview};
+
+pub struct StoreState(pub Mutex<DataStore>);
+
+pub struct BackupState(pub BackupManager);
+
+/// `git` is `None` when `sync-config.json` is absent. Its lock is held for a
+/// whole sync, so syncs never overlap.
+pub struct SyncState {
+    pub git: Option<Mutex<GitSync>>,
+    pub last: Mutex<Option<SyncReport>>,
+}
+
+/// CSV datasets loaded for analysis this session.
+pub struct DatasetState(pub Mutex<Workspace>);
+
+/// Keeps the folder sync watcher alive for the life of the app. Nothing reads
+/// it; dropping it would stop the watch.
+pub struct FolderWatchState(#[allow(dead_code)] pub Mutex<Option<notify::RecommendedWatcher>>);
+
+/// Emitted when the data changes other than through the webview's own saves
+/// (e.g. a sync); the webview reloads on it.
+pub const DATA_CHANGED_EVENT: &str = "data-changed";
+
+type StoreResult<T> = Result<T, StoreError>;
+
+/// Snapshot the data file before a destructive change. Call with the store
+/// lock held so the snapshot is the state the change is applied to.
+fn snapshot_before(store: &DataStore, backups: &BackupState, reason: &str) -> StoreResult<()> {
+    backups.0.snapshot(reason, &store.contents()?).map_err(StoreError::io)?;
+    Ok(())
+}
+
+/// Whether replacing `current` with `next` would drop any record.
+fn drops_records(current: &AppData, next: &AppData) -> bool {
+    fn dropped<'a>(old: impl Iterator<Item = &'a String>, new: impl Iterator<Item = &'a String>) -> bool {
+        let new: HashSet<&String> = new.collect();
+        old.into_iter().any(|id| !new.contains(id))
+    }
+    dropped(current.projects.iter().map(|p| &p.id), next.projects.iter().map(|p| &p.id))
+        || dropped(current.columns.iter().map(|c| &c.id), next.columns.iter().map(|c| &c.id))
+        || dropped(current.cards.iter().map(|c| &c.id), next.cards.iter().map(|c| &c.id))
+        || dropped(current.tasks.iter().map(|t| &t.id), next.tasks.iter().map(|t| &t.id))
+        || dropped(current.notes.iter().map(|n| &n.id), next.notes.iter().map(|n| &n.id))
+}
+
+#[tauri::command]
+pub fn get_data(state: tauri::State<'_, StoreState>) -> AppData {
+    state.0.lock().unwrap().data().clone()
+}
+
+/// Whole-document saves happen on every edit, so only those that drop
+/// records are backed up first.
+#[tauri::command]
+pub fn put_data(
+    state: tauri::State<'_, StoreState>,
+    backups: tauri::State<'_, BackupState>,
+    data: AppData,
+) -> StoreResult<()> {
+    replace_data(&state, &backups, data)
+}
+
+/// Replace the whole document, backing it up first if records would be
+/// dropped. Shared by `put_data` and the HTTP server's `PUT /api/data`.
+pub fn replace_data(state: &StoreState, backups: &BackupState, data: AppData) -> StoreResult<()> {
+    let mut store = state.0.lock().unwrap();
+    if drops_records(store.data(), &data) {
+        snapshot_before(&store, backups, "put-data")?;
+    }
+    store.replace(data)
+}
+
+#[tauri::command]
+pub fn patch_data(
+    state: tauri::State<'_, StoreState>,
+    backups: tauri::State<'_, BackupState>,
+    patch: AppDataPatch,
+) -> StoreResult<()> {
+    let mut store = state.0.lock().unwrap();
+    let mut next = store.data().clone();
+    patch.clone().apply(&mut next);
+    if drops_records(store.data(), &next) {
+        snapshot_before(&store, &backups, "patch-data")?;
+    }
+    store.patch(patch)
+}
+
+#[tauri::command]
+pub fn search(state: tauri::State<'_, StoreState>, query: SearchQuery) -> Vec<SearchResult> {
+    state.0.lock().unwrap().search(&query)
+}
+
+// Undo
+
+/// Undo the latest change. Returns what was undone, or `None` if there was
+/// nothing to undo. No backup is taken: `redo` puts it back.
+#[tauri::command]
+pub fn undo(app: tauri::AppHandle, state: tauri::State<'_, StoreState>) -> StoreResult<Option<HistoryEntry>> {
+    let undone = state.0.lock().unwrap().undo()?;
+    if undone.is_some() {
+        let _ = app.emit(DATA_CHANGED_EVENT, ());
+    }
+    Ok(undone)
+}
+
+/// Redo the change undone last. Returns it, or `None` if there was nothing
+/// to redo.
+#[tauri::command]
+pub fn redo(app: tauri::AppHandle, state: tauri::State<'_, StoreState>) -> StoreResult<Option<HistoryEntry>> {
+    let redone = state.0.lock().unwrap().redo()?;
+    if redone.is_some() {
+        let _ = app.emit(DATA_CHANGED_EVENT, ());
+    }
+    Ok(redone)
+}
+
+#[tauri::command]
+pub fn history(state: tauri::State<'_, StoreState>) -> Vec<HistoryEntry> {
+    state.0.lock().unwrap().history()
+}
+
+// Change history
+
+/// Field-level changes to a card, task or note, oldest first. Kept after the
+/// record is deleted, until compaction.
+#[tauri::command]
+pub fn get_entity_history(
+    state: tauri::State<'_, StoreState>,
+    entity: EntityKind,
+    id: String,
+) -> StoreResult<Vec<AuditEntry>> {
+    if !matches!(entity, EntityKind::Card | EntityKind::Task | EntityKind::Note) {
+        return Err(StoreError::invalid(format!("No change history is kept for {}s", entity)));
+    }
+    state.0.lock().unwrap().entity_history(entity, &id)
+}
+
+/// Compact history older than `olderThanDays` (default 90; also done on
+/// launch).
+#[tauri::command]
+pub fn compact_history(
+    state: tauri::State<'_, StoreState>,
+    older_than_days: Option<u32>,
+) -> StoreResult<CompactionReport> {
+    state
+        .0
+        .lock()
+        .unwrap()
+        .compact_history(older_than_days.unwrap_or(crate::audit::RETENTION_DAYS))
+}
+
+// Flow analytics
+
+/// Default range of `get_cycle_times`.
+const CYCLE_TIME_DAYS: i64 = 90;
+/// Default range of `get_throughput`.
+const THROUGHPUT_WEEKS: u32 = 12;
+
+fn flow(state: &StoreState, project_id: &str) -> StoreResult<Flow> {
+    let store = state.0.lock().unwrap();
+    let entries = store.all_history(EntityKind::Card)?;
+    Flow::new(store.data(), project_id, &entries)
+}
+
+/// Time spent in each of the project's columns, and how long the cards in
+/// each have been there.
+#[tauri::command]
+pub fn get_column_dwell_times(
+    state: tauri::State<'_, StoreState>,
+    project_id: String,
+) -> StoreResult<Vec<ColumnDwell>> {
+    Ok(flow(&state, &project_id)?.dwell_times(crate::mutations::now_millis()))
+}
+
+/// Cards per column at the end of each day, from `from` (default: the first
+/// recorded move) to `to` (default: today).
+#[tauri::command]
+pub fn get_cumulative_flow(
+    state: tauri::State<'_, StoreState>,
+    project_id: String,
+    from: Option<i64>,
+    to: Option<i64>,
+) -> StoreResult<CumulativeFlow> {
+    let to = to.unwrap_or_else(crate::mutations::now_millis);
+    Ok(flow(&state, &project_id)?.cumulative_flow(from, to))
+}
+
+/// Cycle and lead times of the cards completed between `from` and `to`
+/// (default: the last 90 days).
+#[tauri::command]
+pub fn get_cycle_times(
+    state: tauri::State<'_, StoreState>,
+    project_id: String,
+    from: Option<i64>,
+    to: Option<i64>,
+) -> StoreResult<CycleTimes> {
+    let to = to.unwrap_or_else(crate::mutations::now_millis);
+    let from = from.unwrap_or(to - CYCLE_TIME_DAYS * DAY_MS);
+    Ok(flow(&state, &project_id)?.cycle_times(from, to))
+}
+
+/// Cards completed per week over the last `weeks` weeks (default 12),
+/// including this one.
+#[tauri::command]
+pub fn get_throughput(
+    state: tauri::State<'_, StoreState>,
+    project_id: String,
+    weeks: Option<u32>,
+) -> StoreResult<Vec<WeeklyThroughput>> {
+    let weeks = weeks.unwrap_or(THROUGHPUT_WEEKS);
+    Ok(flow(&state, &project_id)?.throughput(weeks, crate::mutations::now_millis()))
+}
+
+// Backups
+
+#[tauri::command]
+pub fn list_backups(backups: tauri::State<'_, BackupState>) -> Vec<BackupInfo> {
+    backups.0.list()
+}
+
+#[tauri::command]
+pub fn create_backup(
+    state: tauri::State<'_, StoreState>,
+    backups: tauri::State<'_, BackupState>,
+) -> StoreResult<BackupInfo> {
+    let store = state.0.lock().unwrap();
+    backups
+        .0
+        .snapshot("manual", &store.contents()?)
+        .map_err(StoreError::io)
+}
+
+/// Replace the current data with a snapshot. The current data is backed up
+/// first, so a restore can itself be undone.
+#[tauri::command]
+pub fn restore_backup(
+    state: tauri::State<'_, StoreState>,
+    backups: tauri::State<'_, BackupState>,
+    name: String,
+) -> StoreResult<AppData> {
+    let data = backups.0.load(&name).map_err(StoreError::invalid)?;
+    let mut store = state.0.lock().unwrap();
+    snapshot_before(&store, &backups, "pre-restore")?;
+    store.replace(data)?;
+    logging::info(&format!("Restored backup {}", name));
+    Ok(store.data().clone())
+}
+
+// Project bundles
+
+/// Write a project with its columns, cards, tasks and notes to a bundle at
+/// `path`, or in `data/exports/` without one. Returns the path written.
+#[tauri::command]
+pub fn export_project(
+    state: tauri::State<'_, StoreState>,
+    project_id: String,
+    path: Option<String>,
+) -> StoreResult<String> {
+    let store = state.0.lock().unwrap();
+    let json = crate::bundle::export(store.data(), &project_id)?;
+    let path = match path {
+        Some(path) => std::path::PathBuf::from(path),
+        None => {
+            let name = &store.data().projects[store.data().project_index(&project_id)?].name;
+            crate::bundle::default_path(store.project_dir(), name)
+        }
+    };
+    drop(store);
+    crate::store::write_atomic(&path, json.as_bytes()).map_err(StoreError::io)?;
+    logging::info(&format!("Exported project {} to {}", project_id, path.display()));
+    Ok(path.to_string_lossy().to_string())
+}
+
+/// Import a project bundle. `mode` is `copy` (the default: new ids, renamed
+/// if the name is taken) or `replace` (replaces the project with the same
+/// id, after a backup).
+#[tauri::command]
+pub fn import_project(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, StoreState>,
+    backups: tauri::State<'_, BackupState>,
+    path: String,
+    mode: Option<ImportMode>,
+) -> StoreResult<ImportReport> {
+    let content = std::fs::read_to_string(&path)
+        .map_err(|e| StoreError::invalid(format!("Failed to read {}: {}", path, e)))?;
+    let bundle = crate::bundle::parse(&content)?;
+    let mode = mode.unwrap_or_default();
+
+    let mut store = state.0.lock().unwrap();
+    if mode == ImportMode::Replace && store.data().projects.iter().any(|p| p.id == bundle.projects[0].id) {
+        snapshot_before(&store, &backups, "pre-import")?;
+    }
+    let report = store.mutate(|d| crate::bundle::import(d, bundle, mode))?;
+    drop(store);
+    logging::info(&format!(
+        "Imported project {} from {}: {} columns, {} cards, {} tasks, {} notes",
+        report.project_name, path, report.columns, report.cards, report.tasks, report.notes
+    ));
+    let _ = app.emit(DATA_CHANGED_EVENT, ());
+    Ok(report)
+}
+
+// Trello and Jira import
+
+/// Import a Trello board or Jira issue export as a new project. `source` is
+/// detected from the file when not given. With `dry_run`, nothing is
+/// written and the report says what an import would create.
+#[tauri::command]
+pub fn import_board(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, StoreState>,
+    path: String,
+    source: Option<Source>,
+    dry_run: Option<bool>,
+) -> StoreResult<BoardImportReport> {
+    let content = std::fs::read_to_string(&path)
+        .map_err(|e| StoreError::invalid(format!("Failed to read {}: {}", path, e)))?;
+    let (source, converted) = crate::board_import::convert(&content, source)?;
+    let dry_run = dry_run.unwrap_or(false);
+
+    let mut store = state.0.lock().unwrap();
+    if dry_run {
+        let mut data = store.data().clone();
+        return crate::board_import::import(&mut data, source, converted, true);
+    }
+    let report = store.mutate(|d| crate::board_import::import(d, source, converted, false))?;
+    drop(store);
+    logging::info(&format!(
+        "Imported {:?} board {} from {}: {} columns, {} cards, {} tasks, {} notes",
+        source,
+        report.import.project_name,
+        path,
+        report.import.columns,
+        report.import.cards,
+        report.import.tasks,
+        report.import.notes
+    ));
+    let _ = app.emit(DATA_CHANGED_EVENT, ());
+    Ok(report)
+}
+
+// Reports
+
+/// Export a project's task list or board as CSV or Markdown, to a file the
+/// user picks in a save dialog. Returns the path written, or `None` if the
+/// dialog was cancelled.
+#[tauri::command]
+pub async fn export_report(
+    app: tauri::AppHandle,
+    project_id: String,
+    kind: ReportKind,
+    format: ReportFormat,
+) -> StoreResult<Option<String>> {
+    use tauri_plugin_dialog::DialogExt;
+
+    let (content, file_name) = {
+        let state = app.state::<StoreState>();
+        let store = state.0.lock().unwrap();
+        let data = store.data();
+        let content = crate::report::render(data, &project_id, kind, format)?;
+        let name = &data.projects[data.project_index(&project_id)?].name;
+        (content, crate::report::file_name(name, kind, format))
+    };
+
+    // The dialog blocks, so keep it off the async runtime's threads.
+    tauri::async_runtime::spawn_blocking(move || {
+        let Some(path) = app
+            .dialog()
+            .file()
+            .set_file_name(&file_name)
+            .add_filter(format.label(), &[format.extension()])
+            .blocking_save_file()
+        else {
+            return Ok(None);
+        };
+        let path = path.into_path().map_err(|e| StoreError::io(e.to_string()))?;
+        crate::store::write_atomic(&path, content.as_bytes()).map_err(StoreError::io)?;
+        logging::info(&format!("Exported {} report to {}", file_name, path.display()));
+        Ok(Some(path.to_string_lossy().to_string()))
+    })
+    .await
+    .map_err(|e| StoreError::io(e.to_string()))?
+}
+
+// CSV task import
+
+/// Read a CSV file and validate it against `mapping`, or against a mapping
+/// suggested from its header when there is none. Nothing is written.
+#[tauri::command]
+pub fn preview_csv_import(path: String, mapping: Option<ColumnMapping>) -> StoreResult<CsvPreview> {
+    let table = crate::task_import::read(&path)?;
+    Ok(crate::task_import::preview(&table, mapping))
+}
+
+/// Create a task in `project_id` for every row of a CSV file. Nothing is
+/// created unless every row is valid, and the tasks are created in one
+/// write, so they are undone together.
+#[tauri::command]
+pub fn import_csv_tasks(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, StoreState>,
+    path: String,
+    project_id: String,
+    mapping: ColumnMapping,
+) -> StoreResult<CsvImportReport> {
+    let table = crate::task_import::read(&path)?;
+    let tasks = crate::task_import::validate(&table, &mapping, &project_id).map_err(|(errors, count)| {
+        let first = &errors[0];
+        StoreError::invalid(format!(
+            "{} invalid value(s); row {}, {}: {}",
+            count, first.row, first.field, first.message
+        ))
+    })?;
+    let task_ids = state.0.lock().unwrap().mutate(|d| {
+        tasks
+            .into_iter()
+            .map(|task| d.create_task(task).map(|t| t.id))
+            .collect::<Result<Vec<_>, _>>()
+    })?;
+    logging::info(&format!("Imported {} tasks into project {} from {}", task_ids.len(), project_id, path));
+    let _ = app.emit(DATA_CHANGED_EVENT, ());
+    Ok(CsvImportReport {
+        project_id,
+        created: task_ids.len(),
+        task_ids,
+    })
+}
+
+// Usage data
+
+/// Load a CSV file for analysis, inferring its column types. A relative
+/// `path` is taken from the project directory, so
+/// `data/saas_product_usage.csv` works as is.
+#[tauri::command]
+pub fn load_dataset(
+    state: tauri::State<'_, StoreState>,
+    datasets: tauri::State<'_, DatasetState>,
+    path: String,
+) -> StoreResult<DatasetSummary> {
+    let path = state.0.lock().unwrap().project_dir().join(path);
+    datasets.0.lock().unwrap().load(&path)
+}
+
+#[tauri::command]
+pub fn list_datasets(datasets: tauri::State<'_, DatasetState>) -> Vec<DatasetSummary> {
+    datasets.0.lock().unwrap().list()
+}
+
+#[tauri::command]
+pub fn close_dataset(datasets: tauri::State<'_, DatasetState>, dataset_id: String) -> bool {
+    datasets.0.lock().unwrap().close(&dataset_id)
+}
+
+#[tauri::command]
+pub fn aggregate_dataset(
+    datasets: tauri::State<'_, DatasetState>,
+    dataset_id: String,
+    query: AggregateQuery,
+) -> StoreResult<AggregateResult> {
+    datasets.0.lock().unwrap().get(&dataset_id)?.aggregate(&query)
+}
+
+/// Run an aggregate and save the result as a Markdown table in a new note
+/// on the project (and card, if given), as evidence for it.
+#[tauri::command]
+pub fn attach_dataset_result(
+    state: tauri::State<'_, StoreState>,
+    datasets: tauri::State<'_, DatasetState>,
+    dataset_id: String,
+    query: AggregateQuery,
+    project_id: String,
+    title: String,
+    card_id: Option<String>,
+) -> StoreResult<Note> {
+    let content = {
+        let workspace = datasets.0.lock().unwrap();
+        let dataset = workspace.get(&dataset_id)?;
+        let result = dataset.aggregate(&query)?;
+        crate::dataset::to_markdown(dataset, &query, &result)
+    };
+    state.0.lock().unwrap().mutate(|d| {
+        d.create_note(NewNote {
+            project_id,
+            title,
+            content: Some(content),
+            card_id,
+            links: None,
+        })
+    })
+}
+
+// HTTP server
+
+/// The store behind the optional HTTP server (`http-server.json`). Writes
+/// made there are announced like a sync's, so the webview reloads.
+pub struct HttpApi(pub tauri::AppHandle);
+
+impl crate::http_server::Api for HttpApi {
+    fn get_data(&self) -> Result<AppData, String> {
+        Ok(self.0.state::<StoreState>().0.lock().unwrap().data().clone())
+    }
+
+    fn put_data(&self, data: AppData) -> Result<(), String> {
+        replace_data(&self.0.state::<StoreState>(), &self.0.state::<BackupState>(), data)
+            .map_err(|e| e.to_string())?;
+        let _ = self.0.emit(DATA_CHANGED_EVENT, ());
+        Ok(())
+    }
+
+    fn open_url(&self, url: &str) -> Result<(), String> {
+        crate::open_in_browser(url)
+    }
+}
+
+// Integrity
+
+/// Check the data for dangling references, duplicate ids and bad `order`
+/// values. With `repair`, fix them after taking a backup. Also run by the
+/// launch pipeline's `validate-data` stage.
+pub fn validate(store: &StoreState, backups: &BackupState, repair: bool) -> StoreResult<IntegrityReport> {
+    let mut store = store.0.lock().unwrap();
+    let report = integrity::check(store.data());
+    if !repair || report.is_clean() {
+        return Ok(report);
+    }
+    snapshot_before(&store, backups, "pre-repair")?;
+    let report = store.mutate(|d| Ok(integrity::repair(d)))?;
+    report.log();
+    Ok(report)
+}
+
+#[tauri::command]
+pub fn validate_data(
+    state: tauri::State<'_, StoreState>,
+    backups: tauri::State<'_, BackupState>,
+    repair: Option<bool>,
+) -> StoreResult<IntegrityReport> {
+    validate(&state, &backups, repair.unwrap_or(false))
+}
+
+// Sync
+
+/// Sync with the remote and make the merged data current. Also run by the
+/// launch pipeline and on `intervalMinutes`.
+pub fn sync_data(
+    app: &tauri::AppHandle,
+    state: &StoreState,
+    backups: &BackupState,
+    sync: &SyncState,
+) -> StoreResult<SyncReport> {
+    let Some(git) = &sync.git else {
+        return Err(StoreError::invalid(format!(
+            "Sync is not configured. Add {} to the project directory.",
+            crate::sync::CONFIG_FILE
+        )));
+    };
+    let git = git.lock().unwrap();
+
+    // Don't hold the store while talking to the remote.
+    let local = state.0.lock().unwrap().data().clone();
+    let outcome = match git.sync(&local) {
+        Ok(outcome) => outcome,
+        Err(e) => {
+            logging::warn(&format!("Sync failed: {}", e));
+            *sync.last.lock().unwrap() = Some(SyncReport::failed(e.clone()));
+            return Err(StoreError::io(e));
+        }
+    };
+
+    let mut store = state.0.lock().unwrap();
+    // Edits made while syncing are merged in rather than overwritten; they go
+    // out with the next sync.
+    let next = if store.data() == &local {
+        outcome.data
+    } else {
+        crate::merge::merge(&local, store.data(), &outcome.data).data
+    };
+    let changed = &next != store.data();
+    if changed {
+        snapshot_before(&store, backups, "pre-sync")?;
+        store.apply_merged(next)?;
+    }
+    drop(store);
+
+    let report = SyncReport {
+        finished_at: crate::mutations::now_millis(),
+        pulled: outcome.pulled,
+        pushed: outcome.pushed,
+        changed,
+        commit: outcome.commit,
+        conflicts: outcome.conflicts,
+        error: outcome.push_error,
+    };
+    if changed {
+        let _ = app.emit(DATA_CHANGED_EVENT, ());
+    }
+    *sync.last.lock().unwrap() = Some(report.clone());
+    Ok(report)
+}
+
+#[tauri::command]
+pub async fn sync_now(app: tauri::AppHandle) -> StoreResult<SyncReport> {
+    tauri::async_runtime::spawn_blocking(move || {
+        sync_data(
+            &app,
+            &app.state::<StoreState>(),
+            &app.state::<BackupState>(),
+            &app.state::<SyncState>(),
+        )
+    })
+    .await
+    .map_err(|e| StoreError::io(e.to_string()))?
+}
+
+#[tauri::command]
+pub fn get_sync_status(sync: tauri::State<'_, SyncState>) -> Option<SyncReport> {
+    sync.last.lock().unwrap().clone()
+}
+
+/// Apply changes other devices wrote to the sync folder. Called on launch
+/// and whenever a log in the folder changes.
+pub fn replay_folder_sync(app: &tauri::AppHandle, state: &StoreState, backups: &BackupState) -> StoreResult<bool> {
+    let mut store = state.0.lock().unwrap();
+    let Some(merged) = store.poll_folder_sync()? else {
+        return Ok(false);
+    };
+    if drops_records(store.data(), &merged) {
+        snapshot_before(&store, backups, "pre-folder-sync")?;
+    }
+    store.apply_synced(merged)?;
+    drop(store);
+    let _ = app.emit(DATA_CHANGED_EVENT, ());
+    Ok(true)
+}
+
+// Projects
+
+#[tauri::command]
+pub fn create_project(state: tauri::State<'_, StoreState>, project: NewProject) -> StoreResult<Project> {
+    state.0.lock().unwrap().mutate(|d| d.create_project(project))
+}
+
+#[tauri::command]
+pub fn update_project(
+    state: tauri::State<'_, StoreState>,
+    id: String,
+    update: ProjectUpdate,
+) -> StoreResult<Project> {
+    state.0.lock().unwrap().mutate(|d| d.update_project(&id, update))
+}
+
+#[tauri::command]
+pub fn delete_project(
+    state: tauri::State<'_, StoreState>,
+    backups: tauri::State<'_, BackupState>,
+    id: String,
+) -> StoreResult<()> {
+    let mut store = state.0.lock().unwrap();
+    snapshot_before(&store, &backups, "delete-project")?;
+    store.mutate(|d| d.delete_project(&id))
+}
+
+// Columns
+
+#[tauri::command]
+pub fn create_column(
+    state: tauri::State<'_, StoreState>,
+    project_id: String,
+    title: String,
+) -> StoreResult<KanbanColumn> {
+    state.0.lock().unwrap().mutate(|d| d.create_column(&project_id, title))
+}
+
+#[tauri::command]
+pub fn rename_column(
+    state: tauri::State<'_, StoreState>,
+    id: String,
+    title: String,
+) -> StoreResult<KanbanColumn> {
+    state.0.lock().unwrap().mutate(|d| d.rename_column(&id, title))
+}
+
+#[tauri::command]
+pub fn move_column(
+    state: tauri::State<'_, StoreState>,
+    id: String,
+    index: usize,
+) -> StoreResult<Vec<KanbanColumn>> {
+    state.0.lock().unwrap().mutate(|d| d.move_column(&id, index))
+}
+
+#[tauri::command]
+pub fn delete_column(
+    state: tauri::State<'_, StoreState>,
+    backups: tauri::State<'_, BackupState>,
+    id: String,
+) -> StoreResult<()> {
+    let mut store = state.0.lock().unwrap();
+    snapshot_before(&store, &backups, "delete-column")?;
+    store.mutate(|d| d.delete_column(&id))
+}
+
+// Cards
+
+#[tauri::command]
+pub fn create_card(state: tauri::State<'_, StoreState>, card: NewCard) -> StoreResult<KanbanCard> {
+    state.0.lock().unwrap().mutate(|d| d.create_card(card))
+}
+
+#[tauri::command]
+pub fn update_card(
+    state: tauri::State<'_, StoreState>,
+    id: String,
+    update: CardUpdate,
+) -> StoreResult<KanbanCard> {
+    state.0.lock().unwrap().mutate(|d| d.update_card(&id, update))
+}
+
+#[tauri::command]
+pub fn move_card(
+    state: tauri::State<'_, StoreState>,
+    id: String,
+    column_id: String,
+    index: usize,
+) -> StoreResult<KanbanCard> {
+    state.0.lock().unwrap().mutate(|d| d.move_card(&id, &column_id, index))
+}
+
+#[tauri::command]
+pub fn archive_card(state: tauri::State<'_, StoreState>, id: String) -> StoreResult<KanbanCard> {
+    state
+        .0
+        .lock()
+        .unwrap()
+        .mutate(|d| d.archive_card(&id, ArchiveReason::Archived))
+}
+
+#[tauri::command]
+pub fn restore_card(state: tauri::State<'_, StoreState>, id: String) -> StoreResult<KanbanCard> {
+    state.0.lock().unwrap().mutate(|d| d.restore_card(&id))
+}
+
+/// Soft delete — the card moves to the archive with reason `deleted`.
+#[tauri::command]
+pub fn delete_card(state: tauri::State<'_, StoreState>, id: String) -> StoreResult<KanbanCard> {
+    state
+        .0
+        .lock()
+        .unwrap()
+        .mutate(|d| d.archive_card(&id, ArchiveReason::Deleted))
+}
+
+#[tauri::command]
+pub fn purge_card(
+    state: tauri::State<'_, StoreState>,
+    backups: tauri::State<'_, BackupState>,
+    id: String,
+) -> StoreResult<()> {
+    let mut store = state.0.lock().unwrap();
+    snapshot_before(&store, &backups, "purge-card")?;
+    store.mutate(|d| d.purge_card(&id))
+}
+
+// Tasks
+
+#[tauri::command]
+pub fn create_task(state: tauri::State<'_, StoreState>, task: NewTask) -> StoreResult<Task> {
+    state.0.lock().unwrap().mutate(|d| d.create_task(task))
+}
+
+#[tauri::command]
+pub fn update_task(
+    state: tauri::State<'_, StoreState>,
+    id: String,
+    update: TaskUpdate,
+) -> StoreResult<Task> {
+    state.0.lock().unwrap().mutate(|d| d.update_task(&id, update))
+}
+
+#[tauri::command]
+pub fn move_task(
+    state: tauri::State<'_, StoreState>,
+    id: String,
+    index: usize,
+) -> StoreResult<Vec<Task>> {
+    state.0.lock().unwrap().mutate(|d| d.move_task(&id, index))
+}
+
+#[tauri::command]
+pub fn delete_task(
+    state: tauri::State<'_, StoreState>,
+    backups: tauri::State<'_, BackupState>,
+    id: String,
+) -> StoreResult<()> {
+    let mut store = state.0.lock().unwrap();
+    snapshot_before(&store, &backups, "delete-task")?;
+    store.mutate(|d| d.delete_task(&id))
+}
+
+// Notes
+
+#[tauri::command]
+pub fn create_note(state: tauri::State<'_, StoreState>, note: NewNote) -> StoreResult<Note> {
+    state.0.lock().unwrap().mutate(|d| d.create_note(note))
+}
+
+#[tauri::command]
+pub fn update_note(
+    state: tauri::State<'_, StoreState>,
+    id: String,
+    update: NoteUpdate,
+) -> StoreResult<Note> {
+    state.0.lock().unwrap().mutate(|d| d.update_note(&id, update))
+}
+
+#[tauri::command]
+pub fn delete_note(
+    state: tauri::State<'_, StoreState>,
+    backups: tauri::State<'_, BackupState>,
+    id: String,
+) -> StoreResult<()> {
+    let mut store = state.0.lock().unwrap();
+    snapshot_before(&store, &backups, "delete-note")?;
+    store.mutate(|d| d.delete_note(&id))
+}
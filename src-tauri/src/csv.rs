@@ -0,0 +1,141 @@
+//! Minimal RFC 4180 CSV reading and writing: quoted fields with embedded
+//! delimiters, quotes and newlines, CRLF or LF line ends, and a UTF-8 BOM as
+//! Excel writes it. Files exported from European spreadsheets often use `;`
+//! or tabs instead of commas, so the delimiter is taken from the header line.
+
+/// Parsed rows, each as its fields. Blank lines are skipped.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Table {
+    pub delimiter: char,
+    pub rows: Vec<Vec<String>>,
+}
+
+/// The header line's most common candidate delimiter (outside quotes), or a
+/// comma.
+fn detect_delimiter(text: &str) -> char {
+    let mut counts = [(',', 0), (';', 0), ('\t', 0)];
+    let mut quoted = false;
+    for c in text.chars() {
+        match c {
+            '"' => quoted = !quoted,
+            '\n' | '\r' if !quoted => break,
+            _ if !quoted => {
+                if let Some((_, n)) = counts.iter_mut().find(|(d, _)| *d == c) {
+                    *n += 1;
+                }
+            }
+            _ => {}
+        }
+    }
+    counts
+        .iter()
+        .filter(|(_, n)| *n > 0)
+        .max_by_key(|(_, n)| *n)
+        .map_or(',', |(d, _)| *d)
+}
+
+pub fn parse(text: &str) -> Result<Table, String> {
+    let text = text.strip_prefix('\u{feff}').unwrap_or(text);
+    let delimiter = detect_delimiter(text);
+
+    let mut rows = Vec::new();
+    let mut row: Vec<String> = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut line = 1;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if quoted {
+            match c {
+                '"' if chars.peek() == Some(&'"') => {
+                    chars.next();
+                    field.push('"');
+                }
+                '"' => quoted = false,
+                '\n' => {
+                    line += 1;
+                    field.push(c);
+                }
+                _ => field.push(c),
+            }
+            continue;
+        }
+        match c {
+            '"' if field.is_empty() => quoted = true,
+            c if c == delimiter => row.push(std::mem::take(&mut field)),
+            '\r' if chars.peek() == Some(&'\n') => {}
+            '\n' | '\r' => {
+                line += 1;
+                row.push(std::mem::take(&mut field));
+                let row = std::mem::take(&mut row);
+                if !(row.len() == 1 && row[0].is_empty()) {
+                    rows.push(row);
+                }
+            }
+            _ => field.push(c),
+        }
+    }
+    if quoted {
+        return Err(format!("Unterminated quoted field at line {}", line));
+    }
+    row.push(field);
+    if !(row.len() == 1 && row[0].is_empty()) {
+        rows.push(row);
+    }
+    Ok(Table { delimiter, rows })
+}
+
+/// One CSV line, with its newline. Fields are quoted only when they need to
+/// be.
+pub fn row(fields: &[&str]) -> String {
+    let mut row = fields
+        .iter()
+        .map(|f| {
+            if f.contains([',', '"', '\n', '\r']) {
+                format!("\"{}\"", f.replace('"', "\"\""))
+            } else {
+                f.to_string()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(",");
+    row.push('\n');
+    row
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_quoted_fields() {
+        let table = parse("\u{feff}a,b,c\r\n1,\"x, \"\"y\"\"\nz\",3\r\n\r\n4,,\n").unwrap();
+        assert_eq!(table.delimiter, ',');
+        assert_eq!(
+            table.rows,
+            vec![
+                vec!["a", "b", "c"],
+                vec!["1", "x, \"y\"\nz", "3"],
+                vec!["4", "", ""],
+            ]
+        );
+    }
+
+    #[test]
+    fn detects_semicolons() {
+        let table = parse("title;due\n\"a;b\";2026-01-14").unwrap();
+        assert_eq!(table.delimiter, ';');
+        assert_eq!(table.rows[1], ["a;b", "2026-01-14"]);
+    }
+
+    #[test]
+    fn round_trips_written_rows() {
+        let fields = ["plain", "with, comma", "with \"quotes\"", "two\nlines"];
+        assert_eq!(parse(&row(&fields)).unwrap().rows, vec![fields.to_vec()]);
+    }
+
+    #[test]
+    fn rejects_unterminated_quotes() {
+        assert!(parse("a\n\"open").is_err());
+    }
+}
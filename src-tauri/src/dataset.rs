@@ -0,0 +1,853 @@
+//! Tabular data loaded from CSV files (like `data/saas_product_usage.csv`)
+//! for usage analysis: each column's type is inferred on load, and grouped
+//! aggregates are computed on request. Results can be rendered as a
+//! Markdown table to attach to a project as a note.
+//!
+//! A column is a date if every non-empty value is `YYYY-MM-DD` (optionally
+//! with a time, which is ignored), numeric if every non-empty value is a
+//! number, and categorical otherwise. Datasets are held in memory for the
+//! session and are never written back.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use crate::csv::Table;
+use crate::error::StoreError;
+
+const DAY_MS: i64 = 86_400_000;
+/// Most common values listed per categorical column in a summary.
+const TOP_CATEGORIES: usize = 20;
+/// Groups returned by one aggregate, to keep the webview responsive.
+const MAX_GROUPS: usize = 5000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ColumnKind {
+    Date,
+    Numeric,
+    Categorical,
+}
+
+/// A column's values, typed. Dates are days since the epoch.
+#[derive(Debug, Clone, PartialEq)]
+enum Values {
+    Date(Vec<Option<i64>>),
+    Numeric(Vec<Option<f64>>),
+    Categorical(Vec<Option<String>>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Column {
+    name: String,
+    values: Values,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Dataset {
+    pub id: String,
+    pub path: PathBuf,
+    rows: usize,
+    columns: Vec<Column>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ColumnInfo {
+    pub name: String,
+    pub kind: ColumnKind,
+    pub missing: usize,
+    pub distinct: usize,
+    /// Dates as `YYYY-MM-DD`; empty for categorical columns.
+    pub min: Option<String>,
+    pub max: Option<String>,
+    pub mean: Option<f64>,
+    /// The most common values of a categorical column, with their counts.
+    pub categories: Vec<(String, usize)>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DatasetSummary {
+    pub id: String,
+    pub name: String,
+    pub path: String,
+    pub rows: usize,
+    pub columns: Vec<ColumnInfo>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Bucket {
+    Day,
+    /// Weeks start on Monday.
+    Week,
+    Month,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GroupBy {
+    pub column: String,
+    /// For date columns; days when not given.
+    #[serde(default)]
+    pub bucket: Option<Bucket>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Op {
+    /// Rows in the group; takes no column.
+    Count,
+    CountDistinct,
+    Sum,
+    Mean,
+    Median,
+    Min,
+    Max,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Metric {
+    pub op: Op,
+    #[serde(default)]
+    pub column: Option<String>,
+}
+
+/// Keeps rows whose value is one of `values`, and/or between `from` and
+/// `to` inclusive (dates or numbers).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Filter {
+    pub column: String,
+    #[serde(default)]
+    pub values: Vec<String>,
+    #[serde(default)]
+    pub from: Option<String>,
+    #[serde(default)]
+    pub to: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AggregateQuery {
+    #[serde(default)]
+    pub group_by: Vec<GroupBy>,
+    pub metrics: Vec<Metric>,
+    #[serde(default)]
+    pub filters: Vec<Filter>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AggregateRow {
+    pub keys: Vec<String>,
+    /// One per metric; `None` when the group has no values to aggregate.
+    pub values: Vec<Option<f64>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AggregateResult {
+    pub group_by: Vec<String>,
+    pub metrics: Vec<String>,
+    /// Sorted by key.
+    pub rows: Vec<AggregateRow>,
+    /// Rows left after the filters.
+    pub matched_rows: usize,
+    pub total_rows: usize,
+}
+
+/// The datasets loaded this session, by id.
+#[derive(Default)]
+pub struct Workspace {
+    datasets: HashMap<String, Dataset>,
+}
+
+impl Workspace {
+    /// Load (or reload) the CSV at `path`. A file that is already loaded
+    /// keeps its id.
+    pub fn load(&mut self, path: &Path) -> Result<DatasetSummary, StoreError> {
+        let text = std::fs::read_to_string(path).map_err(|e| {
+            StoreError::invalid(format!("Failed to read {}: {}", path.display(), e))
+        })?;
+        let table = crate::csv::parse(&text).map_err(StoreError::invalid)?;
+        let id = self
+            .datasets
+            .values()
+            .find(|d| d.path == path)
+            .map_or_else(crate::mutations::generate_id, |d| d.id.clone());
+        let dataset = Dataset::from_table(id.clone(), path.to_path_buf(), table)?;
+        let summary = dataset.summary();
+        self.datasets.insert(id, dataset);
+        Ok(summary)
+    }
+
+    pub fn get(&self, id: &str) -> Result<&Dataset, StoreError> {
+        self.datasets
+            .get(id)
+            .ok_or_else(|| StoreError::invalid(format!("No dataset {} is loaded", id)))
+    }
+
+    pub fn list(&self) -> Vec<DatasetSummary> {
+        let mut list: Vec<DatasetSummary> = self.datasets.values().map(Dataset::summary).collect();
+        list.sort_by(|a, b| a.path.cmp(&b.path));
+        list
+    }
+
+    pub fn close(&mut self, id: &str) -> bool {
+        self.datasets.remove(id).is_some()
+    }
+}
+
+impl Dataset {
+    pub fn from_table(id: String, path: PathBuf, table: Table) -> Result<Dataset, StoreError> {
+        let mut rows = table.rows.into_iter();
+        let headers = rows
+            .next()
+            .ok_or_else(|| StoreError::invalid(format!("{} is empty", path.display())))?;
+        let rows: Vec<Vec<String>> = rows.collect();
+        let mut seen = HashSet::new();
+        for header in &headers {
+            if !seen.insert(header.trim()) {
+                return Err(StoreError::invalid(format!(
+                    "Column \"{}\" appears twice",
+                    header.trim()
+                )));
+            }
+        }
+        let columns = headers
+            .iter()
+            .enumerate()
+            .map(|(i, name)| {
+                let cells: Vec<&str> = rows
+                    .iter()
+                    .map(|r| r.get(i).map_or("", |v| v.trim()))
+                    .collect();
+                Column {
+                    name: name.trim().to_string(),
+                    values: infer(&cells),
+                }
+            })
+            .collect();
+        Ok(Dataset {
+            id,
+            path,
+            rows: rows.len(),
+            columns,
+        })
+    }
+
+    pub fn name(&self) -> String {
+        self.path.file_name().map_or_else(
+            || self.path.display().to_string(),
+            |n| n.to_string_lossy().to_string(),
+        )
+    }
+
+    pub fn summary(&self) -> DatasetSummary {
+        DatasetSummary {
+            id: self.id.clone(),
+            name: self.name(),
+            path: self.path.to_string_lossy().to_string(),
+            rows: self.rows,
+            columns: self.columns.iter().map(Column::info).collect(),
+        }
+    }
+
+    fn column(&self, name: &str) -> Result<&Column, StoreError> {
+        self.columns.iter().find(|c| c.name == name).ok_or_else(|| {
+            StoreError::invalid(format!("No column \"{}\" in {}", name, self.name()))
+        })
+    }
+
+    pub fn aggregate(&self, query: &AggregateQuery) -> Result<AggregateResult, StoreError> {
+        if query.metrics.is_empty() {
+            return Err(StoreError::invalid("Ask for at least one metric"));
+        }
+        let mut keep = vec![true; self.rows];
+        for filter in &query.filters {
+            self.column(&filter.column)?.filter(filter, &mut keep)?;
+        }
+
+        let groups: Vec<(&Column, Option<Bucket>)> = query
+            .group_by
+            .iter()
+            .map(|g| {
+                let column = self.column(&g.column)?;
+                if g.bucket.is_some() && !matches!(column.values, Values::Date(_)) {
+                    return Err(StoreError::invalid(format!(
+                        "\"{}\" is not a date column and cannot be bucketed",
+                        g.column
+                    )));
+                }
+                Ok((column, g.bucket))
+            })
+            .collect::<Result<_, StoreError>>()?;
+        let metrics: Vec<(Op, Option<&Column>)> = query
+            .metrics
+            .iter()
+            .map(|m| {
+                let column = m.column.as_deref().map(|c| self.column(c)).transpose()?;
+                match (m.op, column) {
+                    (Op::Count, _) | (Op::CountDistinct, Some(_)) => Ok((m.op, column)),
+                    (_, Some(c)) if matches!(c.values, Values::Numeric(_)) => Ok((m.op, column)),
+                    (_, Some(c)) => Err(StoreError::invalid(format!(
+                        "{} needs a numeric column; \"{}\" is not",
+                        label(m),
+                        c.name
+                    ))),
+                    (_, None) => Err(StoreError::invalid(format!("{} needs a column", label(m)))),
+                }
+            })
+            .collect::<Result<_, StoreError>>()?;
+
+        // Row indexes by group key. Keys sort as text, and date keys are
+        // ISO dates, so the groups come out in date order.
+        let mut grouped: BTreeMap<Vec<String>, Vec<usize>> = BTreeMap::new();
+        for row in (0..self.rows).filter(|r| keep[*r]) {
+            let key = groups
+                .iter()
+                .map(|(c, bucket)| c.key(row, *bucket))
+                .collect();
+            grouped.entry(key).or_default().push(row);
+        }
+        if grouped.len() > MAX_GROUPS {
+            return Err(StoreError::invalid(format!(
+                "The grouping gives {} groups; at most {} can be shown. Group by fewer or coarser columns.",
+                grouped.len(),
+                MAX_GROUPS
+            )));
+        }
+
+        Ok(AggregateResult {
+            group_by: query
+                .group_by
+                .iter()
+                .map(|g| match g.bucket {
+                    Some(bucket) => format!("{} ({})", g.column, bucket_name(bucket)),
+                    None => g.column.clone(),
+                })
+                .collect(),
+            metrics: query.metrics.iter().map(label).collect(),
+            matched_rows: keep.iter().filter(|k| **k).count(),
+            total_rows: self.rows,
+            rows: grouped
+                .into_iter()
+                .map(|(keys, rows)| AggregateRow {
+                    keys,
+                    values: metrics
+                        .iter()
+                        .map(|(op, column)| compute(*op, *column, &rows))
+                        .collect(),
+                })
+                .collect(),
+        })
+    }
+}
+
+impl Column {
+    fn info(&self) -> ColumnInfo {
+        let (kind, missing, distinct) = match &self.values {
+            Values::Date(v) => (
+                ColumnKind::Date,
+                count_missing(v),
+                distinct(v.iter().flatten()),
+            ),
+            Values::Numeric(v) => (
+                ColumnKind::Numeric,
+                count_missing(v),
+                distinct(v.iter().flatten().map(|x| x.to_bits())),
+            ),
+            Values::Categorical(v) => (
+                ColumnKind::Categorical,
+                count_missing(v),
+                distinct(v.iter().flatten()),
+            ),
+        };
+        let mut info = ColumnInfo {
+            name: self.name.clone(),
+            kind,
+            missing,
+            distinct,
+            min: None,
+            max: None,
+            mean: None,
+            categories: Vec::new(),
+        };
+        match &self.values {
+            Values::Date(v) => {
+                info.min = v.iter().flatten().min().map(|d| format_day(*d));
+                info.max = v.iter().flatten().max().map(|d| format_day(*d));
+            }
+            Values::Numeric(v) => {
+                let present: Vec<f64> = v.iter().flatten().copied().collect();
+                info.min = present
+                    .iter()
+                    .copied()
+                    .reduce(f64::min)
+                    .map(|x| x.to_string());
+                info.max = present
+                    .iter()
+                    .copied()
+                    .reduce(f64::max)
+                    .map(|x| x.to_string());
+                info.mean = mean(&present);
+            }
+            Values::Categorical(v) => {
+                let mut counts: HashMap<&str, usize> = HashMap::new();
+                for value in v.iter().flatten() {
+                    *counts.entry(value).or_default() += 1;
+                }
+                let mut counts: Vec<(String, usize)> = counts
+                    .into_iter()
+                    .map(|(k, n)| (k.to_string(), n))
+                    .collect();
+                counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+                counts.truncate(TOP_CATEGORIES);
+                info.categories = counts;
+            }
+        }
+        info
+    }
+
+    fn key(&self, row: usize, bucket: Option<Bucket>) -> String {
+        match &self.values {
+            Values::Date(v) => {
+                v[row].map_or_else(String::new, |day| match bucket.unwrap_or(Bucket::Day) {
+                    Bucket::Day => format_day(day),
+                    // 1970-01-01 was a Thursday.
+                    Bucket::Week => format_day(day - (day + 3).rem_euclid(7)),
+                    Bucket::Month => format_day(day)[..7].to_string(),
+                })
+            }
+            Values::Numeric(v) => v[row].map_or_else(String::new, |x| x.to_string()),
+            Values::Categorical(v) => v[row].clone().unwrap_or_default(),
+        }
+    }
+
+    /// Clear `keep` for rows that don't pass `filter`.
+    fn filter(&self, filter: &Filter, keep: &mut [bool]) -> Result<(), StoreError> {
+        let invalid = |value: &str| {
+            StoreError::invalid(format!(
+                "\"{}\" is not a valid bound for column \"{}\"",
+                value, self.name
+            ))
+        };
+        if !filter.values.is_empty() {
+            let values: HashSet<&str> = filter.values.iter().map(String::as_str).collect();
+            for (row, keep) in keep.iter_mut().enumerate() {
+                *keep &= values.contains(self.key(row, None).as_str());
+            }
+        }
+        match &self.values {
+            Values::Date(v) => {
+                let bound = |b: &Option<String>| -> Result<Option<i64>, StoreError> {
+                    b.as_deref()
+                        .map(|s| parse_day(s).ok_or_else(|| invalid(s)))
+                        .transpose()
+                };
+                let (from, to) = (bound(&filter.from)?, bound(&filter.to)?);
+                for (value, keep) in v.iter().zip(keep.iter_mut()) {
+                    *keep &= in_range(*value, from, to);
+                }
+            }
+            Values::Numeric(v) => {
+                let bound = |b: &Option<String>| -> Result<Option<f64>, StoreError> {
+                    b.as_deref()
+                        .map(|s| parse_number(s).ok_or_else(|| invalid(s)))
+                        .transpose()
+                };
+                let (from, to) = (bound(&filter.from)?, bound(&filter.to)?);
+                for (value, keep) in v.iter().zip(keep.iter_mut()) {
+                    *keep &= in_range(*value, from, to);
+                }
+            }
+            Values::Categorical(_) => {
+                if let Some(bound) = filter.from.as_ref().or(filter.to.as_ref()) {
+                    return Err(invalid(bound));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn infer(cells: &[&str]) -> Values {
+    let present = || cells.iter().filter(|c| !c.is_empty());
+    if present().next().is_none() {
+        return Values::Categorical(vec![None; cells.len()]);
+    }
+    if present().all(|c| parse_day(c).is_some()) {
+        return Values::Date(cells.iter().map(|c| parse_day(c)).collect());
+    }
+    if present().all(|c| parse_number(c).is_some()) {
+        return Values::Numeric(cells.iter().map(|c| parse_number(c)).collect());
+    }
+    Values::Categorical(
+        cells
+            .iter()
+            .map(|c| (!c.is_empty()).then(|| c.to_string()))
+            .collect(),
+    )
+}
+
+fn compute(op: Op, column: Option<&Column>, rows: &[usize]) -> Option<f64> {
+    let numbers = || match column.map(|c| &c.values) {
+        Some(Values::Numeric(v)) => rows.iter().filter_map(|r| v[*r]).collect::<Vec<f64>>(),
+        _ => Vec::new(),
+    };
+    match op {
+        Op::Count => Some(rows.len() as f64),
+        Op::CountDistinct => {
+            let column = column?;
+            let keys: HashSet<String> = rows
+                .iter()
+                .map(|r| column.key(*r, None))
+                .filter(|k| !k.is_empty())
+                .collect();
+            Some(keys.len() as f64)
+        }
+        Op::Sum => Some(numbers().iter().sum()),
+        Op::Mean => mean(&numbers()),
+        Op::Median => {
+            let mut v = numbers();
+            if v.is_empty() {
+                return None;
+            }
+            v.sort_by(f64::total_cmp);
+            let mid = v.len() / 2;
+            Some(if v.len() % 2 == 0 {
+                (v[mid - 1] + v[mid]) / 2.0
+            } else {
+                v[mid]
+            })
+        }
+        Op::Min => numbers().into_iter().reduce(f64::min),
+        Op::Max => numbers().into_iter().reduce(f64::max),
+    }
+}
+
+/// `mean(session_duration_min)`, or `count`.
+fn label(metric: &Metric) -> String {
+    let op = match metric.op {
+        Op::Count => "count",
+        Op::CountDistinct => "distinct",
+        Op::Sum => "sum",
+        Op::Mean => "mean",
+        Op::Median => "median",
+        Op::Min => "min",
+        Op::Max => "max",
+    };
+    match &metric.column {
+        Some(column) if metric.op != Op::Count => format!("{}({})", op, column),
+        _ => op.to_string(),
+    }
+}
+
+fn bucket_name(bucket: Bucket) -> &'static str {
+    match bucket {
+        Bucket::Day => "day",
+        Bucket::Week => "week",
+        Bucket::Month => "month",
+    }
+}
+
+/// The result as a Markdown table, with a line naming its source.
+pub fn to_markdown(dataset: &Dataset, query: &AggregateQuery, result: &AggregateResult) -> String {
+    let cell = |s: &str| {
+        if s.is_empty() {
+            "(empty)".to_string()
+        } else {
+            s.replace('|', "\\|")
+        }
+    };
+    let headers: Vec<String> = result
+        .group_by
+        .iter()
+        .chain(&result.metrics)
+        .map(|h| cell(h))
+        .collect();
+    let mut out = format!("| {} |\n", headers.join(" | "));
+    let align: Vec<&str> = (0..headers.len())
+        .map(|i| {
+            if i < result.group_by.len() {
+                " --- "
+            } else {
+                " ---: "
+            }
+        })
+        .collect();
+    out.push_str(&format!("|{}|\n", align.join("|")));
+    for row in &result.rows {
+        let cells: Vec<String> = row
+            .keys
+            .iter()
+            .map(|k| cell(k))
+            .chain(
+                row.values
+                    .iter()
+                    .map(|v| v.map_or_else(String::new, format_number)),
+            )
+            .collect();
+        out.push_str(&format!("| {} |\n", cells.join(" | ")));
+    }
+    out.push_str(&format!(
+        "\nSource: `{}`, {} of {} rows",
+        dataset.name(),
+        result.matched_rows,
+        result.total_rows
+    ));
+    let filters: Vec<String> = query
+        .filters
+        .iter()
+        .map(|f| {
+            let mut parts = Vec::new();
+            if !f.values.is_empty() {
+                parts.push(format!("in [{}]", f.values.join(", ")));
+            }
+            if let Some(from) = &f.from {
+                parts.push(format!(">= {}", from));
+            }
+            if let Some(to) = &f.to {
+                parts.push(format!("<= {}", to));
+            }
+            format!("{} {}", f.column, parts.join(" and "))
+        })
+        .collect();
+    if !filters.is_empty() {
+        out.push_str(&format!(" where {}", filters.join("; ")));
+    }
+    out.push('\n');
+    out
+}
+
+/// Whole numbers as they are, others to two decimals.
+fn format_number(x: f64) -> String {
+    if x.fract() == 0.0 && x.abs() < 1e15 {
+        format!("{}", x as i64)
+    } else {
+        format!("{:.2}", x)
+    }
+}
+
+fn parse_day(value: &str) -> Option<i64> {
+    let noon = crate::task_import::parse_date(value).ok()??;
+    Some(noon.div_euclid(DAY_MS))
+}
+
+fn format_day(day: i64) -> String {
+    let (y, m, d) = crate::backup::civil_from_days(day);
+    format!("{:04}-{:02}-{:02}", y, m, d)
+}
+
+fn parse_number(value: &str) -> Option<f64> {
+    value.parse::<f64>().ok().filter(|x| x.is_finite())
+}
+
+fn in_range<T: PartialOrd>(value: Option<T>, from: Option<T>, to: Option<T>) -> bool {
+    if from.is_none() && to.is_none() {
+        return true;
+    }
+    match value {
+        Some(v) => !from.is_some_and(|f| v < f) && !to.is_some_and(|t| v > t),
+        None => false,
+    }
+}
+
+fn mean(values: &[f64]) -> Option<f64> {
+    (!values.is_empty()).then(|| values.iter().sum::<f64>() / values.len() as f64)
+}
+
+fn count_missing<T>(values: &[Option<T>]) -> usize {
+    values.iter().filter(|v| v.is_none()).count()
+}
+
+fn distinct<T: std::hash::Hash + Eq>(values: impl Iterator<Item = T>) -> usize {
+    values.collect::<HashSet<T>>().len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dataset() -> Dataset {
+        let text = "date,user_id,plan_type,feature_used,session_duration_min\n\
+                    2024-02-05,U1,Pro,API,10\n\
+                    2024-02-06,U2,Free,API,20\n\
+                    2024-02-11,U1,Pro,Export,30\n\
+                    2024-02-12,U3,Free,API,\n\
+                    2024-02-13,U3,Pro,API,5\n";
+        let table = crate::csv::parse(text).unwrap();
+        Dataset::from_table("d".to_string(), PathBuf::from("usage.csv"), table).unwrap()
+    }
+
+    fn metric(op: Op, column: &str) -> Metric {
+        Metric {
+            op,
+            column: Some(column.to_string()),
+        }
+    }
+
+    fn group(column: &str, bucket: Option<Bucket>) -> GroupBy {
+        GroupBy {
+            column: column.to_string(),
+            bucket,
+        }
+    }
+
+    #[test]
+    fn infers_column_types() {
+        let summary = dataset().summary();
+        let kinds: Vec<ColumnKind> = summary.columns.iter().map(|c| c.kind).collect();
+        assert_eq!(
+            kinds,
+            [
+                ColumnKind::Date,
+                ColumnKind::Categorical,
+                ColumnKind::Categorical,
+                ColumnKind::Categorical,
+                ColumnKind::Numeric
+            ]
+        );
+        let duration = &summary.columns[4];
+        assert_eq!(
+            (
+                duration.missing,
+                duration.min.as_deref(),
+                duration.max.as_deref()
+            ),
+            (1, Some("5"), Some("30"))
+        );
+        assert_eq!(summary.columns[0].min.as_deref(), Some("2024-02-05"));
+        assert_eq!(
+            summary.columns[2].categories,
+            [("Pro".to_string(), 3), ("Free".to_string(), 2)]
+        );
+    }
+
+    #[test]
+    fn groups_by_categories() {
+        let query = AggregateQuery {
+            group_by: vec![group("plan_type", None), group("feature_used", None)],
+            metrics: vec![
+                Metric {
+                    op: Op::Count,
+                    column: None,
+                },
+                metric(Op::CountDistinct, "user_id"),
+            ],
+            filters: Vec::new(),
+        };
+        let result = dataset().aggregate(&query).unwrap();
+        assert_eq!(result.metrics, ["count", "distinct(user_id)"]);
+        let rows: Vec<(Vec<&str>, Vec<Option<f64>>)> = result
+            .rows
+            .iter()
+            .map(|r| {
+                (
+                    r.keys.iter().map(String::as_str).collect(),
+                    r.values.clone(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            rows,
+            [
+                (vec!["Free", "API"], vec![Some(2.0), Some(2.0)]),
+                (vec!["Pro", "API"], vec![Some(2.0), Some(2.0)]),
+                (vec!["Pro", "Export"], vec![Some(1.0), Some(1.0)]),
+            ]
+        );
+    }
+
+    #[test]
+    fn averages_by_week() {
+        let query = AggregateQuery {
+            group_by: vec![group("date", Some(Bucket::Week))],
+            metrics: vec![
+                metric(Op::Mean, "session_duration_min"),
+                metric(Op::Median, "session_duration_min"),
+            ],
+            filters: Vec::new(),
+        };
+        let result = dataset().aggregate(&query).unwrap();
+        assert_eq!(result.group_by, ["date (week)"]);
+        let rows: Vec<(&str, Vec<Option<f64>>)> = result
+            .rows
+            .iter()
+            .map(|r| (r.keys[0].as_str(), r.values.clone()))
+            .collect();
+        // Monday 2024-02-05 to Sunday 2024-02-11; the empty duration is
+        // left out of the second week.
+        assert_eq!(
+            rows,
+            [
+                ("2024-02-05", vec![Some(20.0), Some(20.0)]),
+                ("2024-02-12", vec![Some(5.0), Some(5.0)]),
+            ]
+        );
+    }
+
+    #[test]
+    fn filters_rows() {
+        let query = AggregateQuery {
+            group_by: vec![group("date", Some(Bucket::Month))],
+            metrics: vec![metric(Op::Sum, "session_duration_min")],
+            filters: vec![
+                Filter {
+                    column: "plan_type".to_string(),
+                    values: vec!["Pro".to_string()],
+                    from: None,
+                    to: None,
+                },
+                Filter {
+                    column: "date".to_string(),
+                    values: Vec::new(),
+                    from: Some("2024-02-06".to_string()),
+                    to: None,
+                },
+            ],
+        };
+        let d = dataset();
+        let result = d.aggregate(&query).unwrap();
+        assert_eq!((result.matched_rows, result.total_rows), (2, 5));
+        assert_eq!(result.rows[0].keys, ["2024-02"]);
+        assert_eq!(result.rows[0].values, [Some(35.0)]);
+        assert_eq!(
+            to_markdown(&d, &query, &result),
+            "| date (month) | sum(session_duration_min) |\n\
+             | --- | ---: |\n\
+             | 2024-02 | 35 |\n\
+             \n\
+             Source: `usage.csv`, 2 of 5 rows where plan_type in [Pro]; date >= 2024-02-06\n"
+        );
+    }
+
+    #[test]
+    fn rejects_bad_queries() {
+        let d = dataset();
+        let query = |group_by: Vec<GroupBy>, metrics: Vec<Metric>| AggregateQuery {
+            group_by,
+            metrics,
+            filters: Vec::new(),
+        };
+        assert!(d.aggregate(&query(vec![], vec![])).is_err());
+        assert!(d
+            .aggregate(&query(
+                vec![group("nope", None)],
+                vec![metric(Op::Count, "date")]
+            ))
+            .is_err());
+        assert!(d
+            .aggregate(&query(
+                vec![group("plan_type", Some(Bucket::Week))],
+                vec![metric(Op::Count, "date")]
+            ))
+            .is_err());
+        assert!(d
+            .aggregate(&query(vec![], vec![metric(Op::Mean, "plan_type")]))
+            .is_err());
+    }
+}
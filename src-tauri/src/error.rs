@@ -0,0 +1,90 @@
+//! Typed errors returned by data store commands. Serialized to the webview as
+//! `{ "kind": "notFound", "entity": "card", "id": "..." }` and friends.
+
+use serde::Serialize;
+
+use crate::models::EntityKind;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum StoreError {
+    /// The entity being read or changed does not exist.
+    #[serde(rename_all = "camelCase")]
+    NotFound { entity: EntityKind, id: String },
+    /// A field points at an entity that does not exist, or at one in a
+    /// different project.
+    #[serde(rename_all = "camelCase")]
+    InvalidReference {
+        entity: EntityKind,
+        field: String,
+        target: EntityKind,
+        target_id: String,
+    },
+    /// The input is well-formed JSON but not a valid change.
+    Invalid { message: String },
+    /// Reading or writing the data file failed.
+    Io { message: String },
+}
+
+impl StoreError {
+    pub fn not_found(entity: EntityKind, id: &str) -> Self {
+        StoreError::NotFound {
+            entity,
+            id: id.to_string(),
+        }
+    }
+
+    pub fn invalid_reference(
+        entity: EntityKind,
+        field: &str,
+        target: EntityKind,
+        target_id: &str,
+    ) -> Self {
+        StoreError::InvalidReference {
+            entity,
+            field: field.to_string(),
+            target,
+            target_id: target_id.to_string(),
+        }
+    }
+
+    pub fn invalid(message: impl Into<String>) -> Self {
+        StoreError::Invalid {
+            message: message.into(),
+        }
+    }
+
+    pub fn io(message: impl Into<String>) -> Self {
+        StoreError::Io {
+            message: message.into(),
+        }
+    }
+}
+
+impl std::fmt::Display for StoreError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StoreError::NotFound { entity, id } => write!(f, "{} {} not found", entity, id),
+            StoreError::InvalidReference {
+                entity,
+                field,
+                target,
+                target_id,
+            } => write!(
+                f,
+                "{}.{} refers to unknown {} {}",
+                entity, field, target, target_id
+            ),
+            StoreError::Invalid { message } => write!(f, "{}", message),
+            StoreError::Io { message } => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for StoreError {}
+
+impl From<String> for StoreError {
+    fn from(message: String) -> Self {
+        StoreError::io(message)
+    }
+}
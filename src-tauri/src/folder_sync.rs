@@ -0,0 +1,555 @@
+//! Sync through a shared folder (iCloud Drive, Dropbox, ...). Two machines
+//! writing the same `app-data.json` through a file-sync service overwrite
+//! each other, so instead each device only ever appends to its own change
+//! log in the folder — `<folder>/<device id>.jsonl`, one changed record per
+//! line — and reads everyone else's.
+//!
+//! The data is the replay of all logs: for each record, the change with the
+//! highest `(at, device, seq)` wins, which gives every device the same
+//! result whatever order the logs arrive in. Records keep the order in which
+//! they were first logged.
+//!
+//! `data/folder-sync-state.json` holds this device's id and how much of each
+//! log the local data already reflects, so changes made while sync was off
+//! (or by the browser version) are logged on the next launch.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use crate::logging;
+use crate::merge::Record;
+use crate::models::{AppData, EntityKind};
+
+pub const CONFIG_FILE: &str = "folder-sync.json";
+const STATE_FILE: &str = "data/folder-sync-state.json";
+const LOG_EXTENSION: &str = "jsonl";
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FolderSyncConfig {
+    /// The shared folder. A leading `~/` is the home directory.
+    pub folder: String,
+}
+
+impl FolderSyncConfig {
+    /// `None` if folder sync isn't configured for this machine.
+    pub fn load(project_dir: &str) -> Option<Self> {
+        let path = Path::new(project_dir).join(CONFIG_FILE);
+        let content = std::fs::read_to_string(&path).ok()?;
+        match serde_json::from_str(&content) {
+            Ok(config) => Some(config),
+            Err(e) => {
+                logging::warn(&format!("Ignoring invalid {}: {}", CONFIG_FILE, e));
+                None
+            }
+        }
+    }
+
+    pub fn folder_path(&self) -> PathBuf {
+        match self.folder.strip_prefix("~/") {
+            Some(rest) => Path::new(&std::env::var("HOME").unwrap_or_default()).join(rest),
+            None => PathBuf::from(&self.folder),
+        }
+    }
+}
+
+/// One line of a change log. `record` is `None` for a delete.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Change {
+    at: i64,
+    device: String,
+    seq: u64,
+    entity: EntityKind,
+    id: String,
+    record: Option<Value>,
+}
+
+/// Total order of changes across devices.
+type Version = (i64, String, u64);
+
+impl Change {
+    fn version(&self) -> Version {
+        (self.at, self.device.clone(), self.seq)
+    }
+}
+
+struct Entry {
+    version: Version,
+    /// Version of the first change seen for the record; orders records.
+    first: Version,
+    record: Option<Value>,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SyncState {
+    device_id: String,
+    /// Bytes of each log (by file name) already applied.
+    offsets: BTreeMap<String, u64>,
+}
+
+pub struct FolderSync {
+    folder: PathBuf,
+    state_path: PathBuf,
+    state: SyncState,
+    seq: u64,
+    entries: HashMap<(EntityKind, String), Entry>,
+    /// No state file yet: this device is joining (or starting) the folder.
+    joining: bool,
+}
+
+impl FolderSync {
+    /// Open the folder and load the changes the local data already reflects.
+    pub fn open(project_dir: &str, config: &FolderSyncConfig) -> Result<Self, String> {
+        let folder = config.folder_path();
+        std::fs::create_dir_all(&folder).map_err(|e| format!("Failed to create {}: {}", folder.display(), e))?;
+        let state_path = Path::new(project_dir).join(STATE_FILE);
+        let mut state: SyncState = std::fs::read_to_string(&state_path)
+            .ok()
+            .and_then(|c| serde_json::from_str(&c).ok())
+            .unwrap_or_default();
+        let joining = state.device_id.is_empty();
+        if joining {
+            state.device_id = crate::mutations::generate_id();
+            logging::info(&format!("Folder sync: this device is {}", state.device_id));
+        }
+
+        let mut sync = FolderSync {
+            folder,
+            state_path,
+            state,
+            seq: 0,
+            entries: HashMap::new(),
+            joining,
+        };
+        for (file, offset) in sync.state.offsets.clone() {
+            let (changes, _) = sync.read_log(&file, 0, Some(offset))?;
+            for change in changes {
+                sync.apply(change);
+            }
+        }
+        Ok(sync)
+    }
+
+    pub fn folder(&self) -> &Path {
+        &self.folder
+    }
+
+    fn own_log(&self) -> String {
+        format!("{}.{}", self.state.device_id, LOG_EXTENSION)
+    }
+
+    /// Complete lines of `file` from byte `from` (up to `to`), and the offset
+    /// just past the last one. A line still being written by the sync
+    /// service is left for next time.
+    fn read_log(&self, file: &str, from: u64, to: Option<u64>) -> Result<(Vec<Change>, u64), String> {
+        let path = self.folder.join(file);
+        let mut f = match std::fs::File::open(&path) {
+            Ok(f) => f,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok((Vec::new(), from)),
+            Err(e) => return Err(format!("Failed to open {}: {}", path.display(), e)),
+        };
+        let mut bytes = Vec::new();
+        f.seek(SeekFrom::Start(from))
+            .and_then(|_| match to {
+                Some(to) => f.take(to.saturating_sub(from)).read_to_end(&mut bytes),
+                None => f.read_to_end(&mut bytes),
+            })
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+
+        let complete = bytes.iter().rposition(|b| *b == b'\n').map_or(0, |i| i + 1);
+        let changes = String::from_utf8_lossy(&bytes[..complete])
+            .lines()
+            .filter(|l| !l.trim().is_empty())
+            .filter_map(|line| match serde_json::from_str::<Change>(line) {
+                Ok(change) => Some(change),
+                Err(e) => {
+                    logging::warn(&format!("Folder sync: skipping bad line in {}: {}", file, e));
+                    None
+                }
+            })
+            .collect();
+        Ok((changes, from + complete as u64))
+    }
+
+    fn apply(&mut self, change: Change) {
+        if change.device == self.state.device_id {
+            self.seq = self.seq.max(change.seq);
+        }
+        let version = change.version();
+        let key = (change.entity, change.id);
+        match self.entries.get_mut(&key) {
+            Some(entry) => {
+                if version > entry.version {
+                    entry.version = version.clone();
+                    entry.record = change.record;
+                }
+                if version < entry.first {
+                    entry.first = version;
+                }
+            }
+            None => {
+                self.entries.insert(
+                    key,
+                    Entry {
+                        version: version.clone(),
+                        first: version,
+                        record: change.record,
+                    },
+                );
+            }
+        }
+    }
+
+    fn save_state(&self) -> Result<(), String> {
+        let json = serde_json::to_string_pretty(&self.state).map_err(|e| e.to_string())?;
+        crate::store::write_atomic(&self.state_path, json.as_bytes())
+    }
+
+    /// Append the records that differ between `prev` and `next` to this
+    /// device's log.
+    pub fn record(&mut self, prev: &AppData, next: &AppData) -> Result<(), String> {
+        let at = crate::mutations::now_millis();
+        let mut changes = Vec::new();
+        diff(&prev.projects, &next.projects, &mut changes);
+        diff(&prev.columns, &next.columns, &mut changes);
+        diff(&prev.cards, &next.cards, &mut changes);
+        diff(&prev.tasks, &next.tasks, &mut changes);
+        diff(&prev.notes, &next.notes, &mut changes);
+        if changes.is_empty() {
+            return Ok(());
+        }
+
+        let mut lines = String::new();
+        let mut logged = Vec::new();
+        for (entity, id, record) in changes {
+            self.seq += 1;
+            let change = Change {
+                at,
+                device: self.state.device_id.clone(),
+                seq: self.seq,
+                entity,
+                id,
+                record,
+            };
+            lines.push_str(&serde_json::to_string(&change).map_err(|e| e.to_string())?);
+            lines.push('\n');
+            logged.push(change);
+        }
+
+        let file = self.own_log();
+        let path = self.folder.join(&file);
+        let mut f = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+        f.write_all(lines.as_bytes())
+            .and_then(|_| f.sync_all())
+            .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+
+        for change in logged {
+            self.apply(change);
+        }
+        *self.state.offsets.entry(file).or_insert(0) += lines.len() as u64;
+        self.save_state()
+    }
+
+    /// Log whatever in `data` the logs don't account for yet: changes made
+    /// while folder sync was off. A device joining a folder other devices
+    /// already use takes their version of every record they have, and only
+    /// contributes the records they don't.
+    ///
+    /// Empty local data against non-empty logs means the data file was lost
+    /// or reset, not that every record was deleted, so the device rejoins
+    /// instead of logging a delete for each one.
+    pub fn catch_up(&mut self, data: &AppData) -> Result<(), String> {
+        if !self.joining {
+            let known = self.materialize(data.current_project_id.clone());
+            if !data.is_empty() || known.is_empty() {
+                return self.record(&known, data);
+            }
+            logging::warn("Folder sync: local data is empty, taking the folder's data instead of deleting it");
+        }
+        self.poll()?;
+        let known = self.materialize(data.current_project_id.clone());
+        let mut joined = known.clone();
+        fn add_missing<T: Record>(into: &mut Vec<T>, from: &[T]) {
+            let have: std::collections::HashSet<String> = into.iter().map(|r| r.id().to_string()).collect();
+            into.extend(from.iter().filter(|r| !have.contains(r.id())).cloned());
+        }
+        add_missing(&mut joined.projects, &data.projects);
+        add_missing(&mut joined.columns, &data.columns);
+        add_missing(&mut joined.cards, &data.cards);
+        add_missing(&mut joined.tasks, &data.tasks);
+        add_missing(&mut joined.notes, &data.notes);
+        self.record(&known, &joined)?;
+        self.joining = false;
+        self.save_state()
+    }
+
+    /// Read what other devices have appended since the last poll. Returns
+    /// whether there was anything.
+    pub fn poll(&mut self) -> Result<bool, String> {
+        let entries = std::fs::read_dir(&self.folder)
+            .map_err(|e| format!("Failed to read {}: {}", self.folder.display(), e))?;
+        let own = self.own_log();
+        let mut files: Vec<String> = entries
+            .filter_map(|e| e.ok())
+            .map(|e| e.file_name().to_string_lossy().to_string())
+            .filter(|n| n.ends_with(&format!(".{}", LOG_EXTENSION)) && *n != own)
+            .collect();
+        files.sort();
+
+        let mut any = false;
+        for file in files {
+            let from = self.state.offsets.get(&file).copied().unwrap_or(0);
+            let (changes, to) = self.read_log(&file, from, None)?;
+            if to == from {
+                continue;
+            }
+            logging::info(&format!("Folder sync: {} change(s) from {}", changes.len(), file));
+            any |= !changes.is_empty();
+            for change in changes {
+                self.apply(change);
+            }
+            self.state.offsets.insert(file, to);
+        }
+        if any {
+            self.save_state()?;
+        }
+        Ok(any)
+    }
+
+    /// The data all logs read so far add up to.
+    pub fn materialize(&self, current_project_id: Option<String>) -> AppData {
+        let mut live: Vec<(&(EntityKind, String), &Entry)> =
+            self.entries.iter().filter(|(_, e)| e.record.is_some()).collect();
+        live.sort_by(|(ak, a), (bk, b)| a.first.cmp(&b.first).then(ak.1.cmp(&bk.1)));
+
+        let mut data = AppData {
+            current_project_id,
+            ..AppData::default()
+        };
+        for ((entity, id), entry) in live {
+            let value = entry.record.clone().unwrap_or(Value::Null);
+            let result = match entity {
+                EntityKind::Project => serde_json::from_value(value).map(|r| data.projects.push(r)),
+                EntityKind::Column => serde_json::from_value(value).map(|r| data.columns.push(r)),
+                EntityKind::Card => serde_json::from_value(value).map(|r| data.cards.push(r)),
+                EntityKind::Task => serde_json::from_value(value).map(|r| data.tasks.push(r)),
+                EntityKind::Note => serde_json::from_value(value).map(|r| data.notes.push(r)),
+            };
+            if let Err(e) = result {
+                logging::warn(&format!("Folder sync: skipping invalid {} {}: {}", entity, id, e));
+            }
+        }
+        // The current project is per device, but may have been deleted on
+        // another one.
+        if let Some(current) = &data.current_project_id {
+            if !data.projects.iter().any(|p| &p.id == current) {
+                data.current_project_id = data.projects.first().map(|p| p.id.clone());
+            }
+        }
+        data
+    }
+}
+
+fn diff<T: Record>(prev: &[T], next: &[T], out: &mut Vec<(EntityKind, String, Option<Value>)>) {
+    let before: HashMap<&str, &T> = prev.iter().map(|r| (r.id(), r)).collect();
+    let after: HashMap<&str, &T> = next.iter().map(|r| (r.id(), r)).collect();
+    for record in next {
+        if before.get(record.id()) != Some(&record) {
+            out.push((T::KIND, record.id().to_string(), serde_json::to_value(record).ok()));
+        }
+    }
+    for record in prev {
+        if !after.contains_key(record.id()) {
+            out.push((T::KIND, record.id().to_string(), None));
+        }
+    }
+}
+
+/// Call `on_change` (from a background thread) whenever a log in the folder
+/// changes. Bursts of events are coalesced. The watcher stops when the
+/// returned value is dropped.
+pub fn watch(folder: &Path, on_change: impl Fn() + Send + 'static) -> Result<notify::RecommendedWatcher, String> {
+    use notify::Watcher;
+
+    let (tx, rx) = std::sync::mpsc::channel();
+    let mut watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+        let relevant = event.is_ok_and(|e| {
+            e.paths
+                .iter()
+                .any(|p| p.extension().is_some_and(|x| x == LOG_EXTENSION))
+        });
+        if relevant {
+            let _ = tx.send(());
+        }
+    })
+    .map_err(|e| format!("Failed to watch {}: {}", folder.display(), e))?;
+    watcher
+        .watch(folder, notify::RecursiveMode::NonRecursive)
+        .map_err(|e| format!("Failed to watch {}: {}", folder.display(), e))?;
+
+    std::thread::spawn(move || {
+        while rx.recv().is_ok() {
+            std::thread::sleep(std::time::Duration::from_millis(500));
+            while rx.try_recv().is_ok() {}
+            on_change();
+        }
+    });
+    Ok(watcher)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::Project;
+    use crate::test_util::TempDir;
+
+    /// A device sharing the folder, with its own project directory.
+    fn device(tmp: &TempDir, name: &str) -> FolderSync {
+        let config = FolderSyncConfig {
+            folder: tmp.join("shared").to_string_lossy().to_string(),
+        };
+        FolderSync::open(&tmp.join(name).to_string_lossy(), &config).unwrap()
+    }
+
+    fn project(id: &str, name: &str) -> Project {
+        Project {
+            id: id.to_string(),
+            name: name.to_string(),
+            description: None,
+            created_at: 0,
+            updated_at: 0,
+        }
+    }
+
+    fn data(projects: &[(&str, &str)]) -> AppData {
+        AppData {
+            projects: projects.iter().map(|(id, name)| project(id, name)).collect(),
+            ..AppData::default()
+        }
+    }
+
+    fn names(data: &AppData) -> Vec<&str> {
+        data.projects.iter().map(|p| p.name.as_str()).collect()
+    }
+
+    #[test]
+    fn replay_order_does_not_matter() {
+        let tmp = TempDir::new("folder-sync-order");
+        let mut laptop = device(&tmp, "laptop");
+        let mut desktop = device(&tmp, "desktop");
+        laptop.record(&AppData::default(), &data(&[("a", "A"), ("b", "B")])).unwrap();
+        desktop.record(&AppData::default(), &data(&[("b", "B desktop"), ("c", "C")])).unwrap();
+        laptop
+            .record(&data(&[("a", "A"), ("b", "B")]), &data(&[("a", "A laptop"), ("b", "B")]))
+            .unwrap();
+
+        let replay = |first: &FolderSync, second: &FolderSync| {
+            let mut reader = device(&tmp, "reader");
+            for log in [first.own_log(), second.own_log()] {
+                let (changes, _) = reader.read_log(&log, 0, None).unwrap();
+                for change in changes {
+                    reader.apply(change);
+                }
+            }
+            reader.materialize(None)
+        };
+        let one = replay(&laptop, &desktop);
+        assert_eq!(one, replay(&desktop, &laptop));
+        assert_eq!(one.projects.len(), 3);
+        assert!(names(&one).contains(&"A laptop"));
+        assert!(names(&one).contains(&"C"));
+    }
+
+    #[test]
+    fn deleted_records_can_come_back() {
+        let tmp = TempDir::new("folder-sync-recreate");
+        let mut laptop = device(&tmp, "laptop");
+        let mut desktop = device(&tmp, "desktop");
+
+        let both = data(&[("a", "A"), ("b", "B")]);
+        laptop.record(&AppData::default(), &both).unwrap();
+        laptop.record(&both, &data(&[("b", "B")])).unwrap();
+        assert!(desktop.poll().unwrap());
+        assert_eq!(names(&desktop.materialize(None)), ["B"]);
+
+        laptop.record(&data(&[("b", "B")]), &data(&[("b", "B"), ("a", "A again")])).unwrap();
+        assert!(desktop.poll().unwrap());
+        // It keeps its place from when it was first logged.
+        assert_eq!(names(&desktop.materialize(None)), ["A again", "B"]);
+        assert!(!desktop.poll().unwrap());
+    }
+
+    #[test]
+    fn partial_lines_wait_for_the_rest() {
+        let tmp = TempDir::new("folder-sync-partial");
+        let mut laptop = device(&tmp, "laptop");
+        let mut desktop = device(&tmp, "desktop");
+        laptop.record(&AppData::default(), &data(&[("a", "A")])).unwrap();
+
+        // The sync service has only delivered part of the second line.
+        let log = tmp.join("shared").join(laptop.own_log());
+        let complete = std::fs::read_to_string(&log).unwrap();
+        let line = serde_json::to_string(&Change {
+            at: 1,
+            device: "other".into(),
+            seq: 1,
+            entity: EntityKind::Project,
+            id: "b".into(),
+            record: serde_json::to_value(project("b", "B")).ok(),
+        })
+        .unwrap();
+        let (head, tail) = line.split_at(line.len() / 2);
+        std::fs::write(&log, format!("{}{}", complete, head)).unwrap();
+
+        assert!(desktop.poll().unwrap());
+        assert_eq!(names(&desktop.materialize(None)), ["A"]);
+
+        std::fs::write(&log, format!("{}{}{}\n", complete, head, tail)).unwrap();
+        assert!(desktop.poll().unwrap());
+        assert_eq!(names(&desktop.materialize(None)), ["B", "A"]);
+    }
+
+    #[test]
+    fn joining_takes_the_folders_records() {
+        let tmp = TempDir::new("folder-sync-join");
+        let mut laptop = device(&tmp, "laptop");
+        laptop.record(&AppData::default(), &data(&[("a", "A laptop")])).unwrap();
+
+        let mut desktop = device(&tmp, "desktop");
+        desktop.catch_up(&data(&[("a", "A desktop"), ("d", "D")])).unwrap();
+        assert_eq!(names(&desktop.materialize(None)), ["A laptop", "D"]);
+        // Only the record the folder didn't have was logged.
+        let logged = std::fs::read_to_string(tmp.join("shared").join(desktop.own_log())).unwrap();
+        assert_eq!(logged.lines().count(), 1);
+
+        assert!(laptop.poll().unwrap());
+        assert_eq!(names(&laptop.materialize(None)), ["A laptop", "D"]);
+    }
+
+    #[test]
+    fn empty_local_data_does_not_delete_everything() {
+        let tmp = TempDir::new("folder-sync-reset");
+        let mut laptop = device(&tmp, "laptop");
+        let both = data(&[("a", "A"), ("b", "B")]);
+        laptop.record(&AppData::default(), &both).unwrap();
+        drop(laptop);
+
+        // Reopened with the data file gone.
+        let mut laptop = device(&tmp, "laptop");
+        laptop.catch_up(&AppData::default()).unwrap();
+        assert_eq!(laptop.materialize(None), both);
+
+        // A partial loss is still logged as deletes.
+        laptop.catch_up(&data(&[("b", "B")])).unwrap();
+        assert_eq!(names(&laptop.materialize(None)), ["B"]);
+    }
+}
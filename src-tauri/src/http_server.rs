@@ -0,0 +1,383 @@
+//! Optional loopback HTTP server with the same contract as the Next.js API
+//! routes in `src/app/api/`, backed by the data store. The browser version
+//! forwards its `/api/data` requests here when `TOTAL_TPM_API_URL` is set,
+//! so the browser and the desktop app write through the same store instead
+//! of both writing `app-data.json`.
+//!
+//! Enabled by an `http-server.json` in the project directory:
+//!
+//! ```json
+//! { "port": 3001 }
+//! ```
+//!
+//! - `GET /api/data` returns the data.
+//! - `PUT /api/data` replaces it and returns `{ "success": true }`.
+//! - `POST /api/open-url` with `{ "url": "https://..." }` opens the URL in
+//!   the default browser and returns `{ "success": true }`.
+//!
+//! Errors are `{ "error": "..." }`. The server only listens on 127.0.0.1,
+//! and rejects requests from web pages on other origins: it sends no CORS
+//! headers, requires a JSON content type for writes (so a page can't send
+//! one without a preflight), and checks `Host` and `Origin`.
+
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::io::Read;
+use std::path::Path;
+
+use crate::logging;
+use crate::models::AppData;
+
+pub const CONFIG_FILE: &str = "http-server.json";
+/// Largest request body accepted.
+const MAX_BODY: u64 = 64 * 1024 * 1024;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HttpServerConfig {
+    #[serde(default = "default_port")]
+    pub port: u16,
+}
+
+fn default_port() -> u16 {
+    3001
+}
+
+impl HttpServerConfig {
+    /// `None` if the server isn't enabled on this machine.
+    pub fn load(project_dir: &str) -> Option<Self> {
+        let path = Path::new(project_dir).join(CONFIG_FILE);
+        let content = std::fs::read_to_string(&path).ok()?;
+        match serde_json::from_str(&content) {
+            Ok(config) => Some(config),
+            Err(e) => {
+                logging::warn(&format!("Ignoring invalid {}: {}", CONFIG_FILE, e));
+                None
+            }
+        }
+    }
+}
+
+/// What the server does with valid requests.
+pub trait Api: Send + 'static {
+    fn get_data(&self) -> Result<AppData, String>;
+    fn put_data(&self, data: AppData) -> Result<(), String>;
+    fn open_url(&self, url: &str) -> Result<(), String>;
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Request {
+    pub method: String,
+    pub path: String,
+    pub host: Option<String>,
+    pub origin: Option<String>,
+    pub content_type: Option<String>,
+    pub body: Vec<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Response {
+    pub status: u16,
+    pub body: Value,
+}
+
+impl Response {
+    fn ok(body: Value) -> Self {
+        Response { status: 200, body }
+    }
+
+    fn error(status: u16, message: &str) -> Self {
+        Response {
+            status,
+            body: json!({ "error": message }),
+        }
+    }
+}
+
+/// Listen on 127.0.0.1:`port` and serve requests on a background thread
+/// for the life of the app.
+pub fn start(config: &HttpServerConfig, api: impl Api) -> Result<(), String> {
+    let port = config.port;
+    let server = tiny_http::Server::http(("127.0.0.1", port))
+        .map_err(|e| format!("Failed to listen on 127.0.0.1:{}: {}", port, e))?;
+    std::thread::Builder::new()
+        .name(String::from("http-server"))
+        .spawn(move || {
+            for mut request in server.incoming_requests() {
+                let header = |name: &'static str| {
+                    request
+                        .headers()
+                        .iter()
+                        .find(|h| h.field.equiv(name))
+                        .map(|h| h.value.as_str().to_string())
+                };
+                let mut req = Request {
+                    method: request.method().as_str().to_string(),
+                    path: request.url().to_string(),
+                    host: header("Host"),
+                    origin: header("Origin"),
+                    content_type: header("Content-Type"),
+                    body: Vec::new(),
+                };
+                let response = match request
+                    .as_reader()
+                    .take(MAX_BODY + 1)
+                    .read_to_end(&mut req.body)
+                {
+                    Ok(_) if req.body.len() as u64 > MAX_BODY => {
+                        Response::error(413, "Request body too large")
+                    }
+                    Ok(_) => handle(&api, &req, port),
+                    Err(e) => Response::error(400, &format!("Failed to read request: {}", e)),
+                };
+                if response.status >= 400 {
+                    logging::warn(&format!(
+                        "HTTP {} {} -> {} {}",
+                        req.method, req.path, response.status, response.body["error"]
+                    ));
+                }
+                let reply = tiny_http::Response::from_string(response.body.to_string())
+                    .with_status_code(response.status)
+                    .with_header(
+                        tiny_http::Header::from_bytes(
+                            &b"Content-Type"[..],
+                            &b"application/json"[..],
+                        )
+                        .expect("static header"),
+                    );
+                if let Err(e) = request.respond(reply) {
+                    logging::warn(&format!("HTTP: failed to respond: {}", e));
+                }
+            }
+        })
+        .map_err(|e| format!("Failed to start HTTP server: {}", e))?;
+    logging::event(logging::Level::Info, "HTTP server listening on 127.0.0.1")
+        .port(port)
+        .emit();
+    Ok(())
+}
+
+/// Route one request.
+pub fn handle(api: &dyn Api, req: &Request, port: u16) -> Response {
+    let local = |authority: &str| {
+        let (host, request_port) = match authority.rsplit_once(':') {
+            Some((host, p)) => (host, p.parse::<u16>().ok()),
+            None => (authority, Some(80)),
+        };
+        matches!(host, "127.0.0.1" | "localhost") && request_port == Some(port)
+    };
+    // A page on another site can make the browser send requests here, and
+    // with DNS rebinding even under a name that resolves to 127.0.0.1.
+    if !req.host.as_deref().is_some_and(local) {
+        return Response::error(403, "Forbidden");
+    }
+    if let Some(origin) = &req.origin {
+        if !origin.strip_prefix("http://").is_some_and(local) {
+            return Response::error(403, "Forbidden");
+        }
+    }
+
+    let path = req.path.split('?').next().unwrap_or_default();
+    match (req.method.as_str(), path) {
+        ("GET", "/api/data") => match api.get_data() {
+            Ok(data) => match serde_json::to_value(data) {
+                Ok(body) => Response::ok(body),
+                Err(e) => Response::error(500, &e.to_string()),
+            },
+            Err(e) => Response::error(500, &e),
+        },
+        ("PUT", "/api/data") => {
+            let data = match json_body(req).and_then(app_data) {
+                Ok(data) => data,
+                Err(e) => return e,
+            };
+            match api.put_data(data) {
+                Ok(()) => Response::ok(json!({ "success": true })),
+                Err(e) => {
+                    logging::error(&format!("HTTP: failed to save data: {}", e));
+                    Response::error(500, "Failed to save data")
+                }
+            }
+        }
+        ("POST", "/api/open-url") => {
+            let body = match json_body(req) {
+                Ok(body) => body,
+                Err(e) => return e,
+            };
+            let Some(url) = body
+                .get("url")
+                .and_then(Value::as_str)
+                .filter(|u| !u.is_empty())
+            else {
+                return Response::error(400, "URL is required");
+            };
+            if !url.starts_with("http://") && !url.starts_with("https://") {
+                return Response::error(400, "Invalid URL scheme");
+            }
+            match api.open_url(url) {
+                Ok(()) => Response::ok(json!({ "success": true })),
+                Err(e) => {
+                    logging::error(&format!("HTTP: {}", e));
+                    Response::error(500, "Failed to open URL")
+                }
+            }
+        }
+        (_, "/api/data" | "/api/open-url") => Response::error(405, "Method not allowed"),
+        _ => Response::error(404, "Not found"),
+    }
+}
+
+fn json_body(req: &Request) -> Result<Value, Response> {
+    let json = req.content_type.as_deref().is_some_and(|t| {
+        t.trim()
+            .to_ascii_lowercase()
+            .starts_with("application/json")
+    });
+    if !json {
+        return Err(Response::error(
+            415,
+            "Content-Type must be application/json",
+        ));
+    }
+    serde_json::from_slice(&req.body)
+        .map_err(|e| Response::error(400, &format!("Invalid JSON: {}", e)))
+}
+
+/// The document as the `PUT` route takes it: missing or non-array
+/// collections are empty, and anything else is ignored.
+fn app_data(body: Value) -> Result<AppData, Response> {
+    let list = |key: &str| match body.get(key) {
+        Some(Value::Array(items)) => Value::Array(items.clone()),
+        _ => Value::Array(Vec::new()),
+    };
+    let doc = json!({
+        "projects": list("projects"),
+        "columns": list("columns"),
+        "cards": list("cards"),
+        "tasks": list("tasks"),
+        "notes": list("notes"),
+        "currentProjectId": body.get("currentProjectId").filter(|v| v.is_string()),
+    });
+    serde_json::from_value(doc).map_err(|e| Response::error(400, &format!("Invalid data: {}", e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    #[derive(Default)]
+    struct Fake {
+        data: Mutex<Option<AppData>>,
+        opened: Mutex<Vec<String>>,
+    }
+
+    impl Api for &'static Fake {
+        fn get_data(&self) -> Result<AppData, String> {
+            Ok(self
+                .data
+                .lock()
+                .unwrap()
+                .clone()
+                .unwrap_or_else(|| serde_json::from_value(json!({})).unwrap()))
+        }
+
+        fn put_data(&self, data: AppData) -> Result<(), String> {
+            *self.data.lock().unwrap() = Some(data);
+            Ok(())
+        }
+
+        fn open_url(&self, url: &str) -> Result<(), String> {
+            self.opened.lock().unwrap().push(url.to_string());
+            Ok(())
+        }
+    }
+
+    fn fake() -> &'static Fake {
+        Box::leak(Box::default())
+    }
+
+    fn request(method: &str, path: &str, body: Value) -> Request {
+        Request {
+            method: method.to_string(),
+            path: path.to_string(),
+            host: Some("127.0.0.1:3001".to_string()),
+            origin: None,
+            content_type: Some("application/json".to_string()),
+            body: body.to_string().into_bytes(),
+        }
+    }
+
+    #[test]
+    fn puts_and_gets_data() {
+        let api = fake();
+        let put = request(
+            "PUT",
+            "/api/data",
+            json!({
+                "projects": [{"id": "p", "name": "P", "createdAt": 1, "updatedAt": 1}],
+                "columns": "not a list",
+                "currentProjectId": 7,
+                "extra": true
+            }),
+        );
+        assert_eq!(
+            handle(&api, &put, 3001),
+            Response::ok(json!({ "success": true }))
+        );
+        let got = handle(&api, &request("GET", "/api/data?t=1", Value::Null), 3001);
+        assert_eq!(got.status, 200);
+        assert_eq!(got.body["projects"][0]["id"], "p");
+        assert_eq!(got.body["columns"], json!([]));
+        assert_eq!(got.body["currentProjectId"], Value::Null);
+    }
+
+    #[test]
+    fn opens_http_urls_only() {
+        let api = fake();
+        let open = |body| handle(&api, &request("POST", "/api/open-url", body), 3001);
+        assert_eq!(open(json!({ "url": "https://example.com" })).status, 200);
+        assert_eq!(open(json!({})).body, json!({ "error": "URL is required" }));
+        assert_eq!(
+            open(json!({ "url": "file:///etc/passwd" })).body,
+            json!({ "error": "Invalid URL scheme" })
+        );
+        assert_eq!(*api.opened.lock().unwrap(), ["https://example.com"]);
+    }
+
+    #[test]
+    fn rejects_other_sites() {
+        let api = fake();
+        let mut req = request(
+            "POST",
+            "/api/open-url",
+            json!({ "url": "https://example.com" }),
+        );
+        req.origin = Some("https://evil.example".to_string());
+        assert_eq!(handle(&api, &req, 3001).status, 403);
+        req.origin = None;
+        req.host = Some("rebound.example:3001".to_string());
+        assert_eq!(handle(&api, &req, 3001).status, 403);
+        req.host = Some("localhost:3001".to_string());
+        req.content_type = Some("text/plain".to_string());
+        assert_eq!(handle(&api, &req, 3001).status, 415);
+        assert!(api.opened.lock().unwrap().is_empty());
+
+        req.origin = Some("http://localhost:3001".to_string());
+        req.content_type = Some("application/json; charset=utf-8".to_string());
+        assert_eq!(handle(&api, &req, 3001).status, 200);
+    }
+
+    #[test]
+    fn routes_unknown_requests() {
+        let api = fake();
+        assert_eq!(
+            handle(&api, &request("DELETE", "/api/data", Value::Null), 3001).status,
+            405
+        );
+        assert_eq!(
+            handle(&api, &request("GET", "/", Value::Null), 3001).status,
+            404
+        );
+    }
+}
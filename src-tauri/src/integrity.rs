@@ -0,0 +1,670 @@
+//! Referential integrity checks for `AppData`: duplicate ids, references to
+//! records that no longer exist, and negative or shared `order` values within
+//! a column/project. Gaps in the order (left by deleting or moving records)
+//! are fine: only the relative order matters. Tasks may share an order too,
+//! since the task list renumbers only the tasks its filters show. `repair`
+//! fixes what it finds without dropping records — orphans are moved into a
+//! "Recovered items" project instead of deleted.
+
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+
+use crate::logging;
+use crate::models::{AppData, EntityKind, KanbanColumn, Project};
+use crate::mutations::{generate_id, now_millis};
+
+/// Set to `1` to repair issues found by the launch-time check instead of only
+/// reporting them.
+pub const REPAIR_ENV: &str = "TOTAL_TPM_REPAIR_DATA";
+
+const RECOVERY_PROJECT: &str = "Recovered items";
+const RECOVERY_COLUMN: &str = "Recovered";
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum Issue {
+    #[serde(rename_all = "camelCase")]
+    DuplicateId { entity: EntityKind, id: String, count: usize },
+    #[serde(rename_all = "camelCase")]
+    DanglingReference {
+        entity: EntityKind,
+        id: String,
+        field: String,
+        target: EntityKind,
+        target_id: String,
+    },
+    /// `order` is negative, or shared with a sibling column or card. `scope`
+    /// is the column (for cards) or project (for columns and tasks) the order
+    /// is relative to.
+    #[serde(rename_all = "camelCase")]
+    InvalidOrder {
+        entity: EntityKind,
+        id: String,
+        scope: String,
+        order: i64,
+        siblings: usize,
+    },
+}
+
+impl std::fmt::Display for Issue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Issue::DuplicateId { entity, id, count } => {
+                write!(f, "{} id {} is used {} times", entity, id, count)
+            }
+            Issue::DanglingReference {
+                entity,
+                id,
+                field,
+                target,
+                target_id,
+            } => write!(
+                f,
+                "{} {}: {} points at missing {} {}",
+                entity, id, field, target, target_id
+            ),
+            Issue::InvalidOrder {
+                entity,
+                id,
+                scope,
+                order,
+                siblings,
+            } => write!(
+                f,
+                "{} {}: order {} is invalid among {} siblings in {}",
+                entity, id, order, siblings, scope
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IntegrityReport {
+    pub issues: Vec<Issue>,
+    /// Whether the issues were fixed (repair mode) or only reported.
+    pub repaired: bool,
+}
+
+impl IntegrityReport {
+    pub fn is_clean(&self) -> bool {
+        self.issues.is_empty()
+    }
+
+    pub fn log(&self) {
+        if self.is_clean() {
+            logging::info("Integrity check: no issues");
+            return;
+        }
+        logging::warn(&format!(
+            "Integrity check: {} issue(s){}",
+            self.issues.len(),
+            if self.repaired { ", repaired" } else { "" }
+        ));
+        for issue in &self.issues {
+            logging::warn(&format!("  - {}", issue));
+        }
+    }
+}
+
+/// Report every problem in `data` without changing it.
+pub fn check(data: &AppData) -> IntegrityReport {
+    let mut issues = Vec::new();
+    check_duplicates(data, &mut issues);
+    check_references(data, &mut issues);
+    check_orders(data, &mut issues);
+    IntegrityReport {
+        issues,
+        repaired: false,
+    }
+}
+
+/// Fix every problem `check` would report. Returns what was found.
+pub fn repair(data: &mut AppData) -> IntegrityReport {
+    let mut report = check(data);
+    if report.is_clean() {
+        return report;
+    }
+    repair_duplicates(data);
+    repair_references(data);
+    repair_orders(data);
+    report.repaired = true;
+    report
+}
+
+fn duplicates<'a>(ids: impl Iterator<Item = &'a String>) -> Vec<(String, usize)> {
+    let mut counts: HashMap<&String, usize> = HashMap::new();
+    let mut order = Vec::new();
+    for id in ids {
+        let count = counts.entry(id).or_insert(0);
+        if *count == 0 {
+            order.push(id);
+        }
+        *count += 1;
+    }
+    order
+        .into_iter()
+        .filter(|id| counts[id] > 1)
+        .map(|id| (id.clone(), counts[id]))
+        .collect()
+}
+
+fn check_duplicates(data: &AppData, issues: &mut Vec<Issue>) {
+    let sets: [(EntityKind, Vec<(String, usize)>); 5] = [
+        (EntityKind::Project, duplicates(data.projects.iter().map(|p| &p.id))),
+        (EntityKind::Column, duplicates(data.columns.iter().map(|c| &c.id))),
+        (EntityKind::Card, duplicates(data.cards.iter().map(|c| &c.id))),
+        (EntityKind::Task, duplicates(data.tasks.iter().map(|t| &t.id))),
+        (EntityKind::Note, duplicates(data.notes.iter().map(|n| &n.id))),
+    ];
+    for (entity, dups) in sets {
+        for (id, count) in dups {
+            issues.push(Issue::DuplicateId { entity, id, count });
+        }
+    }
+}
+
+fn dangling(entity: EntityKind, id: &str, field: &str, target: EntityKind, target_id: &str) -> Issue {
+    Issue::DanglingReference {
+        entity,
+        id: id.to_string(),
+        field: field.to_string(),
+        target,
+        target_id: target_id.to_string(),
+    }
+}
+
+fn check_references(data: &AppData, issues: &mut Vec<Issue>) {
+    let projects: HashSet<&str> = data.projects.iter().map(|p| p.id.as_str()).collect();
+    let columns: HashSet<&str> = data.columns.iter().map(|c| c.id.as_str()).collect();
+    let cards: HashSet<&str> = data.cards.iter().map(|c| c.id.as_str()).collect();
+    let tasks: HashSet<&str> = data.tasks.iter().map(|t| t.id.as_str()).collect();
+    let notes: HashSet<&str> = data.notes.iter().map(|n| n.id.as_str()).collect();
+
+    for column in &data.columns {
+        if !projects.contains(column.project_id.as_str()) {
+            issues.push(dangling(EntityKind::Column, &column.id, "projectId", EntityKind::Project, &column.project_id));
+        }
+    }
+    for card in &data.cards {
+        if !columns.contains(card.column_id.as_str()) {
+            issues.push(dangling(EntityKind::Card, &card.id, "columnId", EntityKind::Column, &card.column_id));
+        }
+        for task_id in card.linked_task_ids.iter().flatten() {
+            if !tasks.contains(task_id.as_str()) {
+                issues.push(dangling(EntityKind::Card, &card.id, "linkedTaskIds", EntityKind::Task, task_id));
+            }
+        }
+        for note_id in card.linked_note_ids.iter().flatten() {
+            if !notes.contains(note_id.as_str()) {
+                issues.push(dangling(EntityKind::Card, &card.id, "linkedNoteIds", EntityKind::Note, note_id));
+            }
+        }
+    }
+    for task in &data.tasks {
+        if !projects.contains(task.project_id.as_str()) {
+            issues.push(dangling(EntityKind::Task, &task.id, "projectId", EntityKind::Project, &task.project_id));
+        }
+        if let Some(card_id) = task.card_id.as_deref().filter(|c| !cards.contains(c)) {
+            issues.push(dangling(EntityKind::Task, &task.id, "cardId", EntityKind::Card, card_id));
+        }
+    }
+    for note in &data.notes {
+        if !projects.contains(note.project_id.as_str()) {
+            issues.push(dangling(EntityKind::Note, &note.id, "projectId", EntityKind::Project, &note.project_id));
+        }
+        if let Some(card_id) = note.card_id.as_deref().filter(|c| !cards.contains(c)) {
+            issues.push(dangling(EntityKind::Note, &note.id, "cardId", EntityKind::Card, card_id));
+        }
+    }
+    if let Some(current) = data.current_project_id.as_deref().filter(|p| !projects.contains(p)) {
+        issues.push(Issue::DanglingReference {
+            entity: EntityKind::Project,
+            id: String::new(),
+            field: String::from("currentProjectId"),
+            target: EntityKind::Project,
+            target_id: current.to_string(),
+        });
+    }
+}
+
+/// Ids in `scope` whose order is negative or shared with a sibling.
+fn invalid_orders<'a>(
+    items: impl Iterator<Item = (&'a str, i64)>,
+    allow_ties: bool,
+) -> (usize, Vec<(&'a str, i64)>) {
+    let items: Vec<(&str, i64)> = items.collect();
+    let n = items.len();
+    let mut counts: HashMap<i64, usize> = HashMap::new();
+    for (_, order) in &items {
+        *counts.entry(*order).or_insert(0) += 1;
+    }
+    let invalid = items
+        .into_iter()
+        .filter(|(_, order)| *order < 0 || (!allow_ties && counts[order] > 1))
+        .collect();
+    (n, invalid)
+}
+
+fn check_orders(data: &AppData, issues: &mut Vec<Issue>) {
+    let mut push = |entity: EntityKind, scope: &str, (siblings, invalid): (usize, Vec<(&str, i64)>)| {
+        for (id, order) in invalid {
+            issues.push(Issue::InvalidOrder {
+                entity,
+                id: id.to_string(),
+                scope: scope.to_string(),
+                order,
+                siblings,
+            });
+        }
+    };
+
+    let mut seen = HashSet::new();
+    let project_ids: Vec<&str> = data
+        .projects
+        .iter()
+        .map(|p| p.id.as_str())
+        .filter(|id| seen.insert(*id))
+        .collect();
+    for project_id in &project_ids {
+        push(
+            EntityKind::Column,
+            project_id,
+            invalid_orders(
+                data.columns
+                    .iter()
+                    .filter(|c| c.project_id == *project_id)
+                    .map(|c| (c.id.as_str(), c.order)),
+                false,
+            ),
+        );
+        push(
+            EntityKind::Task,
+            project_id,
+            invalid_orders(
+                data.tasks
+                    .iter()
+                    .filter(|t| t.project_id == *project_id)
+                    .filter_map(|t| t.order.map(|o| (t.id.as_str(), o))),
+                true,
+            ),
+        );
+    }
+    for column in &data.columns {
+        push(
+            EntityKind::Card,
+            &column.id,
+            invalid_orders(
+                data.cards
+                    .iter()
+                    .filter(|c| c.column_id == column.id)
+                    .map(|c| (c.id.as_str(), c.order)),
+                false,
+            ),
+        );
+    }
+}
+
+/// Give every repeat of an id a fresh one. References keep pointing at the
+/// first record with the id.
+fn repair_duplicates(data: &mut AppData) {
+    fn reassign<'a>(ids: impl Iterator<Item = &'a mut String>) {
+        let mut seen = HashSet::new();
+        for id in ids {
+            if !seen.insert(id.clone()) {
+                *id = generate_id();
+            }
+        }
+    }
+    reassign(data.projects.iter_mut().map(|p| &mut p.id));
+    reassign(data.columns.iter_mut().map(|c| &mut c.id));
+    reassign(data.cards.iter_mut().map(|c| &mut c.id));
+    reassign(data.tasks.iter_mut().map(|t| &mut t.id));
+    reassign(data.notes.iter_mut().map(|n| &mut n.id));
+}
+
+/// Get (creating on first use) the project orphaned records are moved into.
+fn recovery_project(data: &mut AppData) -> String {
+    if let Some(p) = data.projects.iter().find(|p| p.name == RECOVERY_PROJECT) {
+        return p.id.clone();
+    }
+    let now = now_millis();
+    let project = Project {
+        id: generate_id(),
+        name: RECOVERY_PROJECT.to_string(),
+        description: Some(String::from("Records found without a valid project or column during a data repair.")),
+        created_at: now,
+        updated_at: now,
+    };
+    let id = project.id.clone();
+    data.projects.push(project);
+    id
+}
+
+fn recovery_column(data: &mut AppData) -> String {
+    let project_id = recovery_project(data);
+    if let Some(c) = data
+        .columns
+        .iter()
+        .find(|c| c.project_id == project_id && c.title == RECOVERY_COLUMN)
+    {
+        return c.id.clone();
+    }
+    let order = data.columns.iter().filter(|c| c.project_id == project_id).count() as i64;
+    let column = KanbanColumn {
+        id: generate_id(),
+        project_id,
+        title: RECOVERY_COLUMN.to_string(),
+        order,
+        created_at: now_millis(),
+    };
+    let id = column.id.clone();
+    data.columns.push(column);
+    id
+}
+
+fn repair_references(data: &mut AppData) {
+    let projects: HashSet<String> = data.projects.iter().map(|p| p.id.clone()).collect();
+
+    let orphan_columns = data.columns.iter().any(|c| !projects.contains(&c.project_id));
+    let orphan_tasks = data.tasks.iter().any(|t| !projects.contains(&t.project_id));
+    let orphan_notes = data.notes.iter().any(|n| !projects.contains(&n.project_id));
+    if orphan_columns || orphan_tasks || orphan_notes {
+        let recovery = recovery_project(data);
+        for column in data.columns.iter_mut().filter(|c| !projects.contains(&c.project_id)) {
+            column.project_id = recovery.clone();
+        }
+        for task in data.tasks.iter_mut().filter(|t| !projects.contains(&t.project_id)) {
+            task.project_id = recovery.clone();
+        }
+        for note in data.notes.iter_mut().filter(|n| !projects.contains(&n.project_id)) {
+            note.project_id = recovery.clone();
+        }
+    }
+
+    let columns: HashSet<String> = data.columns.iter().map(|c| c.id.clone()).collect();
+    if data.cards.iter().any(|c| !columns.contains(&c.column_id)) {
+        let recovery = recovery_column(data);
+        for card in data.cards.iter_mut().filter(|c| !columns.contains(&c.column_id)) {
+            card.column_id = recovery.clone();
+        }
+    }
+
+    let cards: HashSet<String> = data.cards.iter().map(|c| c.id.clone()).collect();
+    let tasks: HashSet<String> = data.tasks.iter().map(|t| t.id.clone()).collect();
+    let notes: HashSet<String> = data.notes.iter().map(|n| n.id.clone()).collect();
+    for card in data.cards.iter_mut() {
+        if let Some(ids) = card.linked_task_ids.as_mut() {
+            ids.retain(|id| tasks.contains(id));
+        }
+        if let Some(ids) = card.linked_note_ids.as_mut() {
+            ids.retain(|id| notes.contains(id));
+        }
+    }
+    for task in data.tasks.iter_mut() {
+        if task.card_id.as_ref().is_some_and(|c| !cards.contains(c)) {
+            task.card_id = None;
+        }
+    }
+    for note in data.notes.iter_mut() {
+        if note.card_id.as_ref().is_some_and(|c| !cards.contains(c)) {
+            note.card_id = None;
+        }
+    }
+
+    let current_valid = data
+        .current_project_id
+        .as_ref()
+        .is_some_and(|id| data.projects.iter().any(|p| &p.id == id));
+    if !current_valid && data.current_project_id.is_some() {
+        data.current_project_id = data.projects.first().map(|p| p.id.clone());
+    }
+}
+
+/// Renumber columns and cards densely from 0, keeping the existing relative
+/// order (ties broken by creation time). A project's tasks are renumbered the
+/// same way only if one has a negative order.
+fn repair_orders(data: &mut AppData) {
+    let project_ids: Vec<String> = data.projects.iter().map(|p| p.id.clone()).collect();
+    let column_ids: Vec<String> = data.columns.iter().map(|c| c.id.clone()).collect();
+
+    for project_id in &project_ids {
+        let mut columns: Vec<&mut KanbanColumn> = data
+            .columns
+            .iter_mut()
+            .filter(|c| &c.project_id == project_id)
+            .collect();
+        columns.sort_by_key(|c| (c.order, c.created_at));
+        for (i, column) in columns.into_iter().enumerate() {
+            column.order = i as i64;
+        }
+
+        // Shared task orders are fine, so tasks are only renumbered to get
+        // rid of a negative one.
+        let mut tasks: Vec<_> = data
+            .tasks
+            .iter_mut()
+            .filter(|t| &t.project_id == project_id && t.order.is_some())
+            .collect();
+        if tasks.iter().any(|t| t.order.is_some_and(|o| o < 0)) {
+            tasks.sort_by_key(|t| (t.order, t.created_at));
+            for (i, task) in tasks.into_iter().enumerate() {
+                task.order = Some(i as i64);
+            }
+        }
+    }
+    for column_id in &column_ids {
+        let mut cards: Vec<_> = data.cards.iter_mut().filter(|c| &c.column_id == column_id).collect();
+        cards.sort_by_key(|c| (c.order, c.created_at));
+        for (i, card) in cards.into_iter().enumerate() {
+            card.order = i as i64;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{KanbanCard, Note, Task, TaskPriority, TaskStatus};
+
+    fn project(id: &str) -> Project {
+        Project {
+            id: id.into(),
+            name: id.to_uppercase(),
+            description: None,
+            created_at: 1,
+            updated_at: 1,
+        }
+    }
+
+    fn column(id: &str, project_id: &str, order: i64) -> KanbanColumn {
+        KanbanColumn {
+            id: id.into(),
+            project_id: project_id.into(),
+            title: id.to_uppercase(),
+            order,
+            created_at: 1,
+        }
+    }
+
+    fn card(id: &str, column_id: &str, order: i64) -> KanbanCard {
+        KanbanCard {
+            id: id.into(),
+            title: id.to_uppercase(),
+            description: None,
+            priority: None,
+            column_id: column_id.into(),
+            order,
+            created_at: 1,
+            updated_at: 1,
+            column_changed_at: 1,
+            due_date: None,
+            archived: None,
+            archived_at: None,
+            archive_reason: None,
+            linked_task_ids: None,
+            linked_note_ids: None,
+        }
+    }
+
+    fn task(id: &str, project_id: &str, card_id: Option<&str>, order: Option<i64>) -> Task {
+        Task {
+            id: id.into(),
+            project_id: project_id.into(),
+            title: id.to_uppercase(),
+            description: None,
+            status: TaskStatus::Todo,
+            priority: TaskPriority::P2,
+            due_date: None,
+            card_id: card_id.map(str::to_string),
+            links: None,
+            comments: None,
+            order,
+            created_at: 1,
+            updated_at: 1,
+        }
+    }
+
+    fn note(id: &str, project_id: &str, card_id: Option<&str>) -> Note {
+        Note {
+            id: id.into(),
+            project_id: project_id.into(),
+            title: id.to_uppercase(),
+            content: String::new(),
+            card_id: card_id.map(str::to_string),
+            links: None,
+            created_at: 1,
+            updated_at: 1,
+        }
+    }
+
+    /// A valid board: gaps in `order` are not issues.
+    fn clean() -> AppData {
+        AppData {
+            projects: vec![project("p")],
+            columns: vec![column("todo", "p", 0), column("done", "p", 5)],
+            cards: vec![card("a", "todo", 0), card("b", "todo", 3), card("c", "done", 0)],
+            tasks: vec![task("t1", "p", Some("a"), Some(2)), task("t2", "p", None, None)],
+            notes: vec![note("n1", "p", Some("c"))],
+            current_project_id: Some("p".into()),
+            ..AppData::default()
+        }
+    }
+
+    /// Check, repair, and check that repairing again changes nothing.
+    fn repaired(mut data: AppData) -> (Vec<Issue>, AppData) {
+        let report = repair(&mut data);
+        assert!(report.repaired);
+        assert!(check(&data).is_clean(), "{:?}", check(&data).issues);
+        let again = data.clone();
+        assert!(!repair(&mut data).repaired);
+        assert_eq!(data, again);
+        (report.issues, data)
+    }
+
+    #[test]
+    fn clean_data_has_no_issues() {
+        let mut data = clean();
+        assert!(check(&data).is_clean(), "{:?}", check(&data).issues);
+        let report = repair(&mut data);
+        assert!(!report.repaired);
+        assert_eq!(data, clean());
+    }
+
+    #[test]
+    fn duplicate_ids() {
+        let mut data = clean();
+        data.cards.push(card("a", "done", 1));
+        data.notes.push(note("n1", "p", None));
+        data.notes.push(note("n1", "p", None));
+
+        let (issues, data) = repaired(data);
+        assert_eq!(
+            issues,
+            [
+                Issue::DuplicateId { entity: EntityKind::Card, id: "a".into(), count: 2 },
+                Issue::DuplicateId { entity: EntityKind::Note, id: "n1".into(), count: 3 },
+            ]
+        );
+        // The first keeps its id and its links.
+        assert_eq!(data.cards[0].id, "a");
+        assert_ne!(data.cards[3].id, "a");
+        assert_eq!(data.notes.len(), 3);
+        assert_eq!(data.notes[0].card_id.as_deref(), Some("c"));
+    }
+
+    #[test]
+    fn dangling_references() {
+        let mut data = clean();
+        data.columns.push(column("lost", "gone", 0));
+        data.cards.push(card("d", "nowhere", 0));
+        data.cards[0].linked_note_ids = Some(vec!["n1".into(), "n9".into()]);
+        data.tasks.push(task("t3", "gone", Some("z"), None));
+        data.notes[0].card_id = Some("z".into());
+        data.current_project_id = Some("gone".into());
+
+        let (issues, data) = repaired(data);
+        let fields: Vec<(EntityKind, &str, &str)> = issues
+            .iter()
+            .map(|i| match i {
+                Issue::DanglingReference { entity, id, field, .. } => (*entity, id.as_str(), field.as_str()),
+                other => panic!("unexpected {:?}", other),
+            })
+            .collect();
+        assert_eq!(
+            fields,
+            [
+                (EntityKind::Column, "lost", "projectId"),
+                (EntityKind::Card, "a", "linkedNoteIds"),
+                (EntityKind::Card, "d", "columnId"),
+                (EntityKind::Task, "t3", "projectId"),
+                (EntityKind::Task, "t3", "cardId"),
+                (EntityKind::Note, "n1", "cardId"),
+                (EntityKind::Project, "", "currentProjectId"),
+            ]
+        );
+
+        // Orphans are moved, not dropped.
+        let recovery = &data.projects.iter().find(|p| p.name == RECOVERY_PROJECT).unwrap().id;
+        let lost = data.columns.iter().find(|c| c.id == "lost").unwrap();
+        assert_eq!(&lost.project_id, recovery);
+        let d = data.cards.iter().find(|c| c.id == "d").unwrap();
+        let recovered = data.columns.iter().find(|c| c.id == d.column_id).unwrap();
+        assert_eq!((&recovered.project_id, recovered.title.as_str()), (recovery, RECOVERY_COLUMN));
+        assert_eq!(&data.tasks[2].project_id, recovery);
+        assert_eq!(data.tasks[2].card_id, None);
+        assert_eq!(data.notes[0].card_id, None);
+        assert_eq!(data.cards[0].linked_note_ids, Some(vec!["n1".into()]));
+        assert_eq!(data.current_project_id.as_deref(), Some("p"));
+    }
+
+    #[test]
+    fn negative_and_shared_orders() {
+        // Tasks share orders when the list is reordered with a filter on.
+        let mut data = clean();
+        data.tasks[1].order = Some(2);
+        assert!(check(&data).is_clean());
+
+        data.cards[1].order = 0;
+        data.cards[1].created_at = 2;
+        data.cards[2].order = -1;
+        data.tasks[1].order = Some(-1);
+
+        let (issues, data) = repaired(data);
+        let orders: Vec<(&str, i64, usize)> = issues
+            .iter()
+            .map(|i| match i {
+                Issue::InvalidOrder { id, order, siblings, .. } => (id.as_str(), *order, *siblings),
+                other => panic!("unexpected {:?}", other),
+            })
+            .collect();
+        assert_eq!(orders, [("t2", -1, 2), ("a", 0, 2), ("b", 0, 2), ("c", -1, 1)]);
+
+        // Renumbered densely, ties broken by creation time.
+        let cards: Vec<(&str, i64)> = data.cards.iter().map(|c| (c.id.as_str(), c.order)).collect();
+        assert_eq!(cards, [("a", 0), ("b", 1), ("c", 0)]);
+        let tasks: Vec<(&str, Option<i64>)> = data.tasks.iter().map(|t| (t.id.as_str(), t.order)).collect();
+        assert_eq!(tasks, [("t1", Some(1)), ("t2", Some(0))]);
+        assert_eq!(data.columns[1].order, 1);
+    }
+}
@@ -0,0 +1,517 @@
+//! Undo history. Every change made through the store is recorded as an
+//! operation: the records it touched, with their values (and positions)
+//! before and after. Undo puts the "before" side back, redo the "after"
+//! side. The journal is kept in `data/journal.json`, so undo still works
+//! after a restart.
+//!
+//! Only this device's edits are recorded. Changes that arrive through sync
+//! are not undoable, and an operation whose records a sync has changed since
+//! can't be undone either; the history is dropped instead.
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use crate::logging;
+use crate::merge::Record;
+use crate::models::{AppData, EntityKind};
+
+pub const JOURNAL_FILE: &str = "data/journal.json";
+
+/// Older operations are dropped past this many.
+const MAX_OPERATIONS: usize = 100;
+
+/// One record's change. `None` means the record didn't exist on that side.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RecordChange {
+    entity: EntityKind,
+    id: String,
+    before: Option<Value>,
+    /// Position in its collection before the change.
+    before_index: usize,
+    after: Option<Value>,
+    after_index: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Operation {
+    pub id: u64,
+    pub at: i64,
+    pub description: String,
+    changes: Vec<RecordChange>,
+}
+
+/// An operation as listed by the `history` command, newest first.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryEntry {
+    pub id: u64,
+    pub at: i64,
+    pub description: String,
+    pub changes: usize,
+    /// Undone, and so available to redo.
+    pub undone: bool,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct JournalFile {
+    operations: Vec<Operation>,
+    undone: usize,
+}
+
+pub struct Journal {
+    path: PathBuf,
+    /// Oldest first.
+    operations: Vec<Operation>,
+    /// How many of the newest operations have been undone.
+    undone: usize,
+}
+
+#[derive(Clone, Copy)]
+enum Side {
+    Before,
+    After,
+}
+
+impl Operation {
+    /// The operation that turns `prev` into `next`, or `None` if no record
+    /// differs. `currentProjectId` is a view setting and isn't recorded.
+    pub fn between(id: u64, prev: &AppData, next: &AppData) -> Option<Self> {
+        let mut changes = Vec::new();
+        diff(&prev.projects, &next.projects, &mut changes);
+        diff(&prev.columns, &next.columns, &mut changes);
+        diff(&prev.cards, &next.cards, &mut changes);
+        diff(&prev.tasks, &next.tasks, &mut changes);
+        diff(&prev.notes, &next.notes, &mut changes);
+        if changes.is_empty() {
+            return None;
+        }
+        Some(Operation {
+            id,
+            at: crate::mutations::now_millis(),
+            description: describe(&changes),
+            changes,
+        })
+    }
+
+    /// Whether the records this operation touched are still as it left them.
+    pub fn can_undo(&self, data: &AppData) -> bool {
+        self.changes
+            .iter()
+            .all(|c| current_value(data, c.entity, &c.id) == c.after)
+    }
+
+    /// Whether the records are still as undoing this operation left them.
+    pub fn can_redo(&self, data: &AppData) -> bool {
+        self.changes
+            .iter()
+            .all(|c| current_value(data, c.entity, &c.id) == c.before)
+    }
+
+    pub fn undo(&self, data: &mut AppData) -> Result<(), String> {
+        self.put(data, Side::Before)
+    }
+
+    pub fn redo(&self, data: &mut AppData) -> Result<(), String> {
+        self.put(data, Side::After)
+    }
+
+    fn put(&self, data: &mut AppData, side: Side) -> Result<(), String> {
+        put_records(&mut data.projects, &self.changes, side)?;
+        put_records(&mut data.columns, &self.changes, side)?;
+        put_records(&mut data.cards, &self.changes, side)?;
+        put_records(&mut data.tasks, &self.changes, side)?;
+        put_records(&mut data.notes, &self.changes, side)?;
+        if let Some(current) = &data.current_project_id {
+            if !data.projects.iter().any(|p| &p.id == current) {
+                data.current_project_id = data.projects.first().map(|p| p.id.clone());
+            }
+        }
+        Ok(())
+    }
+
+    pub fn entry(&self, undone: bool) -> HistoryEntry {
+        HistoryEntry {
+            id: self.id,
+            at: self.at,
+            description: self.description.clone(),
+            changes: self.changes.len(),
+            undone,
+        }
+    }
+}
+
+impl Journal {
+    /// Load the journal for a project. It is discarded (and a fresh one
+    /// started) if it can't be read or doesn't match `data`, e.g. because
+    /// the data file was edited while the app was closed.
+    pub fn load(project_dir: &str, data: &AppData) -> Self {
+        let path = Path::new(project_dir).join(JOURNAL_FILE);
+        let file = match std::fs::read_to_string(&path) {
+            Ok(content) => match serde_json::from_str::<JournalFile>(&content) {
+                Ok(file) => file,
+                Err(e) => {
+                    logging::warn(&format!("Undo history {} is invalid ({}), starting a new one", path.display(), e));
+                    JournalFile::default()
+                }
+            },
+            Err(_) => JournalFile::default(),
+        };
+        let mut journal = Journal {
+            path,
+            undone: file.undone.min(file.operations.len()),
+            operations: file.operations,
+        };
+        let consistent = match (journal.undo_target(), journal.redo_target()) {
+            (Some(op), _) => op.can_undo(data),
+            (None, Some(op)) => op.can_redo(data),
+            (None, None) => true,
+        };
+        if !consistent {
+            logging::info("Data changed outside the app since the undo history was saved, starting a new one");
+            journal.clear();
+        }
+        journal
+    }
+
+    /// The operation that turns `prev` into `next`; `push` it once the
+    /// change is saved.
+    pub fn operation(&self, prev: &AppData, next: &AppData) -> Option<Operation> {
+        let id = self.operations.last().map_or(1, |op| op.id + 1);
+        Operation::between(id, prev, next)
+    }
+
+    /// Record an operation. Anything undone is no longer redoable.
+    pub fn push(&mut self, op: Operation) -> Result<(), String> {
+        self.operations.truncate(self.operations.len() - self.undone);
+        self.undone = 0;
+        self.operations.push(op);
+        if self.operations.len() > MAX_OPERATIONS {
+            let excess = self.operations.len() - MAX_OPERATIONS;
+            self.operations.drain(..excess);
+        }
+        self.save()
+    }
+
+    /// The operation `undo` would revert.
+    pub fn undo_target(&self) -> Option<&Operation> {
+        let applied = self.operations.len() - self.undone;
+        applied.checked_sub(1).map(|i| &self.operations[i])
+    }
+
+    /// The operation `redo` would reapply.
+    pub fn redo_target(&self) -> Option<&Operation> {
+        if self.undone == 0 {
+            return None;
+        }
+        self.operations.get(self.operations.len() - self.undone)
+    }
+
+    /// Call once the undo target has been applied.
+    pub fn mark_undone(&mut self) -> Result<(), String> {
+        self.undone += 1;
+        self.save()
+    }
+
+    /// Call once the redo target has been applied.
+    pub fn mark_redone(&mut self) -> Result<(), String> {
+        self.undone -= 1;
+        self.save()
+    }
+
+    pub fn history(&self) -> Vec<HistoryEntry> {
+        let applied = self.operations.len() - self.undone;
+        self.operations
+            .iter()
+            .enumerate()
+            .rev()
+            .map(|(i, op)| op.entry(i >= applied))
+            .collect()
+    }
+
+    /// Drop the whole history, e.g. once it no longer matches the data.
+    pub fn clear(&mut self) {
+        self.operations.clear();
+        self.undone = 0;
+        if let Err(e) = self.save() {
+            logging::warn(&format!("Failed to save undo history: {}", e));
+        }
+    }
+
+    fn save(&self) -> Result<(), String> {
+        let file = JournalFile {
+            operations: self.operations.clone(),
+            undone: self.undone,
+        };
+        let json = serde_json::to_string(&file).map_err(|e| e.to_string())?;
+        crate::store::write_atomic(&self.path, json.as_bytes())
+    }
+}
+
+fn diff<T: Record>(prev: &[T], next: &[T], out: &mut Vec<RecordChange>) {
+    let before: HashMap<&str, (usize, &T)> = prev.iter().enumerate().map(|(i, r)| (r.id(), (i, r))).collect();
+    let after: HashMap<&str, &T> = next.iter().map(|r| (r.id(), r)).collect();
+    for (i, record) in next.iter().enumerate() {
+        let old = before.get(record.id());
+        if old.map(|(_, r)| *r) != Some(record) {
+            out.push(RecordChange {
+                entity: T::KIND,
+                id: record.id().to_string(),
+                before: old.and_then(|(_, r)| serde_json::to_value(r).ok()),
+                before_index: old.map_or(0, |(i, _)| *i),
+                after: serde_json::to_value(record).ok(),
+                after_index: i,
+            });
+        }
+    }
+    for (i, record) in prev.iter().enumerate() {
+        if !after.contains_key(record.id()) {
+            out.push(RecordChange {
+                entity: T::KIND,
+                id: record.id().to_string(),
+                before: serde_json::to_value(record).ok(),
+                before_index: i,
+                after: None,
+                after_index: 0,
+            });
+        }
+    }
+}
+
+/// Set every changed record of `T`'s kind to its value on `side`. Edited
+/// records are replaced where they are; records that come back are
+/// reinserted where they were.
+fn put_records<T: Record + DeserializeOwned>(
+    records: &mut Vec<T>,
+    changes: &[RecordChange],
+    side: Side,
+) -> Result<(), String> {
+    let mut inserts = Vec::new();
+    for change in changes.iter().filter(|c| c.entity == T::KIND) {
+        let (value, index) = match side {
+            Side::Before => (&change.before, change.before_index),
+            Side::After => (&change.after, change.after_index),
+        };
+        let value = match value {
+            Some(value) => Some(
+                serde_json::from_value::<T>(value.clone())
+                    .map_err(|e| format!("Invalid {} in undo history: {}", T::KIND, e))?,
+            ),
+            None => None,
+        };
+        let existing = records.iter().position(|r| r.id() == change.id);
+        match (existing, value) {
+            (Some(i), Some(record)) => records[i] = record,
+            (Some(i), None) => {
+                records.remove(i);
+            }
+            (None, Some(record)) => inserts.push((index, record)),
+            (None, None) => {}
+        }
+    }
+    inserts.sort_by_key(|(i, _)| *i);
+    for (i, record) in inserts {
+        records.insert(i.min(records.len()), record);
+    }
+    Ok(())
+}
+
+fn current_value(data: &AppData, entity: EntityKind, id: &str) -> Option<Value> {
+    fn find<T: Record>(records: &[T], id: &str) -> Option<Value> {
+        records
+            .iter()
+            .find(|r| r.id() == id)
+            .and_then(|r| serde_json::to_value(r).ok())
+    }
+    match entity {
+        EntityKind::Project => find(&data.projects, id),
+        EntityKind::Column => find(&data.columns, id),
+        EntityKind::Card => find(&data.cards, id),
+        EntityKind::Task => find(&data.tasks, id),
+        EntityKind::Note => find(&data.notes, id),
+    }
+}
+
+/// E.g. `Delete column "Done" and 3 other changes`. Named after the first
+/// record added or deleted, if any, since an edit alongside one is usually
+/// its side effect.
+fn describe(changes: &[RecordChange]) -> String {
+    let main = changes
+        .iter()
+        .find(|c| c.before.is_none() || c.after.is_none())
+        .unwrap_or(&changes[0]);
+    let verb = match (&main.before, &main.after) {
+        (None, _) => "Add",
+        (_, None) => "Delete",
+        _ => "Edit",
+    };
+    let name = main
+        .after
+        .as_ref()
+        .or(main.before.as_ref())
+        .and_then(|v| v.get("title").or_else(|| v.get("name")))
+        .and_then(Value::as_str)
+        .filter(|s| !s.is_empty());
+    let mut description = match name {
+        Some(name) => format!("{} {} \"{}\"", verb, main.entity, name),
+        None => format!("{} {}", verb, main.entity),
+    };
+    match changes.len() - 1 {
+        0 => {}
+        1 => description.push_str(" and 1 other change"),
+        n => description.push_str(&format!(" and {} other changes", n)),
+    }
+    description
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mutations::{NewCard, NewProject};
+    use crate::test_util::TempDir;
+
+    /// A project with three columns and two cards in each.
+    fn board() -> AppData {
+        let mut data = AppData::default();
+        data.create_project(NewProject {
+            name: "P".into(),
+            description: None,
+        })
+        .unwrap();
+        for column in data.columns.clone() {
+            for title in ["one", "two"] {
+                data.create_card(NewCard {
+                    column_id: column.id.clone(),
+                    title: format!("{} {}", column.title, title),
+                    description: None,
+                    priority: None,
+                    due_date: None,
+                })
+                .unwrap();
+            }
+        }
+        data
+    }
+
+    /// `data` with the project renamed, as an arbitrary distinct state.
+    fn renamed(data: &AppData, name: &str) -> AppData {
+        let mut next = data.clone();
+        next.projects[0].name = name.into();
+        next
+    }
+
+    #[test]
+    fn undo_and_redo_round_trip() {
+        let prev = board();
+        let mut next = prev.clone();
+        let middle = next.columns[1].id.clone();
+        next.delete_column(&middle).unwrap();
+        next.cards[0].title = "Edited".into();
+
+        let op = Operation::between(1, &prev, &next).unwrap();
+        assert_eq!(op.description, "Delete column \"In Progress\" and 3 other changes");
+
+        // The column and its cards go back where they were.
+        let mut undone = next.clone();
+        op.undo(&mut undone).unwrap();
+        assert_eq!(undone, prev);
+        let mut redone = undone;
+        op.redo(&mut redone).unwrap();
+        assert_eq!(redone, next);
+
+        assert!(Operation::between(2, &prev, &prev).is_none());
+        let mut current_only = prev.clone();
+        current_only.current_project_id = None;
+        assert!(Operation::between(2, &prev, &current_only).is_none());
+    }
+
+    #[test]
+    fn external_changes_block_undo() {
+        let prev = board();
+        let mut next = prev.clone();
+        next.cards[0].title = "Edited".into();
+        let op = Operation::between(1, &prev, &next).unwrap();
+        assert!(op.can_undo(&next));
+        assert!(!op.can_redo(&next));
+
+        // A record the operation didn't touch doesn't matter...
+        let mut synced = next.clone();
+        synced.cards[1].title = "Synced".into();
+        assert!(op.can_undo(&synced));
+        // ...one it did does.
+        synced.cards[0].title = "Synced".into();
+        assert!(!op.can_undo(&synced));
+        synced.cards.remove(0);
+        assert!(!op.can_undo(&synced));
+    }
+
+    #[test]
+    fn pushing_drops_the_redo_tail() {
+        let dir = TempDir::new("journal-push");
+        let base = board();
+        let mut journal = Journal::load(dir.as_str(), &base);
+        let states: Vec<AppData> = (0..4).map(|i| renamed(&base, &format!("P{}", i))).collect();
+        for pair in states.windows(2) {
+            let op = journal.operation(&pair[0], &pair[1]).unwrap();
+            journal.push(op).unwrap();
+        }
+        journal.mark_undone().unwrap();
+        journal.mark_undone().unwrap();
+        assert_eq!(journal.redo_target().unwrap().id, 2);
+        assert_eq!(journal.undo_target().unwrap().id, 1);
+
+        let op = journal.operation(&states[1], &renamed(&base, "Other")).unwrap();
+        assert_eq!(op.id, 4);
+        journal.push(op).unwrap();
+        assert!(journal.redo_target().is_none());
+        let history: Vec<(u64, bool)> = journal.history().iter().map(|e| (e.id, e.undone)).collect();
+        assert_eq!(history, [(4, false), (1, false)]);
+    }
+
+    #[test]
+    fn history_is_capped() {
+        let dir = TempDir::new("journal-cap");
+        let base = board();
+        let mut journal = Journal::load(dir.as_str(), &base);
+        let mut prev = base.clone();
+        for i in 0..MAX_OPERATIONS + 5 {
+            let next = renamed(&base, &format!("P{}", i));
+            let op = journal.operation(&prev, &next).unwrap();
+            journal.push(op).unwrap();
+            prev = next;
+        }
+        let history = journal.history();
+        assert_eq!(history.len(), MAX_OPERATIONS);
+        assert_eq!(history[0].id, MAX_OPERATIONS as u64 + 5);
+        assert_eq!(history[MAX_OPERATIONS - 1].id, 6);
+    }
+
+    #[test]
+    fn load_discards_a_journal_that_does_not_match() {
+        let dir = TempDir::new("journal-load");
+        let project_dir = dir.as_str();
+        let before = board();
+        let after = renamed(&before, "Renamed");
+        let mut journal = Journal::load(project_dir, &before);
+        journal.push(journal.operation(&before, &after).unwrap()).unwrap();
+
+        assert_eq!(Journal::load(project_dir, &after).history().len(), 1);
+        journal.mark_undone().unwrap();
+        // Undone, so it matches the data from before the change.
+        assert!(Journal::load(project_dir, &before).redo_target().is_some());
+
+        // Edited while the app was closed.
+        let edited = renamed(&before, "Edited elsewhere");
+        assert!(Journal::load(project_dir, &edited).history().is_empty());
+        // The discarded journal isn't picked up again.
+        assert!(Journal::load(project_dir, &before).history().is_empty());
+
+        std::fs::write(dir.join(JOURNAL_FILE), "not json").unwrap();
+        assert!(Journal::load(project_dir, &before).history().is_empty());
+    }
+}
@@ -1,32 +1,70 @@
-use std::io::Write;
 use std::process::{Child, Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Mutex;
-use tauri::Manager;
+use tauri::{Emitter, Manager};
+
+mod analytics;
+mod audit;
+mod backup;
+mod board_import;
+mod build_marker;
+mod build_slots;
+mod bundle;
+mod commands;
+mod csv;
+mod dataset;
+mod error;
+mod folder_sync;
+mod http_server;
+mod integrity;
+mod journal;
+mod logging;
+mod merge;
+mod migrations;
+mod models;
+mod mutations;
+mod pipeline;
+mod report;
+mod search;
+mod server_log;
+#[cfg(feature = "sqlite")]
+mod sqlite;
+mod store;
+mod supervisor;
+mod sync;
+mod task_import;
+#[cfg(test)]
+mod test_util;
+
+use backup::BackupManager;
+use build_marker::BuildFingerprint;
+use commands::{BackupState, DatasetState, FolderWatchState, StoreState, SyncState};
+use folder_sync::{FolderSync, FolderSyncConfig};
+use http_server::HttpServerConfig;
+use logging::Level;
+use pipeline::{FailurePolicy, LaunchContext, LaunchPipeline, LaunchReport, PipelineConfig, StageOutcome};
+use server_log::{ServerLog, ServerLogTail, Stream};
+use store::DataStore;
+use supervisor::{Backoff, RestartPolicy};
+use sync::{GitSync, SyncConfig};
 
 struct ServerProcess(Mutex<Option<Child>>);
 
-/// Lifecycle log — writes to both stdout and a file so we can debug
-/// issues when the app is launched from Finder (where stdout is invisible).
-static LOG_DIR: Mutex<Option<String>> = Mutex::new(None);
-
-fn lifecycle_log(msg: &str) {
-    let secs = std::time::SystemTime::now()
-        .duration_since(std::time::UNIX_EPOCH)
-        .unwrap_or_default()
-        .as_secs();
-    let line = format!("[{}] {}\n", secs, msg);
-    print!("{}", line);
-    if let Some(dir) = LOG_DIR.lock().ok().and_then(|g| g.clone()) {
-        let path = std::path::Path::new(&dir).join(".lifecycle.log");
-        if let Ok(mut f) = std::fs::OpenOptions::new()
-            .create(true)
-            .append(true)
-            .open(&path)
-        {
-            let _ = f.write_all(line.as_bytes());
-        }
-    }
-}
+/// Set when the app starts exiting, so the supervisor doesn't restart the
+/// server it is about to kill.
+static SERVER_STOPPING: AtomicBool = AtomicBool::new(false);
+
+/// Report from the most recent launch pipeline run.
+struct LaunchReportState(Mutex<Option<LaunchReport>>);
+
+/// Built with the frontend as a static export bundled into the app (see
+/// `tauri.static.conf.json`). The webview loads it directly, so there is no
+/// Node toolchain, build or server at launch, and the launch pipeline only
+/// runs its data stages.
+const STATIC_FRONTEND: bool = cfg!(feature = "static-frontend");
+
+/// The project directory, for commands that run outside the app state.
+static PROJECT_DIR: Mutex<Option<String>> = Mutex::new(None);
 
 /// Kill any orphaned Next.js server processes from previous app launches.
 /// This prevents stale servers from occupying ports and serving old code.
@@ -84,7 +122,7 @@ fn get_enhanced_path() -> String {
     }
 
     let result = paths.join(":");
-    lifecycle_log(&format!("Resolved PATH: {}", result));
+    logging::debug(&format!("Resolved PATH: {}", result));
     result
 }
 
@@ -99,8 +137,8 @@ fn find_available_port(start: u16) -> u16 {
 }
 
 /// Run git pull in the project directory. Returns true if code was updated.
-fn run_git_pull(project_dir: &str) -> bool {
-    lifecycle_log("Running git pull --ff-only...");
+fn run_git_pull(project_dir: &str) -> Result<bool, String> {
+    logging::info("Running git pull --ff-only...");
     let enhanced_path = get_enhanced_path();
 
     // Reset package-lock.json before pulling — corporate npm registries rewrite
@@ -126,28 +164,29 @@ fn run_git_pull(project_dir: &str) -> bool {
             let stderr = String::from_utf8_lossy(&output.stderr);
             if output.status.success() {
                 let msg = stdout.trim();
-                lifecycle_log(&format!("git pull: {}", msg));
-                !msg.contains("Already up to date")
+                logging::info(&format!("git pull: {}", msg));
+                Ok(!msg.contains("Already up to date"))
             } else {
-                lifecycle_log(&format!("git pull warning: {}", stderr.trim()));
-                lifecycle_log(&format!("git pull stdout: {}", stdout.trim()));
-                false
+                logging::warn(&format!("git pull warning: {}", stderr.trim()));
+                logging::warn(&format!("git pull stdout: {}", stdout.trim()));
+                Err(format!("git pull failed: {}", stderr.trim()))
             }
         }
         Err(e) => {
-            lifecycle_log(&format!("git pull failed to execute: {}", e));
-            false
+            logging::warn(&format!("git pull failed to execute: {}", e));
+            Err(format!("git pull failed to execute: {}", e))
         }
     }
 }
 
 /// Run npm install if node_modules is missing or package-lock.json is newer.
-fn run_npm_install(project_dir: &str) -> bool {
+/// Returns true if an install was performed.
+fn run_npm_install(project_dir: &str) -> Result<bool, String> {
     let node_modules = std::path::Path::new(project_dir).join("node_modules");
     let lock_file = std::path::Path::new(project_dir).join("package-lock.json");
 
     let needs_install = if !node_modules.exists() {
-        lifecycle_log("node_modules missing, running npm install...");
+        logging::info("node_modules missing, running npm install...");
         true
     } else if lock_file.exists() {
         let lock_modified = lock_file
@@ -160,10 +199,10 @@ fn run_npm_install(project_dir: &str) -> bool {
             .unwrap_or(std::time::SystemTime::UNIX_EPOCH);
 
         if lock_modified > nm_modified {
-            lifecycle_log("package-lock.json changed, running npm install...");
+            logging::info("package-lock.json changed, running npm install...");
             true
         } else {
-            lifecycle_log("node_modules up to date, skipping npm install");
+            logging::info("node_modules up to date, skipping npm install");
             false
         }
     } else {
@@ -171,7 +210,7 @@ fn run_npm_install(project_dir: &str) -> bool {
     };
 
     if !needs_install {
-        return false;
+        return Ok(false);
     }
 
     let enhanced_path = get_enhanced_path();
@@ -185,17 +224,17 @@ fn run_npm_install(project_dir: &str) -> bool {
     {
         Ok(output) => {
             if output.status.success() {
-                lifecycle_log("npm install completed");
-                true
+                logging::info("npm install completed");
+                Ok(true)
             } else {
                 let stderr = String::from_utf8_lossy(&output.stderr);
-                lifecycle_log(&format!("npm install failed: {}", stderr.trim()));
-                false
+                logging::warn(&format!("npm install failed: {}", stderr.trim()));
+                Err(format!("npm install failed: {}", stderr.trim()))
             }
         }
         Err(e) => {
-            lifecycle_log(&format!("npm install failed to execute: {}", e));
-            false
+            logging::warn(&format!("npm install failed to execute: {}", e));
+            Err(format!("npm install failed to execute: {}", e))
         }
     }
 }
@@ -215,34 +254,21 @@ fn get_git_head(project_dir: &str) -> Option<String> {
         .map(|o| String::from_utf8_lossy(&o.stdout).trim().to_string())
 }
 
-/// Save the current HEAD hash as the build marker.
-fn save_build_marker(project_dir: &str) {
-    if let Some(head) = get_git_head(project_dir) {
-        let marker = std::path::Path::new(project_dir).join(".next/.build-commit");
-        let _ = std::fs::write(&marker, &head);
-        lifecycle_log(&format!("Saved build marker: {}", head.trim()));
-    }
-}
-
-/// Build the Next.js app for production.
-fn run_next_build(project_dir: &str) -> bool {
+/// Build the Next.js app for production into the staging directory and swap
+/// it into `.next` on success. A failed build leaves `.next` untouched.
+/// `fingerprint` describes the tree being built and is saved as the build
+/// marker on success.
+fn run_next_build(project_dir: &str, fingerprint: Option<&BuildFingerprint>) -> Result<(), String> {
     let enhanced_path = get_enhanced_path();
+    let staging_dir = build_slots::prepare_staging(project_dir)?;
 
-    // Delete .next directory to ensure a clean build
-    let next_dir = std::path::Path::new(project_dir).join(".next");
-    if next_dir.exists() {
-        lifecycle_log("Removing .next directory for clean build...");
-        if let Err(e) = std::fs::remove_dir_all(&next_dir) {
-            lifecycle_log(&format!("Failed to remove .next: {}", e));
-        }
-    }
-
-    lifecycle_log("Building Next.js for production...");
+    logging::info("Building Next.js for production...");
 
     match Command::new("npx")
         .args(["next", "build"])
         .current_dir(project_dir)
         .env("PATH", &enhanced_path)
+        .env(build_slots::DIST_DIR_ENV, build_slots::STAGING_DIR)
         .stdout(Stdio::piped())
         .stderr(Stdio::piped())
         .output()
@@ -251,28 +277,33 @@ fn run_next_build(project_dir: &str) -> bool {
             let stdout = String::from_utf8_lossy(&output.stdout);
             let stderr = String::from_utf8_lossy(&output.stderr);
             if output.status.success() {
-                lifecycle_log("Build complete");
-                lifecycle_log(&format!("Build stdout: {}", stdout.trim()));
-                save_build_marker(project_dir);
-                true
+                logging::info("Build complete");
+                logging::debug(&format!("Build stdout: {}", stdout.trim()));
+                if let Some(fingerprint) = fingerprint {
+                    build_marker::write_marker(&staging_dir, fingerprint);
+                }
+                build_slots::promote_staging(project_dir)?;
+                build_marker::clear_force_rebuild(project_dir);
+                Ok(())
             } else {
-                lifecycle_log(&format!("Next.js build failed stderr: {}", stderr.trim()));
-                lifecycle_log(&format!("Next.js build failed stdout: {}", stdout.trim()));
-                false
+                logging::error(&format!("Next.js build failed stderr: {}", stderr.trim()));
+                logging::error(&format!("Next.js build failed stdout: {}", stdout.trim()));
+                Err(format!("next build exited with {}", output.status))
             }
         }
         Err(e) => {
-            lifecycle_log(&format!("Next.js build failed to execute: {}", e));
-            false
+            logging::error(&format!("Next.js build failed to execute: {}", e));
+            Err(format!("Next.js build failed to execute: {}", e))
         }
     }
 }
 
-/// Start the Next.js server on the given port.
-fn start_nextjs_server(project_dir: &str, port: u16) -> Result<Child, String> {
+/// Start the Next.js server on the given port, with its output going to
+/// `log`.
+fn start_nextjs_server(project_dir: &str, port: u16, log: &ServerLog) -> Result<Child, String> {
     let enhanced_path = get_enhanced_path();
 
-    let child = if cfg!(debug_assertions) {
+    let mut child = if cfg!(debug_assertions) {
         Command::new("npx")
             .args(["next", "dev", "--port", &port.to_string()])
             .current_dir(project_dir)
@@ -292,11 +323,12 @@ fn start_nextjs_server(project_dir: &str, port: u16) -> Result<Child, String> {
             .map_err(|e| format!("Failed to start Next.js production server: {}", e))?
     };
 
-    lifecycle_log(&format!(
-        "Next.js server started on port {} (PID: {})",
-        port,
-        child.id()
-    ));
+    logging::event(Level::Info, "Next.js server started")
+        .pid(child.id())
+        .port(port)
+        .emit();
+    log.write(Stream::Launch, &format!("Started server on port {} (PID {})", port, child.id()));
+    log.capture(&mut child);
     Ok(child)
 }
 
@@ -313,16 +345,23 @@ async fn wait_for_server(port: u16, max_retries: u32) -> Result<(), String> {
             Ok(response)
                 if response.status().is_success() || response.status().is_redirection() =>
             {
-                lifecycle_log(&format!("Server ready after {} attempts", attempt));
+                // Health checks on a running server answer first time.
+                if attempt > 1 {
+                    logging::event(Level::Info, "Server ready")
+                        .port(port)
+                        .field("attempts", attempt)
+                        .emit();
+                }
                 return Ok(());
             }
             Ok(_) => {}
             Err(_) => {
                 if attempt % 10 == 0 {
-                    lifecycle_log(&format!(
-                        "Waiting for server... (attempt {}/{})",
-                        attempt, max_retries
-                    ));
+                    logging::event(Level::Debug, "Waiting for server...")
+                        .port(port)
+                        .field("attempts", attempt)
+                        .field("maxAttempts", max_retries)
+                        .emit();
                 }
             }
         }
@@ -335,10 +374,18 @@ async fn wait_for_server(port: u16, max_retries: u32) -> Result<(), String> {
     ))
 }
 
-/// Kill the server process and all its children.
+/// Kill the server process and all its children. A server that has already
+/// exited has been reaped, so its pid may belong to another process by now —
+/// it is left alone rather than signalled.
 fn kill_server(child: &mut Child) {
     let pid = child.id();
-    lifecycle_log(&format!("Killing Next.js server (PID: {})...", pid));
+    if let Ok(Some(status)) = child.try_wait() {
+        logging::event(Level::Info, format!("Server process already exited ({})", status))
+            .pid(pid)
+            .emit();
+        return;
+    }
+    logging::event(Level::Info, "Killing Next.js server...").pid(pid).emit();
 
     #[cfg(unix)]
     {
@@ -355,27 +402,486 @@ fn kill_server(child: &mut Child) {
     }
 
     let _ = child.wait();
-    lifecycle_log("Server process terminated");
+    logging::event(Level::Info, "Server process terminated").pid(pid).emit();
 }
 
 /// Send a status update to the loading screen.
 fn send_status(app_handle: &tauri::AppHandle, msg: &str) {
-    lifecycle_log(&format!("Status: {}", msg));
+    logging::info(&format!("Status: {}", msg));
     if let Some(window) = app_handle.get_webview_window("main") {
         let js = format!("if(typeof updateStatus==='function')updateStatus('{}')", msg.replace('\'', "\\'"));
         let _ = window.eval(&js);
     }
 }
 
+/// Open an http(s) URL in the default browser. Replaces the Next.js
+/// `/api/open-url` route.
 #[tauri::command]
 fn open_url(url: String) -> Result<(), String> {
-    Command::new("open")
-        .arg(&url)
+    open_in_browser(&url)
+}
+
+fn open_in_browser(url: &str) -> Result<(), String> {
+    if !url.starts_with("http://") && !url.starts_with("https://") {
+        return Err(String::from("Invalid URL scheme"));
+    }
+    #[cfg(target_os = "macos")]
+    let mut command = Command::new("open");
+    #[cfg(target_os = "windows")]
+    let mut command = {
+        // `start` treats its first quoted argument as a window title.
+        let mut c = Command::new("cmd");
+        c.args(["/C", "start", ""]);
+        c
+    };
+    #[cfg(not(any(target_os = "macos", target_os = "windows")))]
+    let mut command = Command::new("xdg-open");
+    command
+        .arg(url)
         .spawn()
         .map_err(|e| format!("Failed to open URL: {}", e))?;
     Ok(())
 }
 
+/// Force a full `next build` on the next launch.
+#[tauri::command]
+fn request_rebuild() -> Result<(), String> {
+    if STATIC_FRONTEND {
+        return Err(String::from("This build bundles its frontend; there is nothing to rebuild"));
+    }
+    let dir = PROJECT_DIR
+        .lock()
+        .ok()
+        .and_then(|g| g.clone())
+        .ok_or_else(|| String::from("Project directory not initialized"))?;
+    std::fs::write(std::path::Path::new(&dir).join(build_marker::FORCE_REBUILD_FILE), "")
+        .map_err(|e| format!("Failed to request rebuild: {}", e))
+}
+
+/// The end of the Next.js server output, for diagnostics.
+#[tauri::command]
+fn get_server_log(log: tauri::State<'_, ServerLog>, lines: Option<usize>) -> ServerLogTail {
+    log.tail(lines.unwrap_or(200))
+}
+
+#[tauri::command]
+fn get_launch_report(state: tauri::State<'_, LaunchReportState>) -> Option<LaunchReport> {
+    state.0.lock().unwrap().clone()
+}
+
+/// Stages that bring the data up to date, run by every build.
+fn data_stages(app_handle: &tauri::AppHandle) -> LaunchPipeline {
+    let h_sync = app_handle.clone();
+    let h_data = app_handle.clone();
+
+    LaunchPipeline::new()
+        .stage("sync-data", FailurePolicy::Continue, move |_ctx| {
+            let Some(sync) = h_sync.try_state::<SyncState>() else {
+                return StageOutcome::skipped("data store not open");
+            };
+            if sync.git.is_none() {
+                return StageOutcome::skipped("sync not configured");
+            }
+            send_status(&h_sync, "Syncing data...");
+            let store = h_sync.state::<StoreState>();
+            let backups = h_sync.state::<BackupState>();
+            match commands::sync_data(&h_sync, &store, &backups, &sync) {
+                Ok(report) if !report.conflicts.is_empty() => {
+                    StageOutcome::degraded(format!("{} sync conflict(s)", report.conflicts.len()))
+                }
+                Ok(report) => match report.error {
+                    Some(e) => StageOutcome::degraded(e),
+                    None => StageOutcome::Succeeded,
+                },
+                Err(e) => StageOutcome::failed(e.to_string()),
+            }
+        })
+        .stage("validate-data", FailurePolicy::Continue, move |_ctx| {
+            let (Some(store), Some(backups)) =
+                (h_data.try_state::<StoreState>(), h_data.try_state::<BackupState>())
+            else {
+                return StageOutcome::skipped("data store not open");
+            };
+            let repair = std::env::var(integrity::REPAIR_ENV).is_ok_and(|v| v == "1");
+            match commands::validate(&store, &backups, repair) {
+                Ok(report) => {
+                    // A repair logs its own report. A static frontend is
+                    // already showing the data, so tell it to reload.
+                    if report.repaired {
+                        let _ = h_data.emit(commands::DATA_CHANGED_EVENT, ());
+                    } else {
+                        report.log();
+                    }
+                    if report.is_clean() || report.repaired {
+                        StageOutcome::Succeeded
+                    } else {
+                        StageOutcome::degraded(format!(
+                            "{} integrity issue(s); set {}=1 or call validate_data to repair",
+                            report.issues.len(),
+                            integrity::REPAIR_ENV
+                        ))
+                    }
+                }
+                Err(e) => StageOutcome::failed(e.to_string()),
+            }
+        })
+}
+
+/// The production launch sequence. Stage names are what `launch-pipeline.json`
+/// refers to when disabling or reordering stages. A static frontend build
+/// only has the data stages.
+fn build_launch_pipeline(app_handle: &tauri::AppHandle) -> LaunchPipeline {
+    let pipeline = data_stages(app_handle);
+    if STATIC_FRONTEND {
+        return pipeline;
+    }
+
+    let h_pull = app_handle.clone();
+    let h_deps = app_handle.clone();
+    let h_build = app_handle.clone();
+    let h_port = app_handle.clone();
+    let h_start = app_handle.clone();
+    let h_wait = app_handle.clone();
+
+    pipeline
+        .stage("git-pull", FailurePolicy::Continue, move |ctx| {
+            if let Some(head) = get_git_head(&ctx.project_dir) {
+                logging::info(&format!("HEAD before pull: {}", head));
+            }
+            send_status(&h_pull, "Checking for updates...");
+            let outcome = match run_git_pull(&ctx.project_dir) {
+                Ok(true) => {
+                    ctx.code_changed = true;
+                    send_status(&h_pull, "Updates found, applying...");
+                    StageOutcome::Succeeded
+                }
+                Ok(false) => {
+                    send_status(&h_pull, "Up to date");
+                    StageOutcome::Succeeded
+                }
+                Err(e) => StageOutcome::failed(e),
+            };
+            if let Some(head) = get_git_head(&ctx.project_dir) {
+                logging::info(&format!("HEAD after pull: {}", head));
+            }
+            outcome
+        })
+        .stage("npm-install", FailurePolicy::Continue, move |ctx| {
+            send_status(&h_deps, "Checking dependencies...");
+            match run_npm_install(&ctx.project_dir) {
+                Ok(true) => {
+                    send_status(&h_deps, "Dependencies updated");
+                    StageOutcome::Succeeded
+                }
+                Ok(false) => StageOutcome::skipped("node_modules up to date"),
+                Err(e) => StageOutcome::failed(e),
+            }
+        })
+        .stage("next-build", FailurePolicy::Continue, move |ctx| {
+            let fingerprint = BuildFingerprint::current(&ctx.project_dir);
+            let Some(reason) = build_marker::rebuild_reason(&ctx.project_dir, fingerprint.as_ref())
+            else {
+                logging::info("No rebuild needed, skipping next build");
+                return StageOutcome::skipped("build up to date");
+            };
+            logging::info(&format!("Rebuilding: {}", reason));
+            send_status(&h_build, "Building application...");
+            let error = match run_next_build(&ctx.project_dir, fingerprint.as_ref()) {
+                Ok(()) => {
+                    ctx.build_promoted = true;
+                    return StageOutcome::Succeeded;
+                }
+                Err(e) => e,
+            };
+
+            logging::event(Level::Error, format!("BUILD FAILED: {}", error))
+                .stage("next-build")
+                .emit();
+            let current = std::path::Path::new(&ctx.project_dir).join(build_slots::CURRENT_DIR);
+            if build_slots::current_usable(&ctx.project_dir) {
+                let built = build_slots::describe(&current);
+                send_status(&h_build, "Build failed — starting with previous version");
+                return StageOutcome::degraded(format!("{}; serving existing build {}", error, built));
+            }
+            match build_slots::restore_previous(&ctx.project_dir) {
+                Ok(restored) => {
+                    send_status(&h_build, "Build failed — starting with previous version");
+                    StageOutcome::degraded(format!("{}; rolled back to build {}", error, restored))
+                }
+                Err(_) => {
+                    send_status(&h_build, "Build failed — no previous version available");
+                    StageOutcome::failed(error)
+                }
+            }
+        })
+        .stage("find-port", FailurePolicy::Abort, move |ctx| {
+            send_status(&h_port, "Starting server...");
+            let port = find_available_port(3000);
+            logging::event(Level::Info, "Using port").port(port).emit();
+            ctx.port = Some(port);
+            StageOutcome::Succeeded
+        })
+        .stage("start-server", FailurePolicy::Abort, move |ctx| {
+            let port = ctx.port.unwrap_or(3000);
+            match start_nextjs_server(&ctx.project_dir, port, &h_start.state::<ServerLog>()) {
+                Ok(child) => {
+                    let state = h_start.state::<ServerProcess>();
+                    *state.0.lock().unwrap() = Some(child);
+                    ctx.port = Some(port);
+                    StageOutcome::Succeeded
+                }
+                Err(e) => {
+                    send_status(&h_start, "Failed to start server");
+                    StageOutcome::failed(e)
+                }
+            }
+        })
+        .stage("wait-for-server", FailurePolicy::Abort, move |ctx| {
+            let port = ctx.port.unwrap_or(3000);
+            send_status(&h_wait, "Almost ready...");
+            let error = match tauri::async_runtime::block_on(wait_for_server(port, 60)) {
+                Ok(()) => {
+                    ctx.server_ready = true;
+                    return StageOutcome::Succeeded;
+                }
+                Err(e) => e,
+            };
+
+            // A build promoted during this launch may be the reason the server
+            // will not come up — fall back to the last known-good build once.
+            if !ctx.build_promoted || !build_slots::previous_usable(&ctx.project_dir) {
+                send_status(&h_wait, "Server failed to start");
+                return StageOutcome::failed(error);
+            }
+            logging::warn(&format!("Server failed on new build ({}), rolling back", error));
+            send_status(&h_wait, "New version failed to start — rolling back...");
+            let previous_server = h_wait.state::<ServerProcess>().0.lock().unwrap().take();
+            if let Some(mut child) = previous_server {
+                kill_server(&mut child);
+            }
+            let restored = match build_slots::restore_previous(&ctx.project_dir) {
+                Ok(restored) => restored,
+                Err(e) => {
+                    send_status(&h_wait, "Server failed to start");
+                    return StageOutcome::failed(format!("{}; rollback failed: {}", error, e));
+                }
+            };
+            ctx.build_promoted = false;
+
+            let port = find_available_port(port);
+            let child = match start_nextjs_server(&ctx.project_dir, port, &h_wait.state::<ServerLog>()) {
+                Ok(child) => child,
+                Err(e) => {
+                    send_status(&h_wait, "Failed to start server");
+                    return StageOutcome::failed(e);
+                }
+            };
+            *h_wait.state::<ServerProcess>().0.lock().unwrap() = Some(child);
+            ctx.port = Some(port);
+
+            match tauri::async_runtime::block_on(wait_for_server(port, 60)) {
+                Ok(()) => {
+                    ctx.server_ready = true;
+                    StageOutcome::degraded(format!("{}; serving previous build {}", error, restored))
+                }
+                Err(e) => {
+                    send_status(&h_wait, "Server failed to start");
+                    StageOutcome::failed(e)
+                }
+            }
+        })
+}
+
+/// How often the supervisor checks that the server process is running.
+const SUPERVISOR_TICK: std::time::Duration = std::time::Duration::from_secs(2);
+/// Every this many ticks it also checks that the server answers HTTP...
+const HEALTH_CHECK_TICKS: u32 = 15;
+/// ...allowing this many attempts, half a second apart, before it counts as hung.
+const HEALTH_CHECK_RETRIES: u32 = 20;
+
+/// Watch the server the launch pipeline started for the life of the app.
+/// When it exits or stops answering, restart it with backoff and point the
+/// webview at the new server; once the restart policy gives up, show the
+/// error on the loading page. Blocking.
+fn supervise_server(
+    app_handle: &tauri::AppHandle,
+    project_dir: &str,
+    mut port: u16,
+    loading_page: Option<tauri::Url>,
+) {
+    let policy = RestartPolicy::default();
+    let mut backoff = Backoff::new(policy);
+    backoff.healthy(std::time::Instant::now());
+    let mut tick = 0;
+    loop {
+        std::thread::sleep(SUPERVISOR_TICK);
+        tick += 1;
+        let exited = {
+            let state = app_handle.state::<ServerProcess>();
+            let mut guard = state.0.lock().unwrap();
+            // Taken by the exit handler.
+            let Some(child) = guard.as_mut() else {
+                return;
+            };
+            match child.try_wait() {
+                Ok(Some(status)) => Some(format!("exited ({})", status)),
+                Ok(None) => None,
+                Err(e) => Some(format!("could not be checked: {}", e)),
+            }
+        };
+        if SERVER_STOPPING.load(Ordering::SeqCst) {
+            return;
+        }
+        let mut failure = match exited {
+            Some(reason) => reason,
+            None if tick % HEALTH_CHECK_TICKS == 0 => {
+                match tauri::async_runtime::block_on(wait_for_server(port, HEALTH_CHECK_RETRIES)) {
+                    Ok(()) => continue,
+                    Err(e) => format!("stopped responding ({})", e),
+                }
+            }
+            None => continue,
+        };
+        app_handle
+            .state::<ServerLog>()
+            .write(Stream::Launch, &format!("Server {}", failure));
+
+        loop {
+            logging::event(Level::Warn, format!("Next.js server {}", failure))
+                .port(port)
+                .emit();
+            let Some(delay) = backoff.failed(std::time::Instant::now()) else {
+                show_server_error(app_handle, loading_page.as_ref(), &failure);
+                return;
+            };
+            logging::event(Level::Warn, "Restarting server")
+                .field("delayMs", delay.as_millis() as u64)
+                .field("failures", backoff.failures())
+                .field("maxFailures", policy.max_failures)
+                .emit();
+            std::thread::sleep(delay);
+            match restart_server(app_handle, project_dir, port) {
+                Ok(Some(new_port)) => {
+                    port = new_port;
+                    backoff.healthy(std::time::Instant::now());
+                    navigate_to_server(app_handle, port);
+                    break;
+                }
+                Ok(None) => return,
+                Err(e) => failure = format!("failed to restart: {}", e),
+            }
+        }
+        tick = 0;
+    }
+}
+
+/// Replace the server with a new one, on `port` if it is free. Returns the
+/// port once the new server answers, or `None` if the app is exiting.
+fn restart_server(app_handle: &tauri::AppHandle, project_dir: &str, port: u16) -> Result<Option<u16>, String> {
+    if SERVER_STOPPING.load(Ordering::SeqCst) {
+        return Ok(None);
+    }
+    let old = app_handle.state::<ServerProcess>().0.lock().unwrap().take();
+    if let Some(mut child) = old {
+        kill_server(&mut child);
+    }
+    let port = find_available_port(port);
+    let mut child = start_nextjs_server(project_dir, port, &app_handle.state::<ServerLog>())?;
+    {
+        let state = app_handle.state::<ServerProcess>();
+        let mut guard = state.0.lock().unwrap();
+        // The exit handler sets the flag before taking the server, so either
+        // it sees this child or we see the flag.
+        if SERVER_STOPPING.load(Ordering::SeqCst) {
+            drop(guard);
+            kill_server(&mut child);
+            return Ok(None);
+        }
+        *guard = Some(child);
+    }
+    tauri::async_runtime::block_on(wait_for_server(port, 60))?;
+    Ok(Some(port))
+}
+
+fn navigate_to_server(app_handle: &tauri::AppHandle, port: u16) {
+    let url = format!("http://localhost:{}", port);
+    logging::info(&format!("Navigating webview to {}", url));
+    if let Some(window) = app_handle.get_webview_window("main") {
+        let _ = window.navigate(url.parse().unwrap());
+    }
+}
+
+/// Stop whatever is left of the server and show why on the loading page.
+fn show_server_error(app_handle: &tauri::AppHandle, loading_page: Option<&tauri::Url>, error: &str) {
+    logging::error(&format!("Giving up on the Next.js server: {}", error));
+    let leftover = app_handle.state::<ServerProcess>().0.lock().unwrap().take();
+    if let Some(mut child) = leftover {
+        kill_server(&mut child);
+    }
+    show_error_page(
+        app_handle,
+        loading_page,
+        "The app server stopped and could not be restarted.",
+        &format!("Next.js server {}", error),
+    );
+}
+
+/// Open the loading page's error screen.
+fn show_error_page(app_handle: &tauri::AppHandle, loading_page: Option<&tauri::Url>, message: &str, detail: &str) {
+    let (Some(window), Some(page)) = (app_handle.get_webview_window("main"), loading_page) else {
+        return;
+    };
+    let mut url = page.clone();
+    url.query_pairs_mut()
+        .clear()
+        .append_pair("error", message)
+        .append_pair("detail", detail);
+    let _ = window.navigate(url);
+    let _ = window.set_focus();
+}
+
+/// Run the launch pipeline and show the app once the server is up, then
+/// supervise the server. A static frontend is already showing, so only the
+/// data stages run.
+/// Blocking — call from a blocking task, not the async runtime.
+fn run_production_launch(app_handle: &tauri::AppHandle, project_dir: &str) {
+    // Still showing the loading page, which is also the error screen.
+    let loading_page = app_handle.get_webview_window("main").and_then(|w| w.url().ok());
+    if !STATIC_FRONTEND {
+        build_slots::recover_interrupted_swap(project_dir);
+    }
+    let config = PipelineConfig::load(project_dir);
+    let pipeline = build_launch_pipeline(app_handle).configure(&config);
+    logging::info(&format!("Launch pipeline: {}", pipeline.stage_names().join(" -> ")));
+
+    let mut ctx = LaunchContext::new(project_dir);
+    let report = pipeline.run(&mut ctx);
+    report.save(project_dir);
+
+    let supervise = report.succeeded() && ctx.server_ready;
+    if report.succeeded() {
+        if let Some(port) = ctx.port {
+            navigate_to_server(app_handle, port);
+            if let Some(window) = app_handle.get_webview_window("main") {
+                std::thread::sleep(std::time::Duration::from_millis(500));
+                let _ = window.set_focus();
+            }
+        }
+    } else if let Some(stage) = &report.aborted_at {
+        logging::event(Level::Error, "Launch aborted").stage(stage).emit();
+    }
+
+    let duration_ms = report.duration_ms;
+    *app_handle.state::<LaunchReportState>().0.lock().unwrap() = Some(report);
+    logging::event(Level::Info, "=== Lifecycle complete")
+        .field("durationMs", duration_ms)
+        .emit();
+
+    if let (true, Some(port)) = (supervise, ctx.port) {
+        supervise_server(app_handle, project_dir, port, loading_page);
+    }
+}
+
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     // Determine the project directory
@@ -417,17 +923,27 @@ pub fn run() {
         }
     };
 
-    // Initialize lifecycle log — truncate old log on each launch
-    if let Ok(mut g) = LOG_DIR.lock() {
+    if let Ok(mut g) = PROJECT_DIR.lock() {
         *g = Some(project_dir.clone());
     }
-    let log_path = std::path::Path::new(&project_dir).join(".lifecycle.log");
-    let _ = std::fs::write(&log_path, ""); // truncate
-    lifecycle_log(&format!("=== App starting, project dir: {}", project_dir));
+    // Logs live in the app-data directory, one file per launch, so the log of
+    // a crashed run survives the next launch. Without one they go to stdout.
+    let log_dir = logging::log_dir();
+    let started = log_dir
+        .as_deref()
+        .ok_or_else(|| String::from("no app-data directory"))
+        .and_then(logging::init);
+    match started {
+        Ok(path) => logging::info(&format!("Logging to {}", path.display())),
+        Err(e) => logging::warn(&format!("File logging disabled: {}", e)),
+    }
+    logging::info(&format!("=== App starting, project dir: {}", project_dir));
 
-    // In production, kill orphaned servers and clear caches before anything else
-    if !cfg!(debug_assertions) {
-        lifecycle_log("Killing any orphaned Next.js server processes...");
+    // In production, kill orphaned servers and clear caches before anything
+    // else. A static frontend build has neither a server nor pages cached
+    // from one.
+    if !cfg!(debug_assertions) && !STATIC_FRONTEND {
+        logging::info("Killing any orphaned Next.js server processes...");
         kill_orphan_servers();
 
         // Clear WebKit cache to prevent stale JS from previous builds
@@ -441,7 +957,7 @@ pub fn run() {
         for dir in &cache_dirs {
             let p = std::path::Path::new(dir);
             if p.exists() {
-                lifecycle_log(&format!("Clearing webview cache: {}", dir));
+                logging::info(&format!("Clearing webview cache: {}", dir));
                 let _ = std::fs::remove_dir_all(p);
             }
         }
@@ -450,96 +966,207 @@ pub fn run() {
     let project_dir_for_setup = project_dir.clone();
 
     let app = tauri::Builder::default()
+        .plugin(tauri_plugin_dialog::init())
         .manage(ServerProcess(Mutex::new(None)))
-        .invoke_handler(tauri::generate_handler![open_url])
+        .manage(ServerLog::new(log_dir.as_deref().unwrap_or(std::path::Path::new(&project_dir))))
+        .manage(LaunchReportState(Mutex::new(None)))
+        .manage(DatasetState(Mutex::new(dataset::Workspace::default())))
+        .invoke_handler(tauri::generate_handler![
+            open_url,
+            request_rebuild,
+            get_launch_report,
+            get_server_log,
+            commands::get_data,
+            commands::put_data,
+            commands::patch_data,
+            commands::search,
+            commands::undo,
+            commands::redo,
+            commands::history,
+            commands::get_entity_history,
+            commands::compact_history,
+            commands::get_column_dwell_times,
+            commands::get_cumulative_flow,
+            commands::get_cycle_times,
+            commands::get_throughput,
+            commands::list_backups,
+            commands::create_backup,
+            commands::restore_backup,
+            commands::export_project,
+            commands::import_project,
+            commands::import_board,
+            commands::export_report,
+            commands::preview_csv_import,
+            commands::import_csv_tasks,
+            commands::load_dataset,
+            commands::list_datasets,
+            commands::close_dataset,
+            commands::aggregate_dataset,
+            commands::attach_dataset_result,
+            commands::validate_data,
+            commands::sync_now,
+            commands::get_sync_status,
+            commands::create_project,
+            commands::update_project,
+            commands::delete_project,
+            commands::create_column,
+            commands::rename_column,
+            commands::move_column,
+            commands::delete_column,
+            commands::create_card,
+            commands::update_card,
+            commands::move_card,
+            commands::archive_card,
+            commands::restore_card,
+            commands::delete_card,
+            commands::purge_card,
+            commands::create_task,
+            commands::update_task,
+            commands::move_task,
+            commands::delete_task,
+            commands::create_note,
+            commands::update_note,
+            commands::delete_note,
+        ])
         .setup(move |app| {
             let app_handle = app.handle().clone();
             let dir = project_dir_for_setup.clone();
 
-            tauri::async_runtime::spawn(async move {
-                if cfg!(debug_assertions) {
-                    lifecycle_log("Dev mode — server managed by Tauri CLI");
-                    tokio::time::sleep(std::time::Duration::from_millis(1000)).await;
-                    if let Some(window) = app_handle.get_webview_window("main") {
-                        let _ = window.show();
-                        let _ = window.set_focus();
+            let backups = BackupManager::new(&dir);
+            let mut store = match DataStore::open(&dir, &backups) {
+                Ok(store) => store,
+                Err(e) => {
+                    // Don't launch the app over data we can't load — saving
+                    // would overwrite it. Nothing that writes or syncs is
+                    // started, and commands fail without the store.
+                    logging::error(&format!("Failed to open data store: {}", e));
+                    if STATIC_FRONTEND {
+                        // The app itself is showing, not the loading page.
+                        use tauri_plugin_dialog::{DialogExt, MessageDialogKind};
+                        app_handle
+                            .dialog()
+                            .message(e.to_string())
+                            .title("Total TPM couldn't open its data")
+                            .kind(MessageDialogKind::Error)
+                            .show(|_| {});
+                    } else {
+                        let page = app_handle.get_webview_window("main").and_then(|w| w.url().ok());
+                        show_error_page(&app_handle, page.as_ref(), "Total TPM couldn't open its data.", &e.to_string());
                     }
-                } else {
-                    // Production mode: full lifecycle management
+                    return Ok(());
+                }
+            };
+            let contents = store.contents().map_err(|e| e.to_string());
+            if let Err(e) = contents.and_then(|c| backups.snapshot_if_changed("launch", &c)) {
+                logging::warn(&format!("Launch backup failed: {}", e));
+            }
 
-                    // Log HEAD commit for diagnostics
-                    if let Some(head) = get_git_head(&dir) {
-                        lifecycle_log(&format!("HEAD before pull: {}", head));
-                    }
+            // Folder sync is best-effort: the app runs on local data without it.
+            let folder_config = FolderSyncConfig::load(&dir);
+            let mut watch_folder = None;
+            if let Some(config) = &folder_config {
+                let attached = FolderSync::open(&dir, config)
+                    .map_err(error::StoreError::io)
+                    .and_then(|sync| {
+                        let folder = sync.folder().to_path_buf();
+                        store.attach_folder_sync(sync).map(|_| folder)
+                    });
+                match attached {
+                    Ok(folder) => watch_folder = Some(folder),
+                    Err(e) => logging::warn(&format!("Folder sync disabled: {}", e)),
+                }
+            }
+            let interval_minutes = backups.config().interval_minutes;
+            app.manage(StoreState(Mutex::new(store)));
+            app.manage(BackupState(backups));
 
-                    // Step 1: Git pull
-                    send_status(&app_handle, "Checking for updates...");
-                    let code_changed = run_git_pull(&dir);
-                    if code_changed {
-                        send_status(&app_handle, "Updates found, applying...");
-                    } else {
-                        send_status(&app_handle, "Up to date");
-                    }
+            if let Some(config) = HttpServerConfig::load(&dir) {
+                if let Err(e) = http_server::start(&config, commands::HttpApi(app.handle().clone())) {
+                    logging::warn(&format!("HTTP server disabled: {}", e));
+                }
+            }
 
-                    if let Some(head) = get_git_head(&dir) {
-                        lifecycle_log(&format!("HEAD after pull: {}", head));
-                    }
+            let sync_config = SyncConfig::load(&dir);
+            let sync_interval = sync_config.as_ref().map_or(0, |c| c.interval_minutes);
+            app.manage(SyncState {
+                git: sync_config.map(|c| Mutex::new(GitSync::new(&dir, c))),
+                last: Mutex::new(None),
+            });
 
-                    // Step 2: npm install if needed
-                    send_status(&app_handle, "Checking dependencies...");
-                    let deps_installed = run_npm_install(&dir);
-                    if deps_installed {
-                        send_status(&app_handle, "Dependencies updated");
+            let mut watcher = None;
+            if let Some(folder) = watch_folder {
+                let replay = |h: &tauri::AppHandle| {
+                    let result = commands::replay_folder_sync(h, &h.state::<StoreState>(), &h.state::<BackupState>());
+                    if let Err(e) = result {
+                        logging::warn(&format!("Folder sync failed: {}", e));
                     }
+                };
+                replay(app.handle());
+                let watch_handle = app.handle().clone();
+                match folder_sync::watch(&folder, move || replay(&watch_handle)) {
+                    Ok(w) => watcher = Some(w),
+                    Err(e) => logging::warn(&format!("Folder sync: {}", e)),
+                }
+            }
+            app.manage(FolderWatchState(Mutex::new(watcher)));
 
-                    // Step 3: ALWAYS rebuild — eliminates all stale-code issues.
-                    send_status(&app_handle, "Building application...");
-                    if !run_next_build(&dir) {
-                        lifecycle_log("BUILD FAILED — this is the likely cause of stale code");
-                        send_status(&app_handle, "Build failed — starting with previous version");
+            if interval_minutes > 0 {
+                let backup_handle = app.handle().clone();
+                tauri::async_runtime::spawn(async move {
+                    let period = std::time::Duration::from_secs(interval_minutes * 60);
+                    loop {
+                        tokio::time::sleep(period).await;
+                        let backups = backup_handle.state::<BackupState>();
+                        let contents = backup_handle
+                            .state::<StoreState>()
+                            .0
+                            .lock()
+                            .unwrap()
+                            .contents()
+                            .map_err(|e| e.to_string());
+                        if let Err(e) = contents.and_then(|c| backups.0.snapshot_if_changed("interval", &c)) {
+                            logging::warn(&format!("Periodic backup failed: {}", e));
+                        }
                     }
+                });
+            }
 
-                    // Step 4: Find available port
-                    send_status(&app_handle, "Starting server...");
-                    let port = find_available_port(3000);
-                    lifecycle_log(&format!("Using port: {}", port));
-
-                    // Step 5: Start Next.js server
-                    match start_nextjs_server(&dir, port) {
-                        Ok(child) => {
-                            let state = app_handle.state::<ServerProcess>();
-                            *state.0.lock().unwrap() = Some(child);
-
-                            // Step 6: Wait for server
-                            send_status(&app_handle, "Almost ready...");
-                            match wait_for_server(port, 60).await {
-                                Ok(()) => {
-                                    lifecycle_log(&format!("Navigating webview to http://localhost:{}", port));
-                                    if let Some(window) = app_handle.get_webview_window("main") {
-                                        let url = format!("http://localhost:{}", port);
-                                        let _ = window.navigate(url.parse().unwrap());
-                                        tokio::time::sleep(
-                                            std::time::Duration::from_millis(500),
-                                        )
-                                        .await;
-                                        let _ = window.set_focus();
-                                    }
-                                }
-                                Err(e) => {
-                                    lifecycle_log(&format!("Server failed to start: {}", e));
-                                    send_status(&app_handle, "Server failed to start");
-                                }
-                            }
-                        }
-                        Err(e) => {
-                            lifecycle_log(&format!("Failed to start server: {}", e));
-                            send_status(&app_handle, "Failed to start server");
-                        }
+            if sync_interval > 0 {
+                let sync_handle = app.handle().clone();
+                tauri::async_runtime::spawn(async move {
+                    let period = std::time::Duration::from_secs(sync_interval * 60);
+                    loop {
+                        tokio::time::sleep(period).await;
+                        let h = sync_handle.clone();
+                        // Errors are logged and kept in the sync status.
+                        let _ = tauri::async_runtime::spawn_blocking(move || {
+                            commands::sync_data(
+                                &h,
+                                &h.state::<StoreState>(),
+                                &h.state::<BackupState>(),
+                                &h.state::<SyncState>(),
+                            )
+                        })
+                        .await;
                     }
+                });
+            }
 
-                    lifecycle_log("=== Lifecycle complete");
-                }
-            });
+            if cfg!(debug_assertions) {
+                tauri::async_runtime::spawn(async move {
+                    logging::info("Dev mode — server managed by Tauri CLI");
+                    tokio::time::sleep(std::time::Duration::from_millis(1000)).await;
+                    if let Some(window) = app_handle.get_webview_window("main") {
+                        let _ = window.show();
+                        let _ = window.set_focus();
+                    }
+                });
+            } else {
+                // Production mode: full lifecycle management
+                tauri::async_runtime::spawn_blocking(move || {
+                    run_production_launch(&app_handle, &dir);
+                });
+            }
 
             Ok(())
         })
@@ -548,6 +1175,7 @@ pub fn run() {
 
     app.run(move |app_handle, event| match event {
         tauri::RunEvent::ExitRequested { .. } | tauri::RunEvent::Exit => {
+            SERVER_STOPPING.store(true, Ordering::SeqCst);
             let mut child_opt = app_handle
                 .state::<ServerProcess>()
                 .0
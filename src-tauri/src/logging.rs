@@ -0,0 +1,423 @@
+//! App log — leveled records written as JSON lines to one file per launch,
+//! and echoed to stdout. The log directory is `logs` in the platform app-data
+//! directory (`~/Library/Application Support/com.totaltpm.app/logs` on
+//! macOS), or `TOTAL_TPM_LOG_DIR` if set:
+//!
+//! ```text
+//! {"ts":"2026-10-17T09:30:12.345Z","level":"info","msg":"Stage 'git-pull' finished","durationMs":812,"outcome":"succeeded","stage":"git-pull"}
+//! ```
+//!
+//! Each launch writes `launch-<UTC time>.jsonl`, continuing in
+//! `launch-<UTC time>.1.jsonl` and so on past `MAX_FILE_BYTES`; a long run
+//! keeps its first file and the most recent ones. The last `KEEP_LAUNCHES`
+//! launches are kept, less any older than `MAX_AGE`.
+//!
+//! `TOTAL_TPM_LOG_LEVEL` (`debug`, `info`, `warn` or `error`) sets the
+//! lowest level written; the default is `info`.
+
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::fs::File;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::{Mutex, PoisonError};
+use std::time::Duration;
+
+/// Matches `identifier` in `tauri.conf.json`.
+const APP_IDENTIFIER: &str = "com.totaltpm.app";
+pub const LOG_DIR_ENV: &str = "TOTAL_TPM_LOG_DIR";
+pub const LOG_LEVEL_ENV: &str = "TOTAL_TPM_LOG_LEVEL";
+const MAX_FILE_BYTES: u64 = 5 * 1024 * 1024;
+/// Files kept per launch, including the first.
+const MAX_PARTS: u32 = 4;
+const KEEP_LAUNCHES: usize = 10;
+const MAX_AGE: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Level {
+    Debug,
+    Info,
+    Warn,
+    Error,
+}
+
+impl Level {
+    fn parse(name: &str) -> Option<Self> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "debug" => Some(Level::Debug),
+            "info" => Some(Level::Info),
+            "warn" | "warning" => Some(Level::Warn),
+            "error" => Some(Level::Error),
+            _ => None,
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Level::Debug => "DEBUG",
+            Level::Info => "INFO ",
+            Level::Warn => "WARN ",
+            Level::Error => "ERROR",
+        }
+    }
+}
+
+static MIN_LEVEL: AtomicU8 = AtomicU8::new(Level::Info as u8);
+/// `None` until `init`; records before then only go to stdout.
+static LOGGER: Mutex<Option<Logger>> = Mutex::new(None);
+
+pub fn debug(msg: &str) {
+    event(Level::Debug, msg).emit();
+}
+
+pub fn info(msg: &str) {
+    event(Level::Info, msg).emit();
+}
+
+pub fn warn(msg: &str) {
+    event(Level::Warn, msg).emit();
+}
+
+pub fn error(msg: &str) {
+    event(Level::Error, msg).emit();
+}
+
+/// A record with structured fields, written by `emit`.
+pub fn event(level: Level, msg: impl Into<String>) -> Event {
+    Event {
+        level,
+        msg: msg.into(),
+        fields: Map::new(),
+    }
+}
+
+#[must_use = "the record is only written by emit()"]
+pub struct Event {
+    level: Level,
+    msg: String,
+    fields: Map<String, Value>,
+}
+
+impl Event {
+    pub fn field(mut self, key: &str, value: impl Into<Value>) -> Self {
+        self.fields.insert(key.to_string(), value.into());
+        self
+    }
+
+    pub fn stage(self, name: &str) -> Self {
+        self.field("stage", name)
+    }
+
+    pub fn duration(self, elapsed: Duration) -> Self {
+        self.field("durationMs", elapsed.as_millis() as u64)
+    }
+
+    pub fn pid(self, pid: u32) -> Self {
+        self.field("pid", pid)
+    }
+
+    pub fn port(self, port: u16) -> Self {
+        self.field("port", port)
+    }
+
+    pub fn emit(self) {
+        if (self.level as u8) < MIN_LEVEL.load(Ordering::Relaxed) {
+            return;
+        }
+        let ts = timestamp();
+        let mut text = format!("{} {} {}", ts, self.level.label(), self.msg);
+        for (key, value) in &self.fields {
+            match value {
+                Value::String(s) => text.push_str(&format!(" {}={}", key, s)),
+                other => text.push_str(&format!(" {}={}", key, other)),
+            }
+        }
+        println!("{}", text);
+
+        let line = record(&ts, self.level, &self.msg, &self.fields);
+        if let Some(logger) = LOGGER
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .as_mut()
+        {
+            logger.append(line.as_bytes());
+        }
+    }
+}
+
+/// One JSON line, with `ts`, `level` and `msg` ahead of the fields.
+fn record(ts: &str, level: Level, msg: &str, fields: &Map<String, Value>) -> String {
+    #[derive(Serialize)]
+    struct Record<'a> {
+        ts: &'a str,
+        level: Level,
+        msg: &'a str,
+        #[serde(flatten)]
+        fields: &'a Map<String, Value>,
+    }
+    let mut line = serde_json::to_string(&Record {
+        ts,
+        level,
+        msg,
+        fields,
+    })
+    .unwrap_or_default();
+    line.push('\n');
+    line
+}
+
+/// `TOTAL_TPM_LOG_DIR`, or `logs` in the platform app-data directory.
+pub fn log_dir() -> Option<PathBuf> {
+    match std::env::var_os(LOG_DIR_ENV) {
+        Some(dir) if !dir.is_empty() => Some(PathBuf::from(dir)),
+        _ => dirs::data_dir().map(|d| d.join(APP_IDENTIFIER).join("logs")),
+    }
+}
+
+/// Start this launch's file in `dir` and prune old launches. Returns the
+/// file's path.
+pub fn init(dir: &Path) -> Result<PathBuf, String> {
+    if let Some(level) = std::env::var(LOG_LEVEL_ENV)
+        .ok()
+        .as_deref()
+        .and_then(Level::parse)
+    {
+        MIN_LEVEL.store(level as u8, Ordering::Relaxed);
+    }
+    std::fs::create_dir_all(dir)
+        .map_err(|e| format!("Failed to create log directory {}: {}", dir.display(), e))?;
+    let logger = Logger::new(dir, &compact_timestamp());
+    let path = logger.path(0);
+    prune(dir, &logger.launch, KEEP_LAUNCHES, MAX_AGE);
+    *LOGGER.lock().unwrap_or_else(PoisonError::into_inner) = Some(logger);
+    Ok(path)
+}
+
+struct Logger {
+    dir: PathBuf,
+    launch: String,
+    part: u32,
+    file: Option<File>,
+    size: u64,
+    max_bytes: u64,
+}
+
+impl Logger {
+    fn new(dir: &Path, launch: &str) -> Self {
+        Logger {
+            dir: dir.to_path_buf(),
+            launch: launch.to_string(),
+            part: 0,
+            file: None,
+            size: 0,
+            max_bytes: MAX_FILE_BYTES,
+        }
+    }
+
+    fn path(&self, part: u32) -> PathBuf {
+        let name = match part {
+            0 => format!("launch-{}.jsonl", self.launch),
+            n => format!("launch-{}.{}.jsonl", self.launch, n),
+        };
+        self.dir.join(name)
+    }
+
+    fn append(&mut self, line: &[u8]) {
+        if self.size > 0 && self.size + line.len() as u64 > self.max_bytes {
+            self.file = None;
+            self.size = 0;
+            self.part += 1;
+            // Keep the first file, with the launch sequence, and the latest.
+            if self.part >= MAX_PARTS {
+                let _ = std::fs::remove_file(self.path(self.part - (MAX_PARTS - 1)));
+            }
+        }
+        if self.file.is_none() {
+            let opened = std::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(self.path(self.part));
+            match opened {
+                Ok(file) => {
+                    self.size = file.metadata().map(|m| m.len()).unwrap_or(0);
+                    self.file = Some(file);
+                }
+                // Try again on the next record.
+                Err(_) => return,
+            }
+        }
+        if let Some(file) = &mut self.file {
+            match file.write_all(line) {
+                Ok(()) => self.size += line.len() as u64,
+                Err(_) => self.file = None,
+            }
+        }
+    }
+}
+
+/// Delete the files of all but the newest `keep` launches, and of launches
+/// last written more than `max_age` ago. `current` is always kept.
+fn prune(dir: &Path, current: &str, keep: usize, max_age: Duration) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    // Launch time -> its files, oldest launch first.
+    let mut launches: std::collections::BTreeMap<String, Vec<PathBuf>> = Default::default();
+    for entry in entries.filter_map(|e| e.ok()) {
+        let name = entry.file_name().to_string_lossy().to_string();
+        let Some(launch) = name
+            .strip_prefix("launch-")
+            .filter(|_| name.ends_with(".jsonl"))
+            .and_then(|rest| rest.split('.').next())
+        else {
+            continue;
+        };
+        launches
+            .entry(launch.to_string())
+            .or_default()
+            .push(entry.path());
+    }
+    let now = std::time::SystemTime::now();
+    let count = launches.len();
+    for (i, (launch, files)) in launches.into_iter().enumerate() {
+        if launch == current {
+            continue;
+        }
+        let last_written = files
+            .iter()
+            .filter_map(|f| f.metadata().and_then(|m| m.modified()).ok())
+            .max();
+        let expired =
+            last_written.is_some_and(|t| now.duration_since(t).unwrap_or_default() >= max_age);
+        if i + keep < count || expired {
+            for file in files {
+                let _ = std::fs::remove_file(file);
+            }
+        }
+    }
+}
+
+/// Now as ISO 8601 UTC with milliseconds, e.g. `2026-10-17T09:30:12.345Z`.
+pub fn timestamp() -> String {
+    let millis = now_millis();
+    let (y, m, d) = crate::backup::civil_from_days(millis.div_euclid(86_400_000));
+    let ms = millis.rem_euclid(86_400_000);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+        y,
+        m,
+        d,
+        ms / 3_600_000,
+        ms / 60_000 % 60,
+        ms / 1000 % 60,
+        ms % 1000
+    )
+}
+
+/// Now as compact ISO 8601 UTC, e.g. `20261017T093012Z`; sorts by time.
+fn compact_timestamp() -> String {
+    let secs = now_millis().div_euclid(1000);
+    let (y, m, d) = crate::backup::civil_from_days(secs.div_euclid(86_400));
+    let rem = secs.rem_euclid(86_400);
+    format!(
+        "{:04}{:02}{:02}T{:02}{:02}{:02}Z",
+        y,
+        m,
+        d,
+        rem / 3600,
+        rem / 60 % 60,
+        rem % 60
+    )
+}
+
+fn now_millis() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as i64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+
+    fn names(dir: &Path) -> Vec<String> {
+        let mut names: Vec<String> = std::fs::read_dir(dir)
+            .unwrap()
+            .map(|e| e.unwrap().file_name().to_string_lossy().to_string())
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn writes_fields_after_the_message() {
+        let e = event(Level::Warn, "Stage 'git-pull' finished")
+            .stage("git-pull")
+            .duration(Duration::from_millis(812))
+            .pid(42)
+            .port(3000);
+        let line = record("2026-10-17T09:30:12.345Z", e.level, &e.msg, &e.fields);
+        assert!(line.starts_with(
+            "{\"ts\":\"2026-10-17T09:30:12.345Z\",\"level\":\"warn\",\"msg\":\"Stage 'git-pull' finished\","
+        ));
+        assert!(line.ends_with("}\n") && !line.trim_end().contains('\n'));
+        let value: Value = serde_json::from_str(&line).unwrap();
+        assert_eq!(value["stage"], "git-pull");
+        assert_eq!(value["durationMs"], 812);
+        assert_eq!(value["pid"], 42);
+        assert_eq!(value["port"], 3000);
+        assert_eq!(Level::parse(" WARNING"), Some(Level::Warn));
+        assert!(Level::Debug < Level::Info);
+    }
+
+    #[test]
+    fn rolls_over_keeping_the_first_and_latest_files() {
+        let dir = TempDir::new("logging-parts");
+        let mut logger = Logger::new(&dir, "20261017T093012Z");
+        logger.max_bytes = 10;
+        for _ in 0..MAX_PARTS + 2 {
+            logger.append(b"0123456789\n");
+        }
+        assert_eq!(
+            names(&dir),
+            [
+                "launch-20261017T093012Z.3.jsonl",
+                "launch-20261017T093012Z.4.jsonl",
+                "launch-20261017T093012Z.5.jsonl",
+                "launch-20261017T093012Z.jsonl",
+            ]
+        );
+    }
+
+    #[test]
+    fn prunes_old_launches() {
+        let dir = TempDir::new("logging-prune");
+        for name in [
+            "launch-20261001T000000Z.jsonl",
+            "launch-20261001T000000Z.1.jsonl",
+            "launch-20261002T000000Z.jsonl",
+            "launch-20261003T000000Z.jsonl",
+            "launch-20261004T000000Z.jsonl",
+            "notes.txt",
+        ] {
+            std::fs::write(dir.join(name), "{}\n").unwrap();
+        }
+        prune(&dir, "20261004T000000Z", 2, MAX_AGE);
+        assert_eq!(
+            names(&dir),
+            [
+                "launch-20261003T000000Z.jsonl",
+                "launch-20261004T000000Z.jsonl",
+                "notes.txt",
+            ]
+        );
+
+        // Everything but the current launch has expired.
+        prune(&dir, "20261004T000000Z", 10, Duration::ZERO);
+        assert_eq!(names(&dir), ["launch-20261004T000000Z.jsonl", "notes.txt"]);
+    }
+}
@@ -0,0 +1,251 @@
+//! Three-way, record-level merge of two versions of `AppData` that share a
+//! common ancestor. Records are matched by `id`; a record changed on only one
+//! side takes that side's version, so concurrent edits to different records
+//! never conflict. When both sides changed the same record differently, the
+//! one with the newer `updatedAt` wins (local on a tie) and the pair is
+//! reported as a conflict. An edit always beats a delete.
+
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+
+use crate::models::{AppData, EntityKind, KanbanCard, KanbanColumn, Note, Project, Task};
+
+pub trait Record: Clone + PartialEq + Serialize {
+    const KIND: EntityKind;
+    fn id(&self) -> &str;
+    /// When the record was last changed. Columns have no `updatedAt`, so
+    /// theirs is `createdAt`.
+    fn updated_at(&self) -> i64;
+}
+
+impl Record for Project {
+    const KIND: EntityKind = EntityKind::Project;
+    fn id(&self) -> &str {
+        &self.id
+    }
+    fn updated_at(&self) -> i64 {
+        self.updated_at
+    }
+}
+
+impl Record for KanbanColumn {
+    const KIND: EntityKind = EntityKind::Column;
+    fn id(&self) -> &str {
+        &self.id
+    }
+    fn updated_at(&self) -> i64 {
+        self.created_at
+    }
+}
+
+impl Record for KanbanCard {
+    const KIND: EntityKind = EntityKind::Card;
+    fn id(&self) -> &str {
+        &self.id
+    }
+    fn updated_at(&self) -> i64 {
+        self.updated_at
+    }
+}
+
+impl Record for Task {
+    const KIND: EntityKind = EntityKind::Task;
+    fn id(&self) -> &str {
+        &self.id
+    }
+    fn updated_at(&self) -> i64 {
+        self.updated_at
+    }
+}
+
+impl Record for Note {
+    const KIND: EntityKind = EntityKind::Note;
+    fn id(&self) -> &str {
+        &self.id
+    }
+    fn updated_at(&self) -> i64 {
+        self.updated_at
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Side {
+    Local,
+    Remote,
+}
+
+/// A record both sides changed. `None` means that side deleted it.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Conflict {
+    pub entity: EntityKind,
+    pub id: String,
+    pub kept: Side,
+    pub local: Option<serde_json::Value>,
+    pub remote: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Clone)]
+pub struct MergeResult {
+    pub data: AppData,
+    pub conflicts: Vec<Conflict>,
+}
+
+/// Merge `local` and `remote`, both descended from `base`.
+/// `currentProjectId` is a per-device setting and always comes from `local`.
+pub fn merge(base: &AppData, local: &AppData, remote: &AppData) -> MergeResult {
+    let mut conflicts = Vec::new();
+    let data = AppData {
+        schema_version: local.schema_version,
+        projects: merge_records(&base.projects, &local.projects, &remote.projects, &mut conflicts),
+        columns: merge_records(&base.columns, &local.columns, &remote.columns, &mut conflicts),
+        cards: merge_records(&base.cards, &local.cards, &remote.cards, &mut conflicts),
+        tasks: merge_records(&base.tasks, &local.tasks, &remote.tasks, &mut conflicts),
+        notes: merge_records(&base.notes, &local.notes, &remote.notes, &mut conflicts),
+        current_project_id: local.current_project_id.clone(),
+    };
+    MergeResult { data, conflicts }
+}
+
+fn by_id<T: Record>(records: &[T]) -> HashMap<&str, &T> {
+    records.iter().map(|r| (r.id(), r)).collect()
+}
+
+fn to_value<T: Record>(record: Option<&T>) -> Option<serde_json::Value> {
+    record.and_then(|r| serde_json::to_value(r).ok())
+}
+
+/// Result order: local records in local order, then records only the remote
+/// has, in remote order.
+fn merge_records<T: Record>(base: &[T], local: &[T], remote: &[T], conflicts: &mut Vec<Conflict>) -> Vec<T> {
+    let base_by_id = by_id(base);
+    let local_by_id = by_id(local);
+    let remote_by_id = by_id(remote);
+
+    let mut ids: Vec<&str> = Vec::new();
+    let mut seen = HashSet::new();
+    for id in local.iter().chain(remote).chain(base).map(Record::id) {
+        if seen.insert(id) {
+            ids.push(id);
+        }
+    }
+
+    let mut merged = Vec::new();
+    for id in ids {
+        let b = base_by_id.get(id).copied();
+        let l = local_by_id.get(id).copied();
+        let r = remote_by_id.get(id).copied();
+
+        let chosen = if l == r || r == b {
+            l
+        } else if l == b {
+            r
+        } else {
+            // Both sides changed it, differently.
+            let kept = match (l, r) {
+                (Some(_), None) => Side::Local,
+                (None, Some(_)) => Side::Remote,
+                (Some(lr), Some(rr)) if rr.updated_at() > lr.updated_at() => Side::Remote,
+                _ => Side::Local,
+            };
+            conflicts.push(Conflict {
+                entity: T::KIND,
+                id: id.to_string(),
+                kept,
+                local: to_value(l),
+                remote: to_value(r),
+            });
+            match kept {
+                Side::Local => l,
+                Side::Remote => r,
+            }
+        };
+        if let Some(record) = chosen {
+            merged.push(record.clone());
+        }
+    }
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn project(id: &str, name: &str, updated_at: i64) -> Project {
+        Project {
+            id: id.to_string(),
+            name: name.to_string(),
+            description: None,
+            created_at: 0,
+            updated_at,
+        }
+    }
+
+    fn data(projects: Vec<Project>) -> AppData {
+        AppData {
+            projects,
+            ..AppData::default()
+        }
+    }
+
+    fn names(data: &AppData) -> Vec<&str> {
+        data.projects.iter().map(|p| p.name.as_str()).collect()
+    }
+
+    #[test]
+    fn independent_changes_merge_cleanly() {
+        let base = data(vec![project("a", "A", 1), project("b", "B", 1)]);
+        let local = data(vec![project("a", "A local", 2), project("b", "B", 1), project("c", "C", 2)]);
+        let remote = data(vec![project("a", "A", 1), project("d", "D", 3)]);
+
+        let result = merge(&base, &local, &remote);
+        assert!(result.conflicts.is_empty());
+        // "b" was deleted remotely and untouched locally.
+        assert_eq!(names(&result.data), ["A local", "C", "D"]);
+    }
+
+    #[test]
+    fn identical_changes_are_not_conflicts() {
+        let base = data(vec![project("a", "A", 1)]);
+        let both = data(vec![project("a", "A2", 2)]);
+        let result = merge(&base, &both, &both);
+        assert!(result.conflicts.is_empty());
+        assert_eq!(result.data, both);
+    }
+
+    #[test]
+    fn newer_edit_wins_a_conflict() {
+        let base = data(vec![project("a", "A", 1)]);
+        let local = data(vec![project("a", "A local", 5)]);
+        let remote = data(vec![project("a", "A remote", 7)]);
+
+        let result = merge(&base, &local, &remote);
+        assert_eq!(names(&result.data), ["A remote"]);
+        assert_eq!(result.conflicts.len(), 1);
+        assert_eq!(result.conflicts[0].kept, Side::Remote);
+        assert_eq!(result.conflicts[0].entity, EntityKind::Project);
+    }
+
+    #[test]
+    fn edit_beats_delete() {
+        let base = data(vec![project("a", "A", 1)]);
+        let local = data(vec![]);
+        let remote = data(vec![project("a", "A remote", 2)]);
+
+        let result = merge(&base, &local, &remote);
+        assert_eq!(names(&result.data), ["A remote"]);
+        assert_eq!(result.conflicts[0].kept, Side::Remote);
+        assert_eq!(result.conflicts[0].local, None);
+    }
+
+    #[test]
+    fn current_project_stays_local() {
+        let mut local = data(vec![]);
+        local.current_project_id = Some("mine".into());
+        let mut remote = data(vec![]);
+        remote.current_project_id = Some("theirs".into());
+        let result = merge(&AppData::default(), &local, &remote);
+        assert_eq!(result.data.current_project_id.as_deref(), Some("mine"));
+    }
+}
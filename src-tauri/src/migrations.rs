@@ -0,0 +1,237 @@
+//! Schema migrations for `app-data.json`. The document carries a
+//! `schemaVersion`; files without one are version 0 (everything written
+//! before versioning, including files saved through the Next.js API route).
+//!
+//! Migrations run on the raw JSON before it is deserialized into `AppData`,
+//! in order, each taking the document from `to - 1` to `to`. They must be
+//! idempotent: the browser version of the app strips `schemaVersion` on save,
+//! so an already-migrated file can come back as version 0.
+//!
+//! To change the schema, add a migration to the end of `MIGRATIONS` and bump
+//! nothing else — `CURRENT_VERSION` follows the list.
+
+use serde_json::{Map, Value};
+
+use crate::error::StoreError;
+
+struct Migration {
+    to: u32,
+    description: &'static str,
+    apply: fn(&mut Map<String, Value>),
+}
+
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        to: 1,
+        description: "ensure top-level collections exist",
+        apply: ensure_collections,
+    },
+    Migration {
+        to: 2,
+        description: "backfill card columnChangedAt",
+        apply: backfill_column_changed_at,
+    },
+    Migration {
+        to: 3,
+        description: "default task status/priority and note content",
+        apply: default_task_and_note_fields,
+    },
+];
+
+pub const CURRENT_VERSION: u32 = MIGRATIONS[MIGRATIONS.len() - 1].to;
+
+#[derive(Debug)]
+pub struct Migrated {
+    pub doc: Value,
+    /// Version the document had before migrating.
+    pub from: u32,
+}
+
+impl Migrated {
+    pub fn changed(&self) -> bool {
+        self.from != CURRENT_VERSION
+    }
+}
+
+pub fn schema_version(doc: &Value) -> Result<u32, StoreError> {
+    match doc.get("schemaVersion") {
+        None | Some(Value::Null) => Ok(0),
+        Some(v) => v
+            .as_u64()
+            .and_then(|v| u32::try_from(v).ok())
+            .ok_or_else(|| StoreError::invalid(format!("Invalid schemaVersion: {}", v))),
+    }
+}
+
+/// Bring a document up to `CURRENT_VERSION`. Documents from a newer version of
+/// the app are rejected rather than loaded and silently downgraded on save.
+pub fn migrate(mut doc: Value) -> Result<Migrated, StoreError> {
+    let from = schema_version(&doc)?;
+    if from > CURRENT_VERSION {
+        return Err(StoreError::invalid(format!(
+            "Data file has schema version {}, but this version of the app only supports up to {}. Update the app to open it.",
+            from, CURRENT_VERSION
+        )));
+    }
+    let Some(map) = doc.as_object_mut() else {
+        return Err(StoreError::invalid("Data file is not a JSON object"));
+    };
+
+    for migration in MIGRATIONS.iter().filter(|m| m.to > from) {
+        crate::logging::info(&format!(
+            "Migrating data to schema v{}: {}",
+            migration.to, migration.description
+        ));
+        (migration.apply)(map);
+    }
+    map.insert("schemaVersion".into(), Value::from(CURRENT_VERSION));
+
+    Ok(Migrated { doc, from })
+}
+
+fn records_mut<'a>(map: &'a mut Map<String, Value>, key: &str) -> impl Iterator<Item = &'a mut Map<String, Value>> {
+    map.get_mut(key)
+        .and_then(Value::as_array_mut)
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_object_mut)
+}
+
+// v1: older files (and the `?? []` fallbacks in `src/lib/api.ts`) could be
+// missing whole collections or have them as null.
+fn ensure_collections(map: &mut Map<String, Value>) {
+    for key in ["projects", "columns", "cards", "tasks", "notes"] {
+        if !map.get(key).is_some_and(Value::is_array) {
+            map.insert(key.into(), Value::Array(Vec::new()));
+        }
+    }
+    if !map.get("currentProjectId").is_some_and(Value::is_string) {
+        map.insert("currentProjectId".into(), Value::Null);
+    }
+}
+
+// v2: `columnChangedAt` was added to cards after launch. Cards from before it
+// existed take the best available guess at when they entered their column.
+fn backfill_column_changed_at(map: &mut Map<String, Value>) {
+    for card in records_mut(map, "cards") {
+        if card.get("columnChangedAt").is_some_and(Value::is_number) {
+            continue;
+        }
+        let fallback = card
+            .get("updatedAt")
+            .or_else(|| card.get("createdAt"))
+            .filter(|v| v.is_number())
+            .cloned()
+            .unwrap_or(Value::from(0));
+        card.insert("columnChangedAt".into(), fallback);
+    }
+}
+
+// v3: early tasks could be saved without a status or priority, and notes
+// without content.
+fn default_task_and_note_fields(map: &mut Map<String, Value>) {
+    for task in records_mut(map, "tasks") {
+        if !task.get("status").is_some_and(Value::is_string) {
+            task.insert("status".into(), Value::from("todo"));
+        }
+        if !task.get("priority").is_some_and(Value::is_string) {
+            task.insert("priority".into(), Value::from("p2"));
+        }
+    }
+    for note in records_mut(map, "notes") {
+        if !note.get("content").is_some_and(Value::is_string) {
+            note.insert("content".into(), Value::from(""));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::AppData;
+    use serde_json::json;
+
+    #[test]
+    fn migrations_are_ordered_and_contiguous() {
+        for (i, m) in MIGRATIONS.iter().enumerate() {
+            assert_eq!(m.to, i as u32 + 1, "migration '{}' is out of order", m.description);
+        }
+    }
+
+    #[test]
+    fn unversioned_file_is_migrated_to_current() {
+        let legacy = json!({
+            "projects": [{ "id": "p1", "name": "P", "createdAt": 1, "updatedAt": 2 }],
+            "cards": [{
+                "id": "c1", "title": "Card", "columnId": "col1", "order": 0,
+                "createdAt": 10, "updatedAt": 20
+            }],
+            "tasks": [{ "id": "t1", "projectId": "p1", "title": "T", "createdAt": 1, "updatedAt": 1 }],
+            "notes": [{ "id": "n1", "projectId": "p1", "title": "N", "createdAt": 1, "updatedAt": 1 }]
+        });
+
+        let migrated = migrate(legacy).unwrap();
+        assert_eq!(migrated.from, 0);
+        assert!(migrated.changed());
+        assert_eq!(migrated.doc["schemaVersion"], json!(CURRENT_VERSION));
+        assert_eq!(migrated.doc["columns"], json!([]));
+        assert_eq!(migrated.doc["currentProjectId"], Value::Null);
+        assert_eq!(migrated.doc["cards"][0]["columnChangedAt"], json!(20));
+        assert_eq!(migrated.doc["tasks"][0]["status"], json!("todo"));
+        assert_eq!(migrated.doc["tasks"][0]["priority"], json!("p2"));
+        assert_eq!(migrated.doc["notes"][0]["content"], json!(""));
+
+        let data: AppData = serde_json::from_value(migrated.doc).unwrap();
+        assert_eq!(data.cards[0].column_changed_at, 20);
+    }
+
+    #[test]
+    fn migrations_keep_existing_values() {
+        let doc = json!({
+            "cards": [{
+                "id": "c1", "title": "Card", "columnId": "col1", "order": 0,
+                "createdAt": 10, "updatedAt": 20, "columnChangedAt": 15
+            }],
+            "tasks": [{
+                "id": "t1", "projectId": "p1", "title": "T", "status": "done",
+                "priority": "p0", "createdAt": 1, "updatedAt": 1
+            }],
+            "currentProjectId": "p1"
+        });
+
+        let migrated = migrate(doc).unwrap();
+        assert_eq!(migrated.doc["cards"][0]["columnChangedAt"], json!(15));
+        assert_eq!(migrated.doc["tasks"][0]["status"], json!("done"));
+        assert_eq!(migrated.doc["tasks"][0]["priority"], json!("p0"));
+        assert_eq!(migrated.doc["currentProjectId"], json!("p1"));
+    }
+
+    #[test]
+    fn migrating_twice_is_a_no_op() {
+        let once = migrate(json!({ "cards": [{ "id": "c1", "createdAt": 5 }] })).unwrap();
+        let mut stripped = once.doc.clone();
+        stripped.as_object_mut().unwrap().remove("schemaVersion");
+        let twice = migrate(stripped).unwrap();
+        assert_eq!(once.doc, twice.doc);
+    }
+
+    #[test]
+    fn current_version_is_left_alone() {
+        let doc = json!({ "schemaVersion": CURRENT_VERSION, "projects": [] });
+        let migrated = migrate(doc.clone()).unwrap();
+        assert!(!migrated.changed());
+        assert_eq!(migrated.doc, doc);
+    }
+
+    #[test]
+    fn newer_version_is_rejected() {
+        let doc = json!({ "schemaVersion": CURRENT_VERSION + 1 });
+        assert!(matches!(migrate(doc), Err(StoreError::Invalid { .. })));
+    }
+
+    #[test]
+    fn non_object_is_rejected() {
+        assert!(migrate(json!([])).is_err());
+        assert!(migrate(json!({ "schemaVersion": "two" })).is_err());
+    }
+}
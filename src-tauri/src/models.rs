@@ -0,0 +1,256 @@
+//! Data model persisted in `data/app-data.json`. Mirrors the interfaces in
+//! `src/types/index.ts` and `AppData` in `src/lib/api.ts` — keep the two in
+//! sync. Timestamps are JavaScript millisecond epochs (`Date.now()`).
+
+use serde::{Deserialize, Deserializer, Serialize};
+
+/// The kinds of record stored in `AppData`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EntityKind {
+    Project,
+    Column,
+    Card,
+    Task,
+    Note,
+}
+
+impl std::fmt::Display for EntityKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            EntityKind::Project => "project",
+            EntityKind::Column => "column",
+            EntityKind::Card => "card",
+            EntityKind::Task => "task",
+            EntityKind::Note => "note",
+        };
+        f.write_str(name)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Project {
+    pub id: String,
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KanbanColumn {
+    pub id: String,
+    pub project_id: String,
+    pub title: String,
+    pub order: i64,
+    pub created_at: i64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ArchiveReason {
+    Archived,
+    Deleted,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KanbanCard {
+    pub id: String,
+    pub title: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub priority: Option<TaskPriority>,
+    pub column_id: String,
+    pub order: i64,
+    pub created_at: i64,
+    pub updated_at: i64,
+    /// When the card entered its current column.
+    pub column_changed_at: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub due_date: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub archived: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub archived_at: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub archive_reason: Option<ArchiveReason>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub linked_task_ids: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub linked_note_ids: Option<Vec<String>>,
+}
+
+impl KanbanCard {
+    pub fn is_archived(&self) -> bool {
+        self.archived.unwrap_or(false)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LinkAttachment {
+    pub url: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum TaskStatus {
+    Todo,
+    InProgress,
+    Done,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TaskPriority {
+    P0,
+    P1,
+    P2,
+    P3,
+    P4,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Task {
+    pub id: String,
+    pub project_id: String,
+    pub title: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    pub status: TaskStatus,
+    pub priority: TaskPriority,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub due_date: Option<i64>,
+    /// Optional link to a KanbanCard. The frontend writes `null` when a card
+    /// is unlinked, so it is always serialized.
+    #[serde(default)]
+    pub card_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub links: Option<Vec<LinkAttachment>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub comments: Option<String>,
+    /// Manual sort order (lower = higher in list).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub order: Option<i64>,
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Note {
+    pub id: String,
+    pub project_id: String,
+    pub title: String,
+    pub content: String,
+    #[serde(default)]
+    pub card_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub links: Option<Vec<LinkAttachment>>,
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+/// The whole persisted document.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AppData {
+    /// Always written as the current version. Documents sent by the webview
+    /// don't carry one and are already in the current shape.
+    #[serde(default = "current_schema_version")]
+    pub schema_version: u32,
+    #[serde(default)]
+    pub projects: Vec<Project>,
+    #[serde(default)]
+    pub columns: Vec<KanbanColumn>,
+    #[serde(default)]
+    pub cards: Vec<KanbanCard>,
+    #[serde(default)]
+    pub tasks: Vec<Task>,
+    #[serde(default)]
+    pub notes: Vec<Note>,
+    #[serde(default)]
+    pub current_project_id: Option<String>,
+}
+
+impl Default for AppData {
+    fn default() -> Self {
+        AppData {
+            schema_version: current_schema_version(),
+            projects: Vec::new(),
+            columns: Vec::new(),
+            cards: Vec::new(),
+            tasks: Vec::new(),
+            notes: Vec::new(),
+            current_project_id: None,
+        }
+    }
+}
+
+impl AppData {
+    /// No records at all, as in a new (or lost) data file.
+    pub fn is_empty(&self) -> bool {
+        self.projects.is_empty()
+            && self.columns.is_empty()
+            && self.cards.is_empty()
+            && self.tasks.is_empty()
+            && self.notes.is_empty()
+    }
+}
+
+fn current_schema_version() -> u32 {
+    crate::migrations::CURRENT_VERSION
+}
+
+/// Partial update of the top-level collections. Absent fields are left
+/// untouched; `currentProjectId: null` clears the current project.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AppDataPatch {
+    pub projects: Option<Vec<Project>>,
+    pub columns: Option<Vec<KanbanColumn>>,
+    pub cards: Option<Vec<KanbanCard>>,
+    pub tasks: Option<Vec<Task>>,
+    pub notes: Option<Vec<Note>>,
+    #[serde(default, deserialize_with = "present")]
+    pub current_project_id: Option<Option<String>>,
+}
+
+impl AppDataPatch {
+    pub fn apply(self, data: &mut AppData) {
+        if let Some(projects) = self.projects {
+            data.projects = projects;
+        }
+        if let Some(columns) = self.columns {
+            data.columns = columns;
+        }
+        if let Some(cards) = self.cards {
+            data.cards = cards;
+        }
+        if let Some(tasks) = self.tasks {
+            data.tasks = tasks;
+        }
+        if let Some(notes) = self.notes {
+            data.notes = notes;
+        }
+        if let Some(current) = self.current_project_id {
+            data.current_project_id = current;
+        }
+    }
+}
+
+/// Distinguish a field that is present-but-null from one that is absent.
+pub(crate) fn present<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
@@ -0,0 +1,952 @@
+//! Entity-level changes to `AppData`. Each operation validates the references
+//! it touches and mirrors the matching action in `src/context/AppContext.tsx`,
+//! so the frontend can send one small change instead of the whole document.
+
+use serde::Deserialize;
+
+use crate::error::StoreError;
+use crate::models::{
+    present, AppData, ArchiveReason, EntityKind, KanbanCard, KanbanColumn, LinkAttachment, Note,
+    Project, Task, TaskPriority, TaskStatus,
+};
+
+/// Columns created with every new project — keep in sync with
+/// `DEFAULT_COLUMNS` in `src/lib/constants.ts`.
+const DEFAULT_COLUMNS: [&str; 3] = ["To Do", "In Progress", "Done"];
+
+pub fn now_millis() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as i64
+}
+
+/// Same shape as `generateId()` in `src/lib/utils.ts`: `<millis>-<base36>`.
+pub fn generate_id() -> String {
+    use std::collections::hash_map::RandomState;
+    use std::hash::{BuildHasher, Hasher};
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u64(COUNTER.fetch_add(1, Ordering::Relaxed));
+    let mut n = hasher.finish();
+
+    let mut suffix = String::with_capacity(9);
+    for _ in 0..9 {
+        let digit = (n % 36) as u32;
+        suffix.push(std::char::from_digit(digit, 36).unwrap_or('0'));
+        n /= 36;
+    }
+    format!("{}-{}", now_millis(), suffix)
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NewProject {
+    pub name: String,
+    pub description: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProjectUpdate {
+    pub name: Option<String>,
+    #[serde(default, deserialize_with = "present")]
+    pub description: Option<Option<String>>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NewCard {
+    pub column_id: String,
+    pub title: String,
+    pub description: Option<String>,
+    pub priority: Option<TaskPriority>,
+    pub due_date: Option<i64>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CardUpdate {
+    pub title: Option<String>,
+    #[serde(default, deserialize_with = "present")]
+    pub description: Option<Option<String>>,
+    #[serde(default, deserialize_with = "present")]
+    pub priority: Option<Option<TaskPriority>>,
+    #[serde(default, deserialize_with = "present")]
+    pub due_date: Option<Option<i64>>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NewTask {
+    pub project_id: String,
+    pub title: String,
+    pub description: Option<String>,
+    pub status: Option<TaskStatus>,
+    pub priority: Option<TaskPriority>,
+    pub due_date: Option<i64>,
+    pub card_id: Option<String>,
+    pub links: Option<Vec<LinkAttachment>>,
+    pub comments: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TaskUpdate {
+    pub title: Option<String>,
+    #[serde(default, deserialize_with = "present")]
+    pub description: Option<Option<String>>,
+    pub status: Option<TaskStatus>,
+    pub priority: Option<TaskPriority>,
+    #[serde(default, deserialize_with = "present")]
+    pub due_date: Option<Option<i64>>,
+    #[serde(default, deserialize_with = "present")]
+    pub card_id: Option<Option<String>>,
+    #[serde(default, deserialize_with = "present")]
+    pub links: Option<Option<Vec<LinkAttachment>>>,
+    #[serde(default, deserialize_with = "present")]
+    pub comments: Option<Option<String>>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NewNote {
+    pub project_id: String,
+    pub title: String,
+    pub content: Option<String>,
+    pub card_id: Option<String>,
+    pub links: Option<Vec<LinkAttachment>>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NoteUpdate {
+    pub title: Option<String>,
+    pub content: Option<String>,
+    #[serde(default, deserialize_with = "present")]
+    pub card_id: Option<Option<String>>,
+    #[serde(default, deserialize_with = "present")]
+    pub links: Option<Option<Vec<LinkAttachment>>>,
+}
+
+impl AppData {
+    // Lookups
+
+    pub fn project_index(&self, id: &str) -> Result<usize, StoreError> {
+        self.projects
+            .iter()
+            .position(|p| p.id == id)
+            .ok_or_else(|| StoreError::not_found(EntityKind::Project, id))
+    }
+
+    pub fn column_index(&self, id: &str) -> Result<usize, StoreError> {
+        self.columns
+            .iter()
+            .position(|c| c.id == id)
+            .ok_or_else(|| StoreError::not_found(EntityKind::Column, id))
+    }
+
+    pub fn card_index(&self, id: &str) -> Result<usize, StoreError> {
+        self.cards
+            .iter()
+            .position(|c| c.id == id)
+            .ok_or_else(|| StoreError::not_found(EntityKind::Card, id))
+    }
+
+    pub fn task_index(&self, id: &str) -> Result<usize, StoreError> {
+        self.tasks
+            .iter()
+            .position(|t| t.id == id)
+            .ok_or_else(|| StoreError::not_found(EntityKind::Task, id))
+    }
+
+    pub fn note_index(&self, id: &str) -> Result<usize, StoreError> {
+        self.notes
+            .iter()
+            .position(|n| n.id == id)
+            .ok_or_else(|| StoreError::not_found(EntityKind::Note, id))
+    }
+
+    /// The project a card belongs to, via its column.
+    pub fn card_project_id(&self, card: &KanbanCard) -> Option<&str> {
+        self.columns
+            .iter()
+            .find(|c| c.id == card.column_id)
+            .map(|c| c.project_id.as_str())
+    }
+
+    // Reference checks
+
+    fn check_project_ref(&self, entity: EntityKind, project_id: &str) -> Result<(), StoreError> {
+        if self.projects.iter().any(|p| p.id == project_id) {
+            Ok(())
+        } else {
+            Err(StoreError::invalid_reference(
+                entity,
+                "projectId",
+                EntityKind::Project,
+                project_id,
+            ))
+        }
+    }
+
+    fn check_column_ref(&self, entity: EntityKind, column_id: &str) -> Result<(), StoreError> {
+        if self.columns.iter().any(|c| c.id == column_id) {
+            Ok(())
+        } else {
+            Err(StoreError::invalid_reference(
+                entity,
+                "columnId",
+                EntityKind::Column,
+                column_id,
+            ))
+        }
+    }
+
+    /// A task or note may only link to a live (not archived) card in its own
+    /// project. Archiving a card unlinks its tasks and notes.
+    fn check_card_ref(
+        &self,
+        entity: EntityKind,
+        project_id: &str,
+        card_id: Option<&str>,
+    ) -> Result<(), StoreError> {
+        let Some(card_id) = card_id else {
+            return Ok(());
+        };
+        let valid = self
+            .cards
+            .iter()
+            .find(|c| c.id == card_id)
+            .is_some_and(|c| c.archived != Some(true) && self.card_project_id(c) == Some(project_id));
+        if valid {
+            Ok(())
+        } else {
+            Err(StoreError::invalid_reference(
+                entity,
+                "cardId",
+                EntityKind::Card,
+                card_id,
+            ))
+        }
+    }
+
+    // Projects
+
+    pub fn create_project(&mut self, input: NewProject) -> Result<Project, StoreError> {
+        if input.name.trim().is_empty() {
+            return Err(StoreError::invalid("Project name cannot be empty"));
+        }
+        let now = now_millis();
+        let project = Project {
+            id: generate_id(),
+            name: input.name,
+            description: input.description,
+            created_at: now,
+            updated_at: now,
+        };
+        for (index, title) in DEFAULT_COLUMNS.iter().enumerate() {
+            self.columns.push(KanbanColumn {
+                id: generate_id(),
+                project_id: project.id.clone(),
+                title: title.to_string(),
+                order: index as i64,
+                created_at: now,
+            });
+        }
+        self.projects.push(project.clone());
+        self.current_project_id = Some(project.id.clone());
+        Ok(project)
+    }
+
+    pub fn update_project(&mut self, id: &str, update: ProjectUpdate) -> Result<Project, StoreError> {
+        let idx = self.project_index(id)?;
+        let project = &mut self.projects[idx];
+        if let Some(name) = update.name {
+            if name.trim().is_empty() {
+                return Err(StoreError::invalid("Project name cannot be empty"));
+            }
+            project.name = name;
+        }
+        if let Some(description) = update.description {
+            project.description = description;
+        }
+        project.updated_at = now_millis();
+        Ok(project.clone())
+    }
+
+    /// Delete a project with all of its columns, cards, tasks and notes.
+    pub fn delete_project(&mut self, id: &str) -> Result<(), StoreError> {
+        self.project_index(id)?;
+        let column_ids: Vec<String> = self
+            .columns
+            .iter()
+            .filter(|c| c.project_id == id)
+            .map(|c| c.id.clone())
+            .collect();
+        self.projects.retain(|p| p.id != id);
+        self.columns.retain(|c| c.project_id != id);
+        self.cards.retain(|c| !column_ids.contains(&c.column_id));
+        self.tasks.retain(|t| t.project_id != id);
+        self.notes.retain(|n| n.project_id != id);
+        if self.current_project_id.as_deref() == Some(id) {
+            self.current_project_id = self.projects.first().map(|p| p.id.clone());
+        }
+        Ok(())
+    }
+
+    // Columns
+
+    pub fn create_column(&mut self, project_id: &str, title: String) -> Result<KanbanColumn, StoreError> {
+        self.check_project_ref(EntityKind::Column, project_id)?;
+        let max_order = self
+            .columns
+            .iter()
+            .filter(|c| c.project_id == project_id)
+            .map(|c| c.order)
+            .max()
+            .unwrap_or(-1);
+        let column = KanbanColumn {
+            id: generate_id(),
+            project_id: project_id.to_string(),
+            title,
+            order: max_order + 1,
+            created_at: now_millis(),
+        };
+        self.columns.push(column.clone());
+        Ok(column)
+    }
+
+    pub fn rename_column(&mut self, id: &str, title: String) -> Result<KanbanColumn, StoreError> {
+        let idx = self.column_index(id)?;
+        self.columns[idx].title = title;
+        Ok(self.columns[idx].clone())
+    }
+
+    /// Move a column to `index` among its project's columns and renumber them.
+    pub fn move_column(&mut self, id: &str, index: usize) -> Result<Vec<KanbanColumn>, StoreError> {
+        let idx = self.column_index(id)?;
+        let project_id = self.columns[idx].project_id.clone();
+
+        let mut siblings: Vec<&KanbanColumn> = self
+            .columns
+            .iter()
+            .filter(|c| c.project_id == project_id && c.id != id)
+            .collect();
+        siblings.sort_by_key(|c| c.order);
+        let mut order: Vec<String> = siblings.into_iter().map(|c| c.id.clone()).collect();
+        order.insert(index.min(order.len()), id.to_string());
+
+        for column in self.columns.iter_mut().filter(|c| c.project_id == project_id) {
+            column.order = order.iter().position(|cid| *cid == column.id).unwrap_or(0) as i64;
+        }
+        let mut result: Vec<KanbanColumn> = self
+            .columns
+            .iter()
+            .filter(|c| c.project_id == project_id)
+            .cloned()
+            .collect();
+        result.sort_by_key(|c| c.order);
+        Ok(result)
+    }
+
+    /// Delete a column and its cards. Tasks and notes linked to those cards
+    /// are unlinked, not deleted.
+    pub fn delete_column(&mut self, id: &str) -> Result<(), StoreError> {
+        self.column_index(id)?;
+        let card_ids: Vec<String> = self
+            .cards
+            .iter()
+            .filter(|c| c.column_id == id)
+            .map(|c| c.id.clone())
+            .collect();
+        self.columns.retain(|c| c.id != id);
+        self.cards.retain(|c| c.column_id != id);
+        self.unlink_cards(&card_ids);
+        Ok(())
+    }
+
+    // Cards
+
+    pub fn create_card(&mut self, input: NewCard) -> Result<KanbanCard, StoreError> {
+        self.check_column_ref(EntityKind::Card, &input.column_id)?;
+        let max_order = self
+            .cards
+            .iter()
+            .filter(|c| c.column_id == input.column_id)
+            .map(|c| c.order)
+            .max()
+            .unwrap_or(-1);
+        let now = now_millis();
+        let card = KanbanCard {
+            id: generate_id(),
+            title: input.title,
+            description: input.description,
+            priority: Some(input.priority.unwrap_or(TaskPriority::P2)),
+            column_id: input.column_id,
+            order: max_order + 1,
+            created_at: now,
+            updated_at: now,
+            column_changed_at: now,
+            due_date: input.due_date,
+            archived: None,
+            archived_at: None,
+            archive_reason: None,
+            linked_task_ids: None,
+            linked_note_ids: None,
+        };
+        self.cards.push(card.clone());
+        Ok(card)
+    }
+
+    pub fn update_card(&mut self, id: &str, update: CardUpdate) -> Result<KanbanCard, StoreError> {
+        let idx = self.card_index(id)?;
+        let card = &mut self.cards[idx];
+        if let Some(title) = update.title {
+            card.title = title;
+        }
+        if let Some(description) = update.description {
+            card.description = description;
+        }
+        if let Some(priority) = update.priority {
+            card.priority = priority;
+        }
+        if let Some(due_date) = update.due_date {
+            card.due_date = due_date;
+        }
+        card.updated_at = now_millis();
+        Ok(card.clone())
+    }
+
+    /// Move a card to `index` within `column_id` and renumber that column,
+    /// and the column it left. Moving between columns resets
+    /// `columnChangedAt`.
+    pub fn move_card(&mut self, id: &str, column_id: &str, index: usize) -> Result<KanbanCard, StoreError> {
+        let idx = self.card_index(id)?;
+        self.check_column_ref(EntityKind::Card, column_id)?;
+
+        let source_project = self.card_project_id(&self.cards[idx]).map(str::to_string);
+        let target_project = self
+            .columns
+            .iter()
+            .find(|c| c.id == column_id)
+            .map(|c| c.project_id.clone());
+        if source_project.is_some() && source_project != target_project {
+            return Err(StoreError::invalid("Cards cannot be moved between projects"));
+        }
+
+        let now = now_millis();
+        let mut siblings: Vec<&KanbanCard> = self
+            .cards
+            .iter()
+            .filter(|c| c.column_id == column_id && c.id != id)
+            .collect();
+        siblings.sort_by_key(|c| c.order);
+        let mut order: Vec<String> = siblings.into_iter().map(|c| c.id.clone()).collect();
+        order.insert(index.min(order.len()), id.to_string());
+
+        let card = &mut self.cards[idx];
+        let source_column = std::mem::replace(&mut card.column_id, column_id.to_string());
+        if source_column != column_id {
+            card.column_changed_at = now;
+        }
+        card.updated_at = now;
+
+        for card in self.cards.iter_mut().filter(|c| c.column_id == column_id) {
+            card.order = order.iter().position(|cid| *cid == card.id).unwrap_or(0) as i64;
+        }
+        if source_column != column_id {
+            let mut left: Vec<(i64, String)> = self
+                .cards
+                .iter()
+                .filter(|c| c.column_id == source_column)
+                .map(|c| (c.order, c.id.clone()))
+                .collect();
+            left.sort_by_key(|(order, _)| *order);
+            for card in self.cards.iter_mut().filter(|c| c.column_id == source_column) {
+                card.order = left.iter().position(|(_, cid)| *cid == card.id).unwrap_or(0) as i64;
+            }
+        }
+        Ok(self.cards[idx].clone())
+    }
+
+    /// Archive a card (`reason` = archived) or soft-delete it (`reason` =
+    /// deleted). Linked tasks and notes are unlinked, and remembered on the
+    /// card so `restore_card` can re-link them. Deleting an archived card
+    /// only changes the reason; it keeps the links remembered when it was
+    /// archived.
+    pub fn archive_card(&mut self, id: &str, reason: ArchiveReason) -> Result<KanbanCard, StoreError> {
+        let idx = self.card_index(id)?;
+        if self.cards[idx].is_archived() {
+            let card = &mut self.cards[idx];
+            card.archive_reason = Some(reason);
+            card.updated_at = now_millis();
+            return Ok(card.clone());
+        }
+        let linked_task_ids: Vec<String> = self
+            .tasks
+            .iter()
+            .filter(|t| t.card_id.as_deref() == Some(id))
+            .map(|t| t.id.clone())
+            .collect();
+        let linked_note_ids: Vec<String> = self
+            .notes
+            .iter()
+            .filter(|n| n.card_id.as_deref() == Some(id))
+            .map(|n| n.id.clone())
+            .collect();
+
+        let now = now_millis();
+        let card = &mut self.cards[idx];
+        card.archived = Some(true);
+        card.archived_at = Some(now);
+        card.archive_reason = Some(reason);
+        card.linked_task_ids = Some(linked_task_ids);
+        card.linked_note_ids = Some(linked_note_ids);
+        card.updated_at = now;
+        let card = card.clone();
+
+        self.unlink_cards(std::slice::from_ref(&card.id));
+        Ok(card)
+    }
+
+    /// Bring an archived card back and re-link the tasks and notes that were
+    /// linked when it was archived (skipping any deleted since). A card that
+    /// isn't archived is refused.
+    pub fn restore_card(&mut self, id: &str) -> Result<KanbanCard, StoreError> {
+        let idx = self.card_index(id)?;
+        if !self.cards[idx].is_archived() {
+            return Err(StoreError::invalid(format!("Card {} is not archived", id)));
+        }
+        let card = &mut self.cards[idx];
+        let linked_task_ids = card.linked_task_ids.take().unwrap_or_default();
+        let linked_note_ids = card.linked_note_ids.take().unwrap_or_default();
+        card.archived = Some(false);
+        card.archived_at = None;
+        card.archive_reason = None;
+        card.updated_at = now_millis();
+        let card = card.clone();
+
+        for task in self.tasks.iter_mut().filter(|t| linked_task_ids.contains(&t.id)) {
+            task.card_id = Some(card.id.clone());
+        }
+        for note in self.notes.iter_mut().filter(|n| linked_note_ids.contains(&n.id)) {
+            note.card_id = Some(card.id.clone());
+        }
+        Ok(card)
+    }
+
+    /// Remove a card for good.
+    pub fn purge_card(&mut self, id: &str) -> Result<(), StoreError> {
+        self.card_index(id)?;
+        self.cards.retain(|c| c.id != id);
+        self.unlink_cards(&[id.to_string()]);
+        Ok(())
+    }
+
+    fn unlink_cards(&mut self, card_ids: &[String]) {
+        for task in self.tasks.iter_mut() {
+            if task.card_id.as_ref().is_some_and(|c| card_ids.contains(c)) {
+                task.card_id = None;
+            }
+        }
+        for note in self.notes.iter_mut() {
+            if note.card_id.as_ref().is_some_and(|c| card_ids.contains(c)) {
+                note.card_id = None;
+            }
+        }
+    }
+
+    // Tasks
+
+    pub fn create_task(&mut self, input: NewTask) -> Result<Task, StoreError> {
+        self.check_project_ref(EntityKind::Task, &input.project_id)?;
+        self.check_card_ref(EntityKind::Task, &input.project_id, input.card_id.as_deref())?;
+        let max_order = self
+            .tasks
+            .iter()
+            .filter(|t| t.project_id == input.project_id)
+            .map(|t| t.order.unwrap_or(0))
+            .max()
+            .unwrap_or(-1);
+        let now = now_millis();
+        let task = Task {
+            id: generate_id(),
+            project_id: input.project_id,
+            title: input.title,
+            description: input.description,
+            status: input.status.unwrap_or(TaskStatus::Todo),
+            priority: input.priority.unwrap_or(TaskPriority::P2),
+            due_date: input.due_date,
+            card_id: input.card_id,
+            links: input.links,
+            comments: input.comments,
+            order: Some(max_order + 1),
+            created_at: now,
+            updated_at: now,
+        };
+        self.tasks.push(task.clone());
+        Ok(task)
+    }
+
+    pub fn update_task(&mut self, id: &str, update: TaskUpdate) -> Result<Task, StoreError> {
+        let idx = self.task_index(id)?;
+        if let Some(card_id) = &update.card_id {
+            let project_id = self.tasks[idx].project_id.clone();
+            self.check_card_ref(EntityKind::Task, &project_id, card_id.as_deref())?;
+        }
+        let task = &mut self.tasks[idx];
+        if let Some(title) = update.title {
+            task.title = title;
+        }
+        if let Some(description) = update.description {
+            task.description = description;
+        }
+        if let Some(status) = update.status {
+            task.status = status;
+        }
+        if let Some(priority) = update.priority {
+            task.priority = priority;
+        }
+        if let Some(due_date) = update.due_date {
+            task.due_date = due_date;
+        }
+        if let Some(card_id) = update.card_id {
+            task.card_id = card_id;
+        }
+        if let Some(links) = update.links {
+            task.links = links;
+        }
+        if let Some(comments) = update.comments {
+            task.comments = comments;
+        }
+        task.updated_at = now_millis();
+        Ok(task.clone())
+    }
+
+    /// Move a task to `index` in its project's manual order and renumber.
+    pub fn move_task(&mut self, id: &str, index: usize) -> Result<Vec<Task>, StoreError> {
+        let idx = self.task_index(id)?;
+        let project_id = self.tasks[idx].project_id.clone();
+
+        let mut siblings: Vec<&Task> = self
+            .tasks
+            .iter()
+            .filter(|t| t.project_id == project_id && t.id != id)
+            .collect();
+        siblings.sort_by_key(|t| t.order.unwrap_or(0));
+        let mut order: Vec<String> = siblings.into_iter().map(|t| t.id.clone()).collect();
+        order.insert(index.min(order.len()), id.to_string());
+
+        for task in self.tasks.iter_mut().filter(|t| t.project_id == project_id) {
+            task.order = order.iter().position(|tid| *tid == task.id).map(|i| i as i64);
+        }
+        let mut result: Vec<Task> = self
+            .tasks
+            .iter()
+            .filter(|t| t.project_id == project_id)
+            .cloned()
+            .collect();
+        result.sort_by_key(|t| t.order.unwrap_or(0));
+        Ok(result)
+    }
+
+    pub fn delete_task(&mut self, id: &str) -> Result<(), StoreError> {
+        self.task_index(id)?;
+        self.tasks.retain(|t| t.id != id);
+        for card in self.cards.iter_mut() {
+            if let Some(ids) = card.linked_task_ids.as_mut() {
+                ids.retain(|t| t != id);
+            }
+        }
+        Ok(())
+    }
+
+    // Notes
+
+    pub fn create_note(&mut self, input: NewNote) -> Result<Note, StoreError> {
+        self.check_project_ref(EntityKind::Note, &input.project_id)?;
+        self.check_card_ref(EntityKind::Note, &input.project_id, input.card_id.as_deref())?;
+        let now = now_millis();
+        let note = Note {
+            id: generate_id(),
+            project_id: input.project_id,
+            title: input.title,
+            content: input.content.unwrap_or_default(),
+            card_id: input.card_id,
+            links: input.links,
+            created_at: now,
+            updated_at: now,
+        };
+        self.notes.push(note.clone());
+        Ok(note)
+    }
+
+    pub fn update_note(&mut self, id: &str, update: NoteUpdate) -> Result<Note, StoreError> {
+        let idx = self.note_index(id)?;
+        if let Some(card_id) = &update.card_id {
+            let project_id = self.notes[idx].project_id.clone();
+            self.check_card_ref(EntityKind::Note, &project_id, card_id.as_deref())?;
+        }
+        let note = &mut self.notes[idx];
+        if let Some(title) = update.title {
+            note.title = title;
+        }
+        if let Some(content) = update.content {
+            note.content = content;
+        }
+        if let Some(card_id) = update.card_id {
+            note.card_id = card_id;
+        }
+        if let Some(links) = update.links {
+            note.links = links;
+        }
+        note.updated_at = now_millis();
+        Ok(note.clone())
+    }
+
+    pub fn delete_note(&mut self, id: &str) -> Result<(), StoreError> {
+        self.note_index(id)?;
+        self.notes.retain(|n| n.id != id);
+        for card in self.cards.iter_mut() {
+            if let Some(ids) = card.linked_note_ids.as_mut() {
+                ids.retain(|n| n != id);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A new project and the ids of its default columns.
+    fn board() -> (AppData, String, Vec<String>) {
+        let mut data = AppData::default();
+        let project = data
+            .create_project(NewProject {
+                name: "P".into(),
+                description: None,
+            })
+            .unwrap();
+        let columns: Vec<String> = data.columns.iter().map(|c| c.id.clone()).collect();
+        (data, project.id, columns)
+    }
+
+    fn card(data: &mut AppData, column_id: &str, title: &str) -> String {
+        data.create_card(NewCard {
+            column_id: column_id.into(),
+            title: title.into(),
+            description: None,
+            priority: None,
+            due_date: None,
+        })
+        .unwrap()
+        .id
+    }
+
+    fn task(data: &mut AppData, project_id: &str, card_id: Option<&str>) -> Result<Task, StoreError> {
+        data.create_task(NewTask {
+            project_id: project_id.into(),
+            title: "T".into(),
+            description: None,
+            status: None,
+            priority: None,
+            due_date: None,
+            card_id: card_id.map(str::to_string),
+            links: None,
+            comments: None,
+        })
+    }
+
+    fn note(data: &mut AppData, project_id: &str, card_id: Option<&str>) -> Result<Note, StoreError> {
+        data.create_note(NewNote {
+            project_id: project_id.into(),
+            title: "N".into(),
+            content: None,
+            card_id: card_id.map(str::to_string),
+            links: None,
+        })
+    }
+
+    /// Card titles in `column_id`, by order, with their orders.
+    fn column(data: &AppData, column_id: &str) -> Vec<(String, i64)> {
+        let mut cards: Vec<(String, i64)> = data
+            .cards
+            .iter()
+            .filter(|c| c.column_id == column_id)
+            .map(|c| (c.title.clone(), c.order))
+            .collect();
+        cards.sort_by_key(|(_, order)| *order);
+        cards
+    }
+
+    #[test]
+    fn bad_references_are_typed_errors() {
+        let (mut data, project, columns) = board();
+        let (mut other, _, other_columns) = board();
+        let other_card = card(&mut other, &other_columns[0], "Elsewhere");
+        data.projects.extend(other.projects);
+        data.columns.extend(other.columns);
+        data.cards.extend(other.cards);
+        let before = data.clone();
+
+        assert_eq!(
+            data.update_project("nope", ProjectUpdate::default()),
+            Err(StoreError::not_found(EntityKind::Project, "nope"))
+        );
+        assert_eq!(
+            data.rename_column("nope", "X".into()),
+            Err(StoreError::not_found(EntityKind::Column, "nope"))
+        );
+        assert_eq!(
+            data.move_card("nope", &columns[0], 0),
+            Err(StoreError::not_found(EntityKind::Card, "nope"))
+        );
+        assert_eq!(
+            data.create_column("nope", "X".into()),
+            Err(StoreError::invalid_reference(EntityKind::Column, "projectId", EntityKind::Project, "nope"))
+        );
+        assert_eq!(
+            data.create_card(NewCard {
+                column_id: "nope".into(),
+                title: "C".into(),
+                description: None,
+                priority: None,
+                due_date: None,
+            }),
+            Err(StoreError::invalid_reference(EntityKind::Card, "columnId", EntityKind::Column, "nope"))
+        );
+        // A card in another project.
+        assert_eq!(
+            task(&mut data, &project, Some(&other_card)),
+            Err(StoreError::invalid_reference(EntityKind::Task, "cardId", EntityKind::Card, &other_card))
+        );
+        assert_eq!(
+            note(&mut data, &project, Some("nope")),
+            Err(StoreError::invalid_reference(EntityKind::Note, "cardId", EntityKind::Card, "nope"))
+        );
+        assert!(matches!(
+            data.move_card(&other_card, &columns[0], 0),
+            Err(StoreError::Invalid { .. })
+        ));
+        assert!(matches!(
+            data.create_project(NewProject {
+                name: " ".into(),
+                description: None,
+            }),
+            Err(StoreError::Invalid { .. })
+        ));
+        assert_eq!(data, before);
+    }
+
+    #[test]
+    fn archived_cards_cannot_be_linked() {
+        let (mut data, project, columns) = board();
+        let c = card(&mut data, &columns[0], "C");
+        let t = task(&mut data, &project, Some(&c)).unwrap().id;
+        data.archive_card(&c, ArchiveReason::Archived).unwrap();
+        assert_eq!(data.tasks[0].card_id, None);
+
+        let relink = TaskUpdate {
+            card_id: Some(Some(c.clone())),
+            ..TaskUpdate::default()
+        };
+        assert_eq!(
+            data.update_task(&t, relink.clone()),
+            Err(StoreError::invalid_reference(EntityKind::Task, "cardId", EntityKind::Card, &c))
+        );
+        data.restore_card(&c).unwrap();
+        assert_eq!(data.tasks[0].card_id.as_deref(), Some(c.as_str()));
+        assert!(data.update_task(&t, relink).is_ok());
+    }
+
+    #[test]
+    fn deleting_an_archived_card_keeps_its_links() {
+        let (mut data, project, columns) = board();
+        let c = card(&mut data, &columns[0], "C");
+        task(&mut data, &project, Some(&c)).unwrap();
+        note(&mut data, &project, Some(&c)).unwrap();
+        data.archive_card(&c, ArchiveReason::Archived).unwrap();
+        let deleted = data.archive_card(&c, ArchiveReason::Deleted).unwrap();
+        assert_eq!(deleted.archive_reason, Some(ArchiveReason::Deleted));
+
+        data.restore_card(&c).unwrap();
+        assert_eq!(data.tasks[0].card_id.as_deref(), Some(c.as_str()));
+        assert_eq!(data.notes[0].card_id.as_deref(), Some(c.as_str()));
+
+        // Only archived cards can be restored.
+        let before = data.clone();
+        assert!(matches!(data.restore_card(&c), Err(StoreError::Invalid { .. })));
+        assert_eq!(data, before);
+    }
+
+    #[test]
+    fn deletes_cascade() {
+        let (mut data, project, columns) = board();
+        let (mut other, other_project, other_columns) = board();
+        card(&mut other, &other_columns[0], "Kept");
+        task(&mut other, &other_project, None).unwrap();
+        data.projects.extend(other.projects);
+        data.columns.extend(other.columns);
+        data.cards.extend(other.cards);
+        data.tasks.extend(other.tasks);
+
+        let first = card(&mut data, &columns[0], "A");
+        let second = card(&mut data, &columns[1], "B");
+        let t = task(&mut data, &project, Some(&first)).unwrap().id;
+        let n = note(&mut data, &project, Some(&second)).unwrap().id;
+
+        // A column takes its cards with it and unlinks their tasks.
+        data.delete_column(&columns[0]).unwrap();
+        assert!(data.card_index(&first).is_err());
+        assert_eq!(data.tasks[data.task_index(&t).unwrap()].card_id, None);
+        assert_eq!(data.notes[0].card_id.as_deref(), Some(second.as_str()));
+
+        // Purging a card unlinks its notes.
+        data.purge_card(&second).unwrap();
+        assert_eq!(data.notes[0].card_id, None);
+
+        // A project takes everything.
+        data.delete_project(&project).unwrap();
+        assert!(data.task_index(&t).is_err());
+        assert!(data.note_index(&n).is_err());
+        assert!(data.columns.iter().all(|c| c.project_id == other_project));
+        assert_eq!(data.cards.len(), 1);
+        assert_eq!(data.tasks.len(), 1);
+        assert_eq!(data.current_project_id.as_deref(), Some(other_project.as_str()));
+    }
+
+    #[test]
+    fn moving_cards_renumbers_both_columns() {
+        let (mut data, _, columns) = board();
+        let a = card(&mut data, &columns[0], "A");
+        card(&mut data, &columns[0], "B");
+        card(&mut data, &columns[0], "C");
+        card(&mut data, &columns[1], "X");
+
+        // Within a column.
+        data.move_card(&a, &columns[0], 2).unwrap();
+        assert_eq!(
+            column(&data, &columns[0]),
+            [("B".into(), 0), ("C".into(), 1), ("A".into(), 2)]
+        );
+
+        // Across columns, past the end.
+        let moved = data.move_card(&data.cards[1].id.clone(), &columns[1], 9).unwrap();
+        assert_eq!(moved.title, "B");
+        assert_eq!(column(&data, &columns[0]), [("C".into(), 0), ("A".into(), 1)]);
+        assert_eq!(column(&data, &columns[1]), [("X".into(), 0), ("B".into(), 1)]);
+        assert_eq!(moved.column_changed_at, moved.updated_at);
+
+        data.move_card(&a, &columns[1], 0).unwrap();
+        assert_eq!(column(&data, &columns[0]), [("C".into(), 0)]);
+        assert_eq!(
+            column(&data, &columns[1]),
+            [("A".into(), 0), ("X".into(), 1), ("B".into(), 2)]
+        );
+    }
+}
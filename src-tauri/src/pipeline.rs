@@ -0,0 +1,366 @@
+//! Launch pipeline — the production startup sequence as an ordered list of
+//! named stages. Each stage reports a typed outcome and its duration, and a
+//! per-stage failure policy decides whether the launch keeps going.
+//!
+//! Stages can be disabled, reordered or given a different failure policy per
+//! machine via an optional `launch-pipeline.json` in the project directory:
+//!
+//! ```json
+//! { "stages": [ { "name": "git-pull", "enabled": false } ] }
+//! ```
+
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+use crate::logging::{self, Level};
+
+pub const CONFIG_FILE: &str = "launch-pipeline.json";
+pub const REPORT_FILE: &str = ".launch-report.json";
+
+/// Result of running a single stage.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "outcome", rename_all = "camelCase")]
+pub enum StageOutcome {
+    Skipped { reason: String },
+    Succeeded,
+    /// The stage finished, but not the way we wanted (e.g. fell back to an
+    /// older build). The launch continues regardless of policy.
+    Degraded { reason: String },
+    Failed { error: String },
+}
+
+impl StageOutcome {
+    pub fn skipped(reason: impl Into<String>) -> Self {
+        StageOutcome::Skipped { reason: reason.into() }
+    }
+
+    pub fn degraded(reason: impl Into<String>) -> Self {
+        StageOutcome::Degraded { reason: reason.into() }
+    }
+
+    pub fn failed(error: impl Into<String>) -> Self {
+        StageOutcome::Failed { error: error.into() }
+    }
+}
+
+/// What the pipeline does when a stage returns `Failed`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum FailurePolicy {
+    /// Stop the launch; remaining stages are not run.
+    Abort,
+    /// Record the failure and run the remaining stages.
+    Continue,
+}
+
+/// State shared between stages for a single launch.
+#[derive(Debug)]
+pub struct LaunchContext {
+    pub project_dir: String,
+    pub code_changed: bool,
+    pub port: Option<u16>,
+    pub server_ready: bool,
+    /// A fresh build was swapped into `.next` during this launch.
+    pub build_promoted: bool,
+}
+
+impl LaunchContext {
+    pub fn new(project_dir: &str) -> Self {
+        LaunchContext {
+            project_dir: project_dir.to_string(),
+            code_changed: false,
+            port: None,
+            server_ready: false,
+            build_promoted: false,
+        }
+    }
+}
+
+type StageFn = Box<dyn FnMut(&mut LaunchContext) -> StageOutcome + Send>;
+
+pub struct Stage {
+    pub name: &'static str,
+    pub policy: FailurePolicy,
+    pub enabled: bool,
+    run: StageFn,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StageReport {
+    pub name: String,
+    pub policy: FailurePolicy,
+    #[serde(flatten)]
+    pub outcome: StageOutcome,
+    pub duration_ms: u64,
+}
+
+/// Machine-readable summary of a launch, written to `.launch-report.json`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LaunchReport {
+    pub started_at: u64,
+    pub duration_ms: u64,
+    /// Name of the stage that stopped the launch, if any.
+    pub aborted_at: Option<String>,
+    pub stages: Vec<StageReport>,
+}
+
+impl LaunchReport {
+    pub fn succeeded(&self) -> bool {
+        self.aborted_at.is_none()
+    }
+
+    pub fn save(&self, project_dir: &str) {
+        let path = std::path::Path::new(project_dir).join(REPORT_FILE);
+        match serde_json::to_string_pretty(self) {
+            Ok(json) => {
+                if let Err(e) = std::fs::write(&path, json) {
+                    logging::warn(&format!("Failed to write launch report: {}", e));
+                }
+            }
+            Err(e) => logging::warn(&format!("Failed to serialize launch report: {}", e)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct PipelineConfig {
+    #[serde(default)]
+    pub stages: Vec<StageConfig>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct StageConfig {
+    pub name: String,
+    pub enabled: Option<bool>,
+    #[serde(rename = "onFailure")]
+    pub policy: Option<FailurePolicy>,
+}
+
+impl PipelineConfig {
+    /// Load `launch-pipeline.json` from the project directory. A missing file
+    /// is the common case and yields the default (empty) config.
+    pub fn load(project_dir: &str) -> Self {
+        let path = std::path::Path::new(project_dir).join(CONFIG_FILE);
+        let Ok(content) = std::fs::read_to_string(&path) else {
+            return PipelineConfig::default();
+        };
+        match serde_json::from_str(&content) {
+            Ok(config) => {
+                logging::info(&format!("Loaded pipeline config from {}", path.display()));
+                config
+            }
+            Err(e) => {
+                logging::warn(&format!("Ignoring invalid {}: {}", CONFIG_FILE, e));
+                PipelineConfig::default()
+            }
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct LaunchPipeline {
+    stages: Vec<Stage>,
+}
+
+impl LaunchPipeline {
+    pub fn new() -> Self {
+        LaunchPipeline { stages: Vec::new() }
+    }
+
+    pub fn stage<F>(mut self, name: &'static str, policy: FailurePolicy, run: F) -> Self
+    where
+        F: FnMut(&mut LaunchContext) -> StageOutcome + Send + 'static,
+    {
+        self.stages.push(Stage {
+            name,
+            policy,
+            enabled: true,
+            run: Box::new(run),
+        });
+        self
+    }
+
+    pub fn stage_names(&self) -> Vec<&'static str> {
+        self.stages.iter().map(|s| s.name).collect()
+    }
+
+    /// Apply per-machine overrides. Stages named in the config run first, in
+    /// the order listed; the rest keep their default relative order.
+    pub fn configure(mut self, config: &PipelineConfig) -> Self {
+        let mut ordered: Vec<Stage> = Vec::with_capacity(self.stages.len());
+        for entry in &config.stages {
+            let Some(idx) = self.stages.iter().position(|s| s.name == entry.name) else {
+                logging::warn(&format!("Pipeline config: unknown stage '{}'", entry.name));
+                continue;
+            };
+            let mut stage = self.stages.remove(idx);
+            if let Some(enabled) = entry.enabled {
+                stage.enabled = enabled;
+            }
+            if let Some(policy) = entry.policy {
+                stage.policy = policy;
+            }
+            ordered.push(stage);
+        }
+        ordered.append(&mut self.stages);
+        self.stages = ordered;
+        self
+    }
+
+    pub fn run(mut self, ctx: &mut LaunchContext) -> LaunchReport {
+        let started_at = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let launch_start = Instant::now();
+        let mut stages = Vec::with_capacity(self.stages.len());
+        let mut aborted_at = None;
+
+        for stage in self.stages.iter_mut() {
+            let (outcome, elapsed) = if aborted_at.is_some() {
+                (StageOutcome::skipped("launch aborted"), Duration::ZERO)
+            } else if !stage.enabled {
+                (StageOutcome::skipped("disabled by config"), Duration::ZERO)
+            } else {
+                logging::event(Level::Debug, format!("Stage '{}' starting", stage.name))
+                    .stage(stage.name)
+                    .emit();
+                let start = Instant::now();
+                let outcome = (stage.run)(ctx);
+                (outcome, start.elapsed())
+            };
+
+            if aborted_at.is_none() {
+                let level = match outcome {
+                    StageOutcome::Skipped { .. } | StageOutcome::Succeeded => Level::Info,
+                    StageOutcome::Degraded { .. } | StageOutcome::Failed { .. } => Level::Warn,
+                };
+                let mut event = logging::event(level, format!("Stage '{}' finished", stage.name))
+                    .stage(stage.name)
+                    .duration(elapsed);
+                // `outcome` and its reason or error.
+                if let Ok(serde_json::Value::Object(fields)) = serde_json::to_value(&outcome) {
+                    for (key, value) in fields {
+                        event = event.field(&key, value);
+                    }
+                }
+                event.emit();
+            }
+            if matches!(outcome, StageOutcome::Failed { .. })
+                && stage.policy == FailurePolicy::Abort
+                && aborted_at.is_none()
+            {
+                logging::event(Level::Error, format!("Stage '{}' failed — aborting launch", stage.name))
+                    .stage(stage.name)
+                    .emit();
+                aborted_at = Some(stage.name.to_string());
+            }
+
+            stages.push(StageReport {
+                name: stage.name.to_string(),
+                policy: stage.policy,
+                outcome,
+                duration_ms: elapsed.as_millis() as u64,
+            });
+        }
+
+        LaunchReport {
+            started_at,
+            duration_ms: launch_start.elapsed().as_millis() as u64,
+            aborted_at,
+            stages,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    type Log = Arc<Mutex<Vec<&'static str>>>;
+
+    /// A stage that records that it ran and returns `outcome`.
+    fn stage(
+        pipeline: LaunchPipeline,
+        log: &Log,
+        name: &'static str,
+        policy: FailurePolicy,
+        outcome: StageOutcome,
+    ) -> LaunchPipeline {
+        let log = log.clone();
+        pipeline.stage(name, policy, move |_| {
+            log.lock().unwrap().push(name);
+            outcome.clone()
+        })
+    }
+
+    fn three(log: &Log) -> LaunchPipeline {
+        let p = stage(LaunchPipeline::new(), log, "a", FailurePolicy::Continue, StageOutcome::Succeeded);
+        let p = stage(p, log, "b", FailurePolicy::Continue, StageOutcome::Succeeded);
+        stage(p, log, "c", FailurePolicy::Continue, StageOutcome::Succeeded)
+    }
+
+    fn config(json: &str) -> PipelineConfig {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn reorders_and_disables_stages() {
+        let log = Log::default();
+        let pipeline = three(&log).configure(&config(
+            r#"{ "stages": [
+                { "name": "c" },
+                { "name": "nope", "enabled": false },
+                { "name": "a", "enabled": false }
+            ] }"#,
+        ));
+        assert_eq!(pipeline.stage_names(), ["c", "a", "b"]);
+
+        let report = pipeline.run(&mut LaunchContext::new("/tmp"));
+        assert_eq!(*log.lock().unwrap(), ["c", "b"]);
+        assert!(report.succeeded());
+        assert_eq!(report.stages[1].outcome, StageOutcome::skipped("disabled by config"));
+    }
+
+    #[test]
+    fn abort_skips_the_remaining_stages() {
+        let log = Log::default();
+        let p = stage(LaunchPipeline::new(), &log, "a", FailurePolicy::Abort, StageOutcome::failed("boom"));
+        let p = stage(p, &log, "b", FailurePolicy::Continue, StageOutcome::Succeeded);
+        let report = p.run(&mut LaunchContext::new("/tmp"));
+
+        assert_eq!(*log.lock().unwrap(), ["a"]);
+        assert_eq!(report.aborted_at.as_deref(), Some("a"));
+        assert_eq!(report.stages[0].outcome, StageOutcome::failed("boom"));
+        assert_eq!(report.stages[1].outcome, StageOutcome::skipped("launch aborted"));
+    }
+
+    #[test]
+    fn continue_runs_past_a_failure() {
+        let log = Log::default();
+        let p = stage(LaunchPipeline::new(), &log, "a", FailurePolicy::Continue, StageOutcome::failed("boom"));
+        let p = stage(p, &log, "b", FailurePolicy::Continue, StageOutcome::degraded("fallback"));
+        let report = p.run(&mut LaunchContext::new("/tmp"));
+
+        assert_eq!(*log.lock().unwrap(), ["a", "b"]);
+        assert!(report.succeeded());
+        assert_eq!(report.stages[1].outcome, StageOutcome::degraded("fallback"));
+    }
+
+    #[test]
+    fn config_can_change_the_failure_policy() {
+        let log = Log::default();
+        let p = stage(LaunchPipeline::new(), &log, "a", FailurePolicy::Continue, StageOutcome::failed("boom"));
+        let p = stage(p, &log, "b", FailurePolicy::Continue, StageOutcome::Succeeded);
+        let report = p
+            .configure(&config(r#"{ "stages": [ { "name": "a", "onFailure": "abort" } ] }"#))
+            .run(&mut LaunchContext::new("/tmp"));
+
+        assert_eq!(*log.lock().unwrap(), ["a"]);
+        assert_eq!(report.aborted_at.as_deref(), Some("a"));
+        assert_eq!(report.stages[0].policy, FailurePolicy::Abort);
+    }
+}
@@ -0,0 +1,483 @@
+//! Status reports for stakeholders: a project's task list or Kanban board as
+//! CSV or Markdown. The output depends only on the data (no export time, a
+//! fixed sort order), so the same data always gives the same file.
+//!
+//! Dates are UTC `YYYY-MM-DD`, as the board shows them. Archived and deleted
+//! cards are left out.
+
+use serde::Deserialize;
+use std::cmp::Ordering;
+
+use crate::csv;
+use crate::error::StoreError;
+use crate::models::{AppData, KanbanCard, LinkAttachment, Note, Project, Task, TaskPriority, TaskStatus};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ReportKind {
+    Tasks,
+    Board,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ReportFormat {
+    Csv,
+    Markdown,
+}
+
+impl ReportFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            ReportFormat::Csv => "csv",
+            ReportFormat::Markdown => "md",
+        }
+    }
+
+    /// For the save dialog's file type filter.
+    pub fn label(self) -> &'static str {
+        match self {
+            ReportFormat::Csv => "CSV",
+            ReportFormat::Markdown => "Markdown",
+        }
+    }
+}
+
+pub fn render(data: &AppData, project_id: &str, kind: ReportKind, format: ReportFormat) -> Result<String, StoreError> {
+    let project = &data.projects[data.project_index(project_id)?];
+    let report = Report::new(data, project);
+    Ok(match (kind, format) {
+        (ReportKind::Tasks, ReportFormat::Csv) => report.tasks_csv(),
+        (ReportKind::Tasks, ReportFormat::Markdown) => report.tasks_markdown(),
+        (ReportKind::Board, ReportFormat::Csv) => report.board_csv(),
+        (ReportKind::Board, ReportFormat::Markdown) => report.board_markdown(),
+    })
+}
+
+/// E.g. `website-relaunch-board.md`.
+pub fn file_name(project_name: &str, kind: ReportKind, format: ReportFormat) -> String {
+    let kind = match kind {
+        ReportKind::Tasks => "tasks",
+        ReportKind::Board => "board",
+    };
+    format!("{}-{}.{}", crate::bundle::slug(project_name), kind, format.extension())
+}
+
+/// A project's records, sorted for output.
+struct Report<'a> {
+    project: &'a Project,
+    /// Each column with its live cards.
+    columns: Vec<(&'a str, Vec<&'a KanbanCard>)>,
+    tasks: Vec<&'a Task>,
+    notes: Vec<&'a Note>,
+    cards: Vec<&'a KanbanCard>,
+}
+
+impl<'a> Report<'a> {
+    fn new(data: &'a AppData, project: &'a Project) -> Self {
+        let mut columns: Vec<_> = data.columns.iter().filter(|c| c.project_id == project.id).collect();
+        columns.sort_by(|a, b| a.order.cmp(&b.order).then_with(|| a.id.cmp(&b.id)));
+        let columns: Vec<(&str, Vec<&KanbanCard>)> = columns
+            .into_iter()
+            .map(|column| {
+                let mut cards: Vec<&KanbanCard> = data
+                    .cards
+                    .iter()
+                    .filter(|c| c.column_id == column.id && !c.is_archived())
+                    .collect();
+                cards.sort_by(|a, b| a.order.cmp(&b.order).then_with(|| a.id.cmp(&b.id)));
+                (column.title.as_str(), cards)
+            })
+            .collect();
+        let cards = columns.iter().flat_map(|(_, cards)| cards.iter().copied()).collect();
+
+        let mut tasks: Vec<&Task> = data.tasks.iter().filter(|t| t.project_id == project.id).collect();
+        tasks.sort_by(|a, b| task_order(a, b));
+        let mut notes: Vec<&Note> = data.notes.iter().filter(|n| n.project_id == project.id).collect();
+        notes.sort_by(|a, b| a.created_at.cmp(&b.created_at).then_with(|| a.id.cmp(&b.id)));
+
+        Report {
+            project,
+            columns,
+            tasks,
+            notes,
+            cards,
+        }
+    }
+
+    fn card_title(&self, id: Option<&str>) -> &str {
+        id.and_then(|id| self.cards.iter().find(|c| c.id == id))
+            .map_or("", |c| c.title.as_str())
+    }
+
+    fn card_tasks(&self, card: &KanbanCard) -> Vec<&'a Task> {
+        self.tasks
+            .iter()
+            .filter(|t| t.card_id.as_deref() == Some(card.id.as_str()))
+            .copied()
+            .collect()
+    }
+
+    fn card_notes(&self, card: &KanbanCard) -> Vec<&'a Note> {
+        self.notes
+            .iter()
+            .filter(|n| n.card_id.as_deref() == Some(card.id.as_str()))
+            .copied()
+            .collect()
+    }
+
+    fn tasks_csv(&self) -> String {
+        let mut out = csv::row(&[
+            "Title", "Status", "Priority", "Due Date", "Card", "Description", "Comments", "Links", "Created",
+            "Updated",
+        ]);
+        for task in &self.tasks {
+            out.push_str(&csv::row(&[
+                &task.title,
+                status_label(task.status),
+                priority_label(task.priority),
+                &task.due_date.map(format_date).unwrap_or_default(),
+                self.card_title(task.card_id.as_deref()),
+                task.description.as_deref().unwrap_or(""),
+                task.comments.as_deref().unwrap_or(""),
+                &links(task.links.as_deref()),
+                &format_date(task.created_at),
+                &format_date(task.updated_at),
+            ]));
+        }
+        out
+    }
+
+    fn board_csv(&self) -> String {
+        let mut out = csv::row(&[
+            "Column", "Title", "Priority", "Due Date", "Description", "Linked Tasks", "Linked Notes", "Created",
+            "Updated",
+        ]);
+        for (column, cards) in &self.columns {
+            for card in cards {
+                let tasks: Vec<&str> = self.card_tasks(card).iter().map(|t| t.title.as_str()).collect();
+                let notes: Vec<&str> = self.card_notes(card).iter().map(|n| n.title.as_str()).collect();
+                out.push_str(&csv::row(&[
+                    column,
+                    &card.title,
+                    card.priority.map(priority_label).unwrap_or(""),
+                    &card.due_date.map(format_date).unwrap_or_default(),
+                    card.description.as_deref().unwrap_or(""),
+                    &tasks.join("; "),
+                    &notes.join("; "),
+                    &format_date(card.created_at),
+                    &format_date(card.updated_at),
+                ]));
+            }
+        }
+        out
+    }
+
+    fn tasks_markdown(&self) -> String {
+        let mut out = format!("# {}: Tasks\n\n", md_inline(&self.project.name));
+        if self.tasks.is_empty() {
+            out.push_str("_No tasks._\n");
+            return out;
+        }
+        out.push_str("| Title | Status | Priority | Due | Card |\n");
+        out.push_str("| --- | --- | --- | --- | --- |\n");
+        for task in &self.tasks {
+            out.push_str(&format!(
+                "| {} | {} | {} | {} | {} |\n",
+                md_inline(&task.title),
+                status_label(task.status),
+                priority_label(task.priority),
+                task.due_date.map(format_date).unwrap_or_default(),
+                md_inline(self.card_title(task.card_id.as_deref())),
+            ));
+        }
+        out
+    }
+
+    fn board_markdown(&self) -> String {
+        let mut out = format!("# {}: Board\n", md_inline(&self.project.name));
+        for (column, cards) in &self.columns {
+            out.push_str(&format!("\n## {}\n\n", md_inline(column)));
+            if cards.is_empty() {
+                out.push_str("_No cards._\n");
+                continue;
+            }
+            for card in cards {
+                let mut details = Vec::new();
+                if let Some(priority) = card.priority {
+                    details.push(priority_label(priority).to_string());
+                }
+                if let Some(due) = card.due_date {
+                    details.push(format!("due {}", format_date(due)));
+                }
+                out.push_str(&format!("- **{}**", md_inline(&card.title)));
+                if !details.is_empty() {
+                    out.push_str(&format!(" ({})", details.join(", ")));
+                }
+                out.push('\n');
+                for task in self.card_tasks(card) {
+                    out.push_str(&format!(
+                        "  - Task: {} ({})\n",
+                        md_inline(&task.title),
+                        status_label(task.status)
+                    ));
+                }
+                for note in self.card_notes(card) {
+                    out.push_str(&format!("  - Note: {}\n", md_inline(&note.title)));
+                }
+            }
+        }
+        out
+    }
+}
+
+/// Manual order first (tasks without one after), then oldest first.
+fn task_order(a: &Task, b: &Task) -> Ordering {
+    let key = |t: &Task| (t.order.is_none(), t.order, t.created_at);
+    key(a).cmp(&key(b)).then_with(|| a.id.cmp(&b.id))
+}
+
+fn status_label(status: TaskStatus) -> &'static str {
+    match status {
+        TaskStatus::Todo => "To Do",
+        TaskStatus::InProgress => "In Progress",
+        TaskStatus::Done => "Done",
+    }
+}
+
+fn priority_label(priority: TaskPriority) -> &'static str {
+    match priority {
+        TaskPriority::P0 => "P0",
+        TaskPriority::P1 => "P1",
+        TaskPriority::P2 => "P2",
+        TaskPriority::P3 => "P3",
+        TaskPriority::P4 => "P4",
+    }
+}
+
+fn links(links: Option<&[LinkAttachment]>) -> String {
+    links
+        .unwrap_or_default()
+        .iter()
+        .map(|l| match &l.title {
+            Some(title) if !title.is_empty() => format!("{} ({})", title, l.url),
+            _ => l.url.clone(),
+        })
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+/// A JS millisecond timestamp as a UTC `YYYY-MM-DD`.
+fn format_date(millis: i64) -> String {
+    let (y, m, d) = crate::backup::civil_from_days(millis.div_euclid(86_400_000));
+    format!("{:04}-{:02}-{:02}", y, m, d)
+}
+
+/// Text that reads as itself inside a Markdown line or table cell.
+fn md_inline(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' | '`' | '*' | '_' | '[' | ']' | '<' | '>' | '|' | '#' => {
+                out.push('\\');
+                out.push(c);
+            }
+            '\n' | '\r' => out.push(' '),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::KanbanColumn;
+
+    // 2026-01-14 12:00 UTC, as the date pickers store dates.
+    const JAN_14: i64 = 1_768_392_000_000;
+    const DAY: i64 = 86_400_000;
+
+    fn data() -> AppData {
+        let column = |id: &str, title: &str, order: i64| KanbanColumn {
+            id: id.into(),
+            project_id: "p".into(),
+            title: title.into(),
+            order,
+            created_at: JAN_14,
+        };
+        let card = |id: &str, title: &str, column: &str, order: i64| KanbanCard {
+            id: id.into(),
+            title: title.into(),
+            description: None,
+            priority: None,
+            column_id: column.into(),
+            order,
+            created_at: JAN_14,
+            updated_at: JAN_14,
+            column_changed_at: JAN_14,
+            due_date: None,
+            archived: None,
+            archived_at: None,
+            archive_reason: None,
+            linked_task_ids: None,
+            linked_note_ids: None,
+        };
+        let task = |id: &str, title: &str, order: Option<i64>| Task {
+            id: id.into(),
+            project_id: "p".into(),
+            title: title.into(),
+            description: None,
+            status: TaskStatus::Todo,
+            priority: TaskPriority::P2,
+            due_date: None,
+            card_id: None,
+            links: None,
+            comments: None,
+            order,
+            created_at: JAN_14,
+            updated_at: JAN_14,
+        };
+
+        let mut launch = card("c1", "Launch *beta*", "done", 1);
+        launch.priority = Some(TaskPriority::P1);
+        launch.due_date = Some(JAN_14 + DAY);
+        launch.description = Some("Ship it, then \"celebrate\"".into());
+        let mut archived = card("c3", "Old idea", "todo", 0);
+        archived.archived = Some(true);
+
+        let mut spec = task("t1", "Write spec", Some(1));
+        spec.status = TaskStatus::Done;
+        spec.priority = TaskPriority::P0;
+        spec.card_id = Some("c1".into());
+        spec.due_date = Some(JAN_14);
+        spec.links = Some(vec![
+            LinkAttachment {
+                url: "https://example.com/spec".into(),
+                title: Some("Spec".into()),
+            },
+            LinkAttachment {
+                url: "https://example.com/raw".into(),
+                title: None,
+            },
+        ]);
+        spec.comments = Some("line one\nline two".into());
+        let mut review = task("t2", "Review | sign off", Some(0));
+        review.status = TaskStatus::InProgress;
+
+        AppData {
+            projects: vec![Project {
+                id: "p".into(),
+                name: "Website Relaunch".into(),
+                description: None,
+                created_at: JAN_14,
+                updated_at: JAN_14,
+            }],
+            columns: vec![
+                column("done", "Done", 2),
+                column("todo", "To Do", 0),
+                column("doing", "In Progress", 1),
+            ],
+            cards: vec![launch, card("c2", "Design", "todo", 1), archived],
+            tasks: vec![task("t3", "Unordered", None), spec, review],
+            notes: vec![Note {
+                id: "n1".into(),
+                project_id: "p".into(),
+                title: "Launch checklist".into(),
+                content: String::new(),
+                card_id: Some("c1".into()),
+                links: None,
+                created_at: JAN_14,
+                updated_at: JAN_14,
+            }],
+            ..AppData::default()
+        }
+    }
+
+    fn render_test(kind: ReportKind, format: ReportFormat) -> String {
+        render(&data(), "p", kind, format).unwrap()
+    }
+
+    #[test]
+    fn tasks_csv() {
+        assert_eq!(
+            render_test(ReportKind::Tasks, ReportFormat::Csv),
+            r#"Title,Status,Priority,Due Date,Card,Description,Comments,Links,Created,Updated
+Review | sign off,In Progress,P2,,,,,,2026-01-14,2026-01-14
+Write spec,Done,P0,2026-01-14,Launch *beta*,,"line one
+line two",Spec (https://example.com/spec); https://example.com/raw,2026-01-14,2026-01-14
+Unordered,To Do,P2,,,,,,2026-01-14,2026-01-14
+"#
+        );
+    }
+
+    #[test]
+    fn board_csv() {
+        assert_eq!(
+            render_test(ReportKind::Board, ReportFormat::Csv),
+            r#"Column,Title,Priority,Due Date,Description,Linked Tasks,Linked Notes,Created,Updated
+To Do,Design,,,,,,2026-01-14,2026-01-14
+Done,Launch *beta*,P1,2026-01-15,"Ship it, then ""celebrate""",Write spec,Launch checklist,2026-01-14,2026-01-14
+"#
+        );
+    }
+
+    #[test]
+    fn tasks_markdown() {
+        assert_eq!(
+            render_test(ReportKind::Tasks, ReportFormat::Markdown),
+            r#"# Website Relaunch: Tasks
+
+| Title | Status | Priority | Due | Card |
+| --- | --- | --- | --- | --- |
+| Review \| sign off | In Progress | P2 |  |  |
+| Write spec | Done | P0 | 2026-01-14 | Launch \*beta\* |
+| Unordered | To Do | P2 |  |  |
+"#
+        );
+    }
+
+    #[test]
+    fn board_markdown() {
+        assert_eq!(
+            render_test(ReportKind::Board, ReportFormat::Markdown),
+            r#"# Website Relaunch: Board
+
+## To Do
+
+- **Design**
+
+## In Progress
+
+_No cards._
+
+## Done
+
+- **Launch \*beta\*** (P1, due 2026-01-15)
+  - Task: Write spec (Done)
+  - Note: Launch checklist
+"#
+        );
+    }
+
+    #[test]
+    fn output_is_deterministic() {
+        let mut shuffled = data();
+        shuffled.cards.reverse();
+        shuffled.tasks.reverse();
+        shuffled.columns.reverse();
+        for kind in [ReportKind::Tasks, ReportKind::Board] {
+            for format in [ReportFormat::Csv, ReportFormat::Markdown] {
+                assert_eq!(render(&shuffled, "p", kind, format).unwrap(), render_test(kind, format));
+            }
+        }
+    }
+
+    #[test]
+    fn file_names() {
+        assert_eq!(
+            file_name("Website Relaunch!", ReportKind::Board, ReportFormat::Markdown),
+            "website-relaunch-board.md"
+        );
+    }
+}
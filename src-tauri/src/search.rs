@@ -0,0 +1,557 @@
+//! Full-text search over cards, tasks and notes. The index is an inverted
+//! map from lowercase word to the records containing it; `DataStore` builds
+//! it on open and updates it with the records each change touched.
+//!
+//! Every word of the query must match (the last one as a prefix, so results
+//! follow typing). Results are ranked by how often and where the words
+//! occur — a match in a title counts for more than one in a description —
+//! weighted by how rare each word is, and come with highlighted snippets.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use crate::models::{AppData, EntityKind, LinkAttachment, TaskPriority, TaskStatus};
+
+const DEFAULT_LIMIT: usize = 50;
+/// Characters of context kept around the first match in long fields.
+const SNIPPET_CONTEXT: usize = 60;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Field {
+    Title,
+    Description,
+    Comments,
+    Content,
+    LinkTitle,
+    LinkUrl,
+}
+
+impl Field {
+    fn weight(self) -> f64 {
+        match self {
+            Field::Title => 3.0,
+            Field::LinkTitle => 1.5,
+            Field::Description | Field::Comments | Field::Content => 1.0,
+            Field::LinkUrl => 0.5,
+        }
+    }
+}
+
+type DocKey = (EntityKind, String);
+
+struct Doc {
+    /// Projects for tasks and notes; cards are resolved through their column.
+    project_id: Option<String>,
+    column_id: Option<String>,
+    title: String,
+    updated_at: i64,
+    status: Option<TaskStatus>,
+    priority: Option<TaskPriority>,
+    archived: bool,
+    fields: Vec<(Field, String)>,
+    terms: HashSet<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchQuery {
+    pub query: String,
+    /// Only these projects. All projects if absent.
+    #[serde(default)]
+    pub project_ids: Option<Vec<String>>,
+    /// Only these kinds of record. Cards, tasks and notes if absent.
+    #[serde(default)]
+    pub entities: Option<Vec<EntityKind>>,
+    /// Only tasks with one of these statuses.
+    #[serde(default)]
+    pub statuses: Option<Vec<TaskStatus>>,
+    /// Only cards and tasks with one of these priorities.
+    #[serde(default)]
+    pub priorities: Option<Vec<TaskPriority>>,
+    /// Include archived and deleted cards.
+    #[serde(default)]
+    pub include_archived: bool,
+    #[serde(default)]
+    pub limit: Option<usize>,
+}
+
+/// Part of a snippet; `highlighted` parts matched the query.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Segment {
+    pub text: String,
+    pub highlighted: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FieldMatch {
+    pub field: Field,
+    pub snippet: Vec<Segment>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchResult {
+    pub entity: EntityKind,
+    pub id: String,
+    pub project_id: Option<String>,
+    pub title: String,
+    pub score: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<TaskStatus>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub priority: Option<TaskPriority>,
+    pub matches: Vec<FieldMatch>,
+}
+
+/// Words in `text` with their byte ranges, lowercased.
+fn tokens(text: &str) -> Vec<(usize, usize, String)> {
+    let mut out = Vec::new();
+    let mut start = None;
+    for (i, c) in text.char_indices().chain(std::iter::once((text.len(), ' '))) {
+        match (start, c.is_alphanumeric()) {
+            (None, true) => start = Some(i),
+            (Some(s), false) => {
+                out.push((s, i, text[s..i].to_lowercase()));
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    out
+}
+
+fn link_fields(links: Option<&Vec<LinkAttachment>>) -> impl Iterator<Item = (Field, String)> + '_ {
+    links.into_iter().flatten().flat_map(|l| {
+        std::iter::once((Field::LinkUrl, l.url.clone())).chain(l.title.clone().map(|t| (Field::LinkTitle, t)))
+    })
+}
+
+#[derive(Default)]
+pub struct SearchIndex {
+    docs: HashMap<DocKey, Doc>,
+    terms: BTreeMap<String, HashSet<DocKey>>,
+    column_projects: HashMap<String, String>,
+}
+
+impl SearchIndex {
+    pub fn build(data: &AppData) -> Self {
+        let mut index = SearchIndex::default();
+        index.update(&AppData::default(), data);
+        index
+    }
+
+    /// Reindex the records that differ between `prev` and `next`.
+    pub fn update(&mut self, prev: &AppData, next: &AppData) {
+        self.column_projects = next
+            .columns
+            .iter()
+            .map(|c| (c.id.clone(), c.project_id.clone()))
+            .collect();
+
+        fn changed<'a, T: PartialEq>(
+            prev: &'a [T],
+            next: &'a [T],
+            id: impl Fn(&T) -> &String,
+        ) -> (Vec<&'a T>, Vec<&'a String>) {
+            let before: HashMap<&String, &T> = prev.iter().map(|r| (id(r), r)).collect();
+            let after: HashSet<&String> = next.iter().map(&id).collect();
+            let updated = next.iter().filter(|r| before.get(id(r)) != Some(r)).collect();
+            let removed = prev.iter().map(&id).filter(|i| !after.contains(i)).collect();
+            (updated, removed)
+        }
+
+        let (updated, removed) = changed(&prev.cards, &next.cards, |c| &c.id);
+        for id in removed {
+            self.remove(&(EntityKind::Card, id.clone()));
+        }
+        for card in updated {
+            let mut fields = vec![(Field::Title, card.title.clone())];
+            fields.extend(card.description.clone().map(|d| (Field::Description, d)));
+            self.insert(
+                (EntityKind::Card, card.id.clone()),
+                Doc {
+                    project_id: None,
+                    column_id: Some(card.column_id.clone()),
+                    title: card.title.clone(),
+                    updated_at: card.updated_at,
+                    status: None,
+                    priority: card.priority,
+                    archived: card.is_archived(),
+                    fields,
+                    terms: HashSet::new(),
+                },
+            );
+        }
+
+        let (updated, removed) = changed(&prev.tasks, &next.tasks, |t| &t.id);
+        for id in removed {
+            self.remove(&(EntityKind::Task, id.clone()));
+        }
+        for task in updated {
+            let mut fields = vec![(Field::Title, task.title.clone())];
+            fields.extend(task.description.clone().map(|d| (Field::Description, d)));
+            fields.extend(task.comments.clone().map(|c| (Field::Comments, c)));
+            fields.extend(link_fields(task.links.as_ref()));
+            self.insert(
+                (EntityKind::Task, task.id.clone()),
+                Doc {
+                    project_id: Some(task.project_id.clone()),
+                    column_id: None,
+                    title: task.title.clone(),
+                    updated_at: task.updated_at,
+                    status: Some(task.status),
+                    priority: Some(task.priority),
+                    archived: false,
+                    fields,
+                    terms: HashSet::new(),
+                },
+            );
+        }
+
+        let (updated, removed) = changed(&prev.notes, &next.notes, |n| &n.id);
+        for id in removed {
+            self.remove(&(EntityKind::Note, id.clone()));
+        }
+        for note in updated {
+            let mut fields = vec![
+                (Field::Title, note.title.clone()),
+                (Field::Content, note.content.clone()),
+            ];
+            fields.extend(link_fields(note.links.as_ref()));
+            self.insert(
+                (EntityKind::Note, note.id.clone()),
+                Doc {
+                    project_id: Some(note.project_id.clone()),
+                    column_id: None,
+                    title: note.title.clone(),
+                    updated_at: note.updated_at,
+                    status: None,
+                    priority: None,
+                    archived: false,
+                    fields,
+                    terms: HashSet::new(),
+                },
+            );
+        }
+    }
+
+    fn insert(&mut self, key: DocKey, mut doc: Doc) {
+        self.remove(&key);
+        doc.terms = doc
+            .fields
+            .iter()
+            .flat_map(|(_, text)| tokens(text))
+            .map(|(_, _, term)| term)
+            .collect();
+        for term in &doc.terms {
+            self.terms.entry(term.clone()).or_default().insert(key.clone());
+        }
+        self.docs.insert(key, doc);
+    }
+
+    fn remove(&mut self, key: &DocKey) {
+        let Some(doc) = self.docs.remove(key) else {
+            return;
+        };
+        for term in &doc.terms {
+            if let Some(keys) = self.terms.get_mut(term) {
+                keys.remove(key);
+                if keys.is_empty() {
+                    self.terms.remove(term);
+                }
+            }
+        }
+    }
+
+    /// Indexed words matching a query word: the word itself, or every word
+    /// it is a prefix of.
+    fn expand(&self, word: &str, prefix: bool) -> Vec<&str> {
+        if !prefix {
+            return self.terms.get_key_value(word).map(|(k, _)| k.as_str()).into_iter().collect();
+        }
+        self.terms
+            .range(word.to_string()..)
+            .take_while(|(k, _)| k.starts_with(word))
+            .map(|(k, _)| k.as_str())
+            .collect()
+    }
+
+    fn project_of<'a>(&'a self, doc: &'a Doc) -> Option<&'a String> {
+        doc.project_id
+            .as_ref()
+            .or_else(|| doc.column_id.as_ref().and_then(|c| self.column_projects.get(c)))
+    }
+
+    fn passes(&self, key: &DocKey, doc: &Doc, query: &SearchQuery) -> bool {
+        let (entity, _) = key;
+        if doc.archived && !query.include_archived {
+            return false;
+        }
+        if query.entities.as_ref().is_some_and(|e| !e.contains(entity)) {
+            return false;
+        }
+        if let Some(projects) = &query.project_ids {
+            if !self.project_of(doc).is_some_and(|p| projects.contains(p)) {
+                return false;
+            }
+        }
+        if let Some(statuses) = &query.statuses {
+            if !doc.status.is_some_and(|s| statuses.contains(&s)) {
+                return false;
+            }
+        }
+        if let Some(priorities) = &query.priorities {
+            if !doc.priority.is_some_and(|p| priorities.contains(&p)) {
+                return false;
+            }
+        }
+        true
+    }
+
+    pub fn search(&self, query: &SearchQuery) -> Vec<SearchResult> {
+        let words: Vec<String> = tokens(&query.query).into_iter().map(|(_, _, w)| w).collect();
+        if words.is_empty() {
+            return Vec::new();
+        }
+        let total = self.docs.len() as f64;
+
+        // Per query word: the indexed words it matches and their weight.
+        let mut expanded: Vec<(HashSet<&str>, f64)> = Vec::new();
+        let mut candidates: Option<HashSet<&DocKey>> = None;
+        for (i, word) in words.iter().enumerate() {
+            let terms = self.expand(word, i == words.len() - 1);
+            let docs: HashSet<&DocKey> = terms.iter().flat_map(|t| &self.terms[*t]).collect();
+            let idf = (1.0 + total / (docs.len().max(1) as f64)).ln();
+            candidates = Some(match candidates {
+                None => docs,
+                Some(c) => c.intersection(&docs).copied().collect(),
+            });
+            expanded.push((terms.into_iter().collect(), idf));
+        }
+
+        let mut results: Vec<(SearchResult, i64)> = candidates
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|key| {
+                let doc = &self.docs[key];
+                self.passes(key, doc, query).then(|| (self.result(key, doc, &expanded), doc.updated_at))
+            })
+            .collect();
+
+        results.sort_by(|(a, a_updated), (b, b_updated)| {
+            b.score
+                .total_cmp(&a.score)
+                .then(b_updated.cmp(a_updated))
+                .then(a.id.cmp(&b.id))
+        });
+        results
+            .into_iter()
+            .take(query.limit.unwrap_or(DEFAULT_LIMIT))
+            .map(|(r, _)| r)
+            .collect()
+    }
+
+    fn result(&self, key: &DocKey, doc: &Doc, expanded: &[(HashSet<&str>, f64)]) -> SearchResult {
+        let mut score = 0.0;
+        let mut matches = Vec::new();
+        for (field, text) in &doc.fields {
+            let mut hits = Vec::new();
+            for (start, end, token) in tokens(text) {
+                if let Some((_, idf)) = expanded.iter().find(|(terms, _)| terms.contains(token.as_str())) {
+                    score += field.weight() * idf;
+                    hits.push((start, end));
+                }
+            }
+            if !hits.is_empty() {
+                matches.push(FieldMatch {
+                    field: *field,
+                    snippet: snippet(text, &hits, *field == Field::Title),
+                });
+            }
+        }
+        SearchResult {
+            entity: key.0,
+            id: key.1.clone(),
+            project_id: self.project_of(doc).cloned(),
+            title: doc.title.clone(),
+            score,
+            status: doc.status,
+            priority: doc.priority,
+            matches,
+        }
+    }
+}
+
+/// Split `text` into plain and highlighted segments. Unless `whole`, only a
+/// window around the first hit is kept, with ellipses where it was cut.
+fn snippet(text: &str, hits: &[(usize, usize)], whole: bool) -> Vec<Segment> {
+    let (from, to) = if whole {
+        (0, text.len())
+    } else {
+        let (first, _) = hits[0];
+        let from = text[..first]
+            .char_indices()
+            .rev()
+            .nth(SNIPPET_CONTEXT - 1)
+            .map_or(0, |(i, _)| i);
+        let to = text[first..]
+            .char_indices()
+            .nth(SNIPPET_CONTEXT * 2)
+            .map_or(text.len(), |(i, _)| first + i);
+        (from, to)
+    };
+
+    let mut segments = Vec::new();
+    let mut push = |s: &str, highlighted: bool| {
+        if !s.is_empty() {
+            segments.push(Segment {
+                text: s.to_string(),
+                highlighted,
+            });
+        }
+    };
+    if from > 0 {
+        push("…", false);
+    }
+    let mut pos = from;
+    for &(start, end) in hits.iter().filter(|(s, e)| *s >= from && *e <= to) {
+        push(&text[pos..start], false);
+        push(&text[start..end], true);
+        pos = end;
+    }
+    push(&text[pos..to], false);
+    if to < text.len() {
+        push("…", false);
+    }
+    segments
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn data() -> AppData {
+        serde_json::from_value(serde_json::json!({
+            "projects": [{ "id": "p", "name": "P", "createdAt": 1, "updatedAt": 1 }],
+            "columns": [{ "id": "c", "projectId": "p", "title": "To Do", "order": 0, "createdAt": 1 }],
+            "cards": [
+                { "id": "k1", "title": "Design review", "description": "Walk through the mockups",
+                  "columnId": "c", "order": 0, "createdAt": 1, "updatedAt": 1, "columnChangedAt": 1 },
+                { "id": "k2", "title": "Old idea", "columnId": "c", "order": 1, "createdAt": 1,
+                  "updatedAt": 1, "columnChangedAt": 1, "archived": true, "archiveReason": "archived" }
+            ],
+            "tasks": [
+                { "id": "t1", "projectId": "p", "title": "Review budget", "status": "todo", "priority": "p1",
+                  "comments": "designers asked for more", "createdAt": 1, "updatedAt": 2,
+                  "links": [{ "url": "https://example.com/budget", "title": "Sheet" }] }
+            ],
+            "notes": [
+                { "id": "n1", "projectId": "p", "title": "Meeting", "content": "Design sync notes",
+                  "createdAt": 1, "updatedAt": 1 }
+            ]
+        }))
+        .unwrap()
+    }
+
+    fn search(index: &SearchIndex, query: &str) -> Vec<String> {
+        index
+            .search(&SearchQuery {
+                query: query.into(),
+                ..SearchQuery::default()
+            })
+            .into_iter()
+            .map(|r| r.id)
+            .collect()
+    }
+
+    fn assert_same(index: &SearchIndex, data: &AppData) {
+        let fresh = SearchIndex::build(data);
+        assert_eq!(index.terms, fresh.terms);
+        assert_eq!(index.column_projects, fresh.column_projects);
+        for (key, doc) in &fresh.docs {
+            let updated = &index.docs[key];
+            assert_eq!((&updated.title, &updated.fields), (&doc.title, &doc.fields));
+        }
+        assert_eq!(index.docs.len(), fresh.docs.len());
+    }
+
+    #[test]
+    fn updates_match_a_fresh_build() {
+        let mut prev = data();
+        let mut index = SearchIndex::build(&prev);
+        assert_eq!(search(&index, "budget"), ["t1"]);
+
+        let mut next = prev.clone();
+        next.tasks[0].title = "Plan roadmap".into();
+        next.tasks[0].links = None;
+        next.cards.remove(0);
+        next.notes[0].content = "Budget sync".into();
+        index.update(&prev, &next);
+        assert_same(&index, &next);
+        assert_eq!(search(&index, "budget"), ["n1"]);
+        assert!(search(&index, "review").is_empty());
+        assert!(search(&index, "sheet").is_empty());
+
+        prev = next.clone();
+        next.notes.clear();
+        next.cards[0].title = "Review idea".into();
+        index.update(&prev, &next);
+        assert_same(&index, &next);
+        assert!(search(&index, "budget").is_empty());
+    }
+
+    #[test]
+    fn only_the_last_word_is_a_prefix() {
+        let index = SearchIndex::build(&data());
+        // A title match ranks first, then ties go to the newest change.
+        assert_eq!(search(&index, "desig"), ["k1", "t1", "n1"]);
+        assert_eq!(search(&index, "design"), ["k1", "t1", "n1"]);
+        assert_eq!(search(&index, "design rev"), ["k1"]);
+        assert!(search(&index, "des review").is_empty());
+        // "designers" doesn't match "design" unless it is the last word.
+        assert!(search(&index, "design budg").is_empty());
+        assert_eq!(search(&index, "designers budg"), ["t1"]);
+        assert!(search(&index, "").is_empty());
+
+        // Archived cards only on request.
+        assert!(search(&index, "idea").is_empty());
+        let archived = index.search(&SearchQuery {
+            query: "idea".into(),
+            include_archived: true,
+            ..SearchQuery::default()
+        });
+        assert_eq!(archived[0].id, "k2");
+        assert_eq!(archived[0].project_id.as_deref(), Some("p"));
+    }
+
+    fn text(segments: &[Segment]) -> String {
+        segments.iter().map(|s| s.text.as_str()).collect()
+    }
+
+    #[test]
+    fn snippets_stay_within_bounds() {
+        let long = format!("{} needle {}", "ä".repeat(200), "ö".repeat(200));
+        let start = long.find("needle").unwrap();
+        let hits = [(start, start + 6)];
+
+        let cut = snippet(&long, &hits, false);
+        assert_eq!(cut.first().unwrap().text, "…");
+        assert_eq!(cut.last().unwrap().text, "…");
+        let highlighted: Vec<&str> = cut.iter().filter(|s| s.highlighted).map(|s| s.text.as_str()).collect();
+        assert_eq!(highlighted, ["needle"]);
+        let shown = text(&cut[1..cut.len() - 1]);
+        assert_eq!(shown.chars().count(), SNIPPET_CONTEXT * 3);
+        assert!(shown.starts_with(&"ä".repeat(SNIPPET_CONTEXT - 1)));
+
+        // Near the start there is nothing to cut before the hit.
+        let short = "needle in a haystack";
+        let segments = snippet(short, &[(0, 6), (12, 20)], false);
+        assert_eq!(text(&segments), short);
+        assert_eq!(segments.iter().filter(|s| s.highlighted).count(), 2);
+
+        // Titles are never cut.
+        assert_eq!(text(&snippet(&long, &hits, true)), long);
+    }
+}
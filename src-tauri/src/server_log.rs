@@ -0,0 +1,302 @@
+//! Next.js server output. The launcher drains the server's stdout and stderr
+//! on background threads (an unread pipe blocks the server once its buffer
+//! fills) into `server.log` in the log directory (see `logging`), one
+//! timestamped line per line of output:
+//!
+//! ```text
+//! 2026-10-17T09:30:12.345Z launch Started server on port 3000 (PID 4242)
+//! 2026-10-17T09:30:13.002Z stdout  ▲ Next.js 16.0.0
+//! 2026-10-17T09:30:14.518Z stderr Error: ENOENT: no such file or directory
+//! ```
+//!
+//! The log rotates once it reaches `MAX_BYTES`, keeping `KEEP` older files;
+//! `server.log.1` is the most recent.
+
+use serde::Serialize;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::Child;
+use std::sync::{Arc, Mutex, PoisonError};
+
+use crate::logging;
+
+pub const LOG_FILE: &str = "server.log";
+const MAX_BYTES: u64 = 2 * 1024 * 1024;
+const KEEP: u32 = 3;
+/// Longer lines are split.
+const MAX_LINE: u64 = 16 * 1024;
+/// Most lines `tail` returns.
+pub const MAX_TAIL: usize = 5000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stream {
+    Stdout,
+    Stderr,
+    /// Written by the launcher itself, e.g. when it starts a server.
+    Launch,
+}
+
+impl Stream {
+    fn as_str(self) -> &'static str {
+        match self {
+            Stream::Stdout => "stdout",
+            Stream::Stderr => "stderr",
+            Stream::Launch => "launch",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ServerLogTail {
+    pub path: String,
+    /// Oldest first.
+    pub lines: Vec<String>,
+}
+
+/// Shared by the reader threads of every server the app starts.
+#[derive(Clone)]
+pub struct ServerLog(Arc<Mutex<Writer>>);
+
+struct Writer {
+    path: PathBuf,
+    file: Option<File>,
+    size: u64,
+    max_bytes: u64,
+    keep: u32,
+}
+
+impl ServerLog {
+    pub fn new(dir: &Path) -> Self {
+        Self::with_limits(&dir.join(LOG_FILE), MAX_BYTES, KEEP)
+    }
+
+    fn with_limits(path: &Path, max_bytes: u64, keep: u32) -> Self {
+        ServerLog(Arc::new(Mutex::new(Writer {
+            path: path.to_path_buf(),
+            file: None,
+            size: 0,
+            max_bytes,
+            keep,
+        })))
+    }
+
+    fn path(&self) -> PathBuf {
+        self.0
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .path
+            .clone()
+    }
+
+    /// Take the child's stdout and stderr and copy them into the log until
+    /// it closes them.
+    pub fn capture(&self, child: &mut Child) {
+        if let Some(stdout) = child.stdout.take() {
+            self.spawn_reader(Stream::Stdout, stdout);
+        }
+        if let Some(stderr) = child.stderr.take() {
+            self.spawn_reader(Stream::Stderr, stderr);
+        }
+    }
+
+    fn spawn_reader(&self, stream: Stream, reader: impl Read + Send + 'static) {
+        let log = self.clone();
+        let spawned = std::thread::Builder::new()
+            .name(format!("server-{}", stream.as_str()))
+            .spawn(move || log.drain(stream, reader));
+        if let Err(e) = spawned {
+            logging::warn(&format!(
+                "Failed to capture server {}: {}",
+                stream.as_str(),
+                e
+            ));
+        }
+    }
+
+    fn drain(&self, stream: Stream, reader: impl Read) {
+        let mut reader = BufReader::new(reader);
+        let mut line = Vec::new();
+        loop {
+            line.clear();
+            match (&mut reader).take(MAX_LINE).read_until(b'\n', &mut line) {
+                Ok(0) => break,
+                Ok(_) => self.write(stream, &String::from_utf8_lossy(&line)),
+                Err(e) => {
+                    logging::warn(&format!(
+                        "Stopped reading server {}: {}",
+                        stream.as_str(),
+                        e
+                    ));
+                    break;
+                }
+            }
+        }
+    }
+
+    pub fn write(&self, stream: Stream, text: &str) {
+        let line = format!(
+            "{} {} {}\n",
+            logging::timestamp(),
+            stream.as_str(),
+            text.trim_end_matches(['\r', '\n'])
+        );
+        self.0
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .append(line.as_bytes());
+    }
+
+    /// The last `count` lines (at most `MAX_TAIL`), reading into the rotated
+    /// file when the current one is shorter.
+    pub fn tail(&self, count: usize) -> ServerLogTail {
+        let path = self.path();
+        let count = count.min(MAX_TAIL);
+        let mut lines: Vec<String> = Vec::new();
+        for file in [path.clone(), rotated(&path, 1)] {
+            if lines.len() >= count {
+                break;
+            }
+            let Ok(bytes) = std::fs::read(&file) else {
+                continue;
+            };
+            let text = String::from_utf8_lossy(&bytes);
+            let file_lines: Vec<&str> = text.lines().collect();
+            let start = file_lines.len().saturating_sub(count - lines.len());
+            let mut older: Vec<String> =
+                file_lines[start..].iter().map(|l| l.to_string()).collect();
+            older.append(&mut lines);
+            lines = older;
+        }
+        ServerLogTail {
+            path: path.to_string_lossy().to_string(),
+            lines,
+        }
+    }
+}
+
+impl Writer {
+    fn append(&mut self, line: &[u8]) {
+        if self.file.is_some() && self.size > 0 && self.size + line.len() as u64 > self.max_bytes {
+            self.rotate();
+        }
+        if self.file.is_none() {
+            let opened = std::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&self.path);
+            match opened {
+                Ok(file) => {
+                    self.size = file.metadata().map(|m| m.len()).unwrap_or(0);
+                    self.file = Some(file);
+                    if self.size > 0 && self.size + line.len() as u64 > self.max_bytes {
+                        self.rotate();
+                        return self.append(line);
+                    }
+                }
+                // Try again on the next line.
+                Err(_) => return,
+            }
+        }
+        if let Some(file) = &mut self.file {
+            match file.write_all(line) {
+                Ok(()) => self.size += line.len() as u64,
+                Err(_) => self.file = None,
+            }
+        }
+    }
+
+    /// Shift `server.log` to `server.log.1`, `.1` to `.2` and so on, dropping
+    /// the oldest.
+    fn rotate(&mut self) {
+        self.file = None;
+        self.size = 0;
+        let _ = std::fs::remove_file(rotated(&self.path, self.keep));
+        for n in (1..self.keep).rev() {
+            let _ = std::fs::rename(rotated(&self.path, n), rotated(&self.path, n + 1));
+        }
+        if self.keep > 0 {
+            let _ = std::fs::rename(&self.path, rotated(&self.path, 1));
+        } else {
+            let _ = std::fs::remove_file(&self.path);
+        }
+    }
+}
+
+fn rotated(path: &Path, n: u32) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(format!(".{}", n));
+    PathBuf::from(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+
+    fn temp_log(max_bytes: u64, keep: u32) -> (TempDir, ServerLog) {
+        let dir = TempDir::new("server-log");
+        let log = ServerLog::with_limits(&dir.join(LOG_FILE), max_bytes, keep);
+        (dir, log)
+    }
+
+    fn text(line: &str) -> &str {
+        // Drop the timestamp.
+        line.split_once(' ').unwrap().1
+    }
+
+    #[test]
+    fn drains_lines_by_stream() {
+        let (_dir, log) = temp_log(MAX_BYTES, KEEP);
+        log.drain(Stream::Stdout, &b"ready\r\nstarted\n\xffpartial"[..]);
+        log.drain(Stream::Stderr, &b"boom\n"[..]);
+
+        let tail = log.tail(10);
+        let lines: Vec<&str> = tail.lines.iter().map(|l| text(l)).collect();
+        assert_eq!(
+            lines,
+            [
+                "stdout ready",
+                "stdout started",
+                "stdout \u{fffd}partial",
+                "stderr boom"
+            ]
+        );
+        assert!(tail.lines[0].ends_with("Z stdout ready"));
+        assert_eq!(
+            tail.lines[0].len(),
+            "2026-10-17T09:30:12.345Z stdout ready".len()
+        );
+    }
+
+    #[test]
+    fn rotates_and_tails_across_files() {
+        let (dir, log) = temp_log(200, 2);
+        for i in 0..20 {
+            log.write(Stream::Stdout, &format!("line {:02}", i));
+        }
+        let path = dir.join(LOG_FILE);
+        assert!(std::fs::metadata(&path).unwrap().len() <= 200);
+        assert!(rotated(&path, 2).exists());
+        assert!(!rotated(&path, 3).exists());
+
+        let current = std::fs::read_to_string(&path).unwrap().lines().count();
+        let tail = log.tail(current + 2);
+        let lines: Vec<&str> = tail.lines.iter().map(|l| text(l)).collect();
+        let expected: Vec<String> = (20 - current - 2..20)
+            .map(|i| format!("stdout line {:02}", i))
+            .collect();
+        assert_eq!(lines, expected);
+    }
+
+    #[test]
+    fn splits_long_lines() {
+        let (_dir, log) = temp_log(MAX_BYTES, KEEP);
+        let long = vec![b'x'; MAX_LINE as usize + 10];
+        log.drain(Stream::Stderr, &long[..]);
+        let tail = log.tail(10);
+        assert_eq!(tail.lines.len(), 2);
+        assert_eq!(text(&tail.lines[1]), "stderr xxxxxxxxxx");
+    }
+}
@@ -0,0 +1,694 @@
+//! SQLite storage backend, enabled with the `sqlite` cargo feature. Records
+//! live in one table per entity, with link attachments and a card's linked
+//! tasks/notes in their own tables, so the history can be queried without
+//! loading the whole document.
+//!
+//! The store still keeps an `AppData` in memory and hands every change to
+//! `save` as a before/after pair; only rows that changed are written, in one
+//! transaction. `seq` keeps each table in the order of its `AppData` array.
+//!
+//! There are no foreign keys: the JSON document never enforced them, and
+//! dangling references are reported by the integrity check rather than
+//! refused on load. Ids are primary keys, so duplicates are repaired before
+//! a JSON file is imported and refused by `save`.
+
+use rusqlite::{params, Connection, OptionalExtension, Transaction};
+use serde::{de::DeserializeOwned, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use crate::error::StoreError;
+use crate::migrations::CURRENT_VERSION;
+use crate::models::{AppData, EntityKind, KanbanCard, KanbanColumn, LinkAttachment, Note, Project, Task};
+
+pub const DB_FILE: &str = "data/app-data.sqlite";
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS meta (
+    key TEXT PRIMARY KEY,
+    value TEXT
+);
+CREATE TABLE IF NOT EXISTS projects (
+    id TEXT PRIMARY KEY,
+    seq INTEGER NOT NULL,
+    name TEXT NOT NULL,
+    description TEXT,
+    created_at INTEGER NOT NULL,
+    updated_at INTEGER NOT NULL
+);
+CREATE TABLE IF NOT EXISTS columns (
+    id TEXT PRIMARY KEY,
+    seq INTEGER NOT NULL,
+    project_id TEXT NOT NULL,
+    title TEXT NOT NULL,
+    sort_order INTEGER NOT NULL,
+    created_at INTEGER NOT NULL
+);
+CREATE TABLE IF NOT EXISTS cards (
+    id TEXT PRIMARY KEY,
+    seq INTEGER NOT NULL,
+    title TEXT NOT NULL,
+    description TEXT,
+    priority TEXT,
+    column_id TEXT NOT NULL,
+    sort_order INTEGER NOT NULL,
+    created_at INTEGER NOT NULL,
+    updated_at INTEGER NOT NULL,
+    column_changed_at INTEGER NOT NULL,
+    due_date INTEGER,
+    archived INTEGER,
+    archived_at INTEGER,
+    archive_reason TEXT,
+    has_linked_tasks INTEGER NOT NULL,
+    has_linked_notes INTEGER NOT NULL
+);
+CREATE TABLE IF NOT EXISTS tasks (
+    id TEXT PRIMARY KEY,
+    seq INTEGER NOT NULL,
+    project_id TEXT NOT NULL,
+    title TEXT NOT NULL,
+    description TEXT,
+    status TEXT NOT NULL,
+    priority TEXT NOT NULL,
+    due_date INTEGER,
+    card_id TEXT,
+    comments TEXT,
+    sort_order INTEGER,
+    has_links INTEGER NOT NULL,
+    created_at INTEGER NOT NULL,
+    updated_at INTEGER NOT NULL
+);
+CREATE TABLE IF NOT EXISTS notes (
+    id TEXT PRIMARY KEY,
+    seq INTEGER NOT NULL,
+    project_id TEXT NOT NULL,
+    title TEXT NOT NULL,
+    content TEXT NOT NULL,
+    card_id TEXT,
+    has_links INTEGER NOT NULL,
+    created_at INTEGER NOT NULL,
+    updated_at INTEGER NOT NULL
+);
+-- URL attachments on tasks and notes.
+CREATE TABLE IF NOT EXISTS links (
+    owner_kind TEXT NOT NULL,
+    owner_id TEXT NOT NULL,
+    position INTEGER NOT NULL,
+    url TEXT NOT NULL,
+    title TEXT,
+    PRIMARY KEY (owner_kind, owner_id, position)
+);
+-- A card's linkedTaskIds / linkedNoteIds.
+CREATE TABLE IF NOT EXISTS card_links (
+    card_id TEXT NOT NULL,
+    target_kind TEXT NOT NULL,
+    position INTEGER NOT NULL,
+    target_id TEXT NOT NULL,
+    PRIMARY KEY (card_id, target_kind, position)
+);
+CREATE INDEX IF NOT EXISTS columns_project ON columns (project_id);
+CREATE INDEX IF NOT EXISTS cards_column ON cards (column_id);
+CREATE INDEX IF NOT EXISTS tasks_project ON tasks (project_id);
+CREATE INDEX IF NOT EXISTS tasks_card ON tasks (card_id);
+CREATE INDEX IF NOT EXISTS notes_project ON notes (project_id);
+";
+
+pub struct SqliteStore {
+    path: PathBuf,
+    conn: Connection,
+}
+
+fn db_error(e: rusqlite::Error) -> StoreError {
+    StoreError::io(format!("Database error: {}", e))
+}
+
+/// Store a serde unit enum (`TaskStatus`, `TaskPriority`, `ArchiveReason`)
+/// as the string it has in JSON.
+fn enum_text<T: Serialize>(value: &T) -> String {
+    serde_json::to_value(value)
+        .ok()
+        .and_then(|v| v.as_str().map(String::from))
+        .unwrap_or_default()
+}
+
+fn parse_enum<T: DeserializeOwned>(text: String) -> rusqlite::Result<T> {
+    serde_json::from_value(serde_json::Value::String(text)).map_err(|e| {
+        rusqlite::Error::FromSqlConversionFailure(0, rusqlite::types::Type::Text, Box::new(e))
+    })
+}
+
+impl SqliteStore {
+    pub fn db_path(project_dir: &str) -> PathBuf {
+        Path::new(project_dir).join(DB_FILE)
+    }
+
+    /// Open (creating if needed) the database. Returns whether it was newly
+    /// created, i.e. still needs importing into. A database written by a
+    /// newer version of the app is refused, like a newer JSON file.
+    pub fn open(project_dir: &str) -> Result<(Self, bool), StoreError> {
+        let path = Self::db_path(project_dir);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| StoreError::io(format!("Failed to create {}: {}", parent.display(), e)))?;
+        }
+        let conn = Connection::open(&path).map_err(db_error)?;
+        conn.execute_batch(SCHEMA).map_err(db_error)?;
+        let version = conn
+            .query_row("SELECT value FROM meta WHERE key = 'schemaVersion'", [], |r| {
+                r.get::<_, String>(0)
+            })
+            .optional()
+            .map_err(db_error)?;
+        if let Some(version) = &version {
+            let supported = version.parse::<u32>().is_ok_and(|v| v <= CURRENT_VERSION);
+            if !supported {
+                return Err(StoreError::invalid(format!(
+                    "{} has schema version {}, but this version of the app only supports up to {}. Update the app to open it.",
+                    path.display(),
+                    version,
+                    CURRENT_VERSION
+                )));
+            }
+        }
+        Ok((SqliteStore { path, conn }, version.is_none()))
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn load(&self) -> Result<AppData, StoreError> {
+        self.load_inner().map_err(db_error)
+    }
+
+    fn load_inner(&self) -> rusqlite::Result<AppData> {
+        let mut links = self.load_links()?;
+        let mut card_links = self.load_card_links()?;
+
+        let projects = self
+            .conn
+            .prepare("SELECT id, name, description, created_at, updated_at FROM projects ORDER BY seq")?
+            .query_map([], |r| {
+                Ok(Project {
+                    id: r.get(0)?,
+                    name: r.get(1)?,
+                    description: r.get(2)?,
+                    created_at: r.get(3)?,
+                    updated_at: r.get(4)?,
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        let columns = self
+            .conn
+            .prepare("SELECT id, project_id, title, sort_order, created_at FROM columns ORDER BY seq")?
+            .query_map([], |r| {
+                Ok(KanbanColumn {
+                    id: r.get(0)?,
+                    project_id: r.get(1)?,
+                    title: r.get(2)?,
+                    order: r.get(3)?,
+                    created_at: r.get(4)?,
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        let cards = self
+            .conn
+            .prepare(
+                "SELECT id, title, description, priority, column_id, sort_order, created_at, updated_at,
+                        column_changed_at, due_date, archived, archived_at, archive_reason,
+                        has_linked_tasks, has_linked_notes
+                 FROM cards ORDER BY seq",
+            )?
+            .query_map([], |r| {
+                let id: String = r.get(0)?;
+                let has_tasks: bool = r.get(13)?;
+                let has_notes: bool = r.get(14)?;
+                Ok(KanbanCard {
+                    title: r.get(1)?,
+                    description: r.get(2)?,
+                    priority: r.get::<_, Option<String>>(3)?.map(parse_enum).transpose()?,
+                    column_id: r.get(4)?,
+                    order: r.get(5)?,
+                    created_at: r.get(6)?,
+                    updated_at: r.get(7)?,
+                    column_changed_at: r.get(8)?,
+                    due_date: r.get(9)?,
+                    archived: r.get(10)?,
+                    archived_at: r.get(11)?,
+                    archive_reason: r.get::<_, Option<String>>(12)?.map(parse_enum).transpose()?,
+                    linked_task_ids: has_tasks.then(Vec::new),
+                    linked_note_ids: has_notes.then(Vec::new),
+                    id,
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?
+            .into_iter()
+            .map(|mut card| {
+                if let Some(ids) = card.linked_task_ids.as_mut() {
+                    *ids = card_links.remove(&(card.id.clone(), "task")).unwrap_or_default();
+                }
+                if let Some(ids) = card.linked_note_ids.as_mut() {
+                    *ids = card_links.remove(&(card.id.clone(), "note")).unwrap_or_default();
+                }
+                card
+            })
+            .collect();
+
+        let tasks = self
+            .conn
+            .prepare(
+                "SELECT id, project_id, title, description, status, priority, due_date, card_id,
+                        comments, sort_order, has_links, created_at, updated_at
+                 FROM tasks ORDER BY seq",
+            )?
+            .query_map([], |r| {
+                let has_links: bool = r.get(10)?;
+                Ok(Task {
+                    id: r.get(0)?,
+                    project_id: r.get(1)?,
+                    title: r.get(2)?,
+                    description: r.get(3)?,
+                    status: parse_enum(r.get(4)?)?,
+                    priority: parse_enum(r.get(5)?)?,
+                    due_date: r.get(6)?,
+                    card_id: r.get(7)?,
+                    comments: r.get(8)?,
+                    order: r.get(9)?,
+                    links: has_links.then(Vec::new),
+                    created_at: r.get(11)?,
+                    updated_at: r.get(12)?,
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<Task>>>()?
+            .into_iter()
+            .map(|mut task| {
+                if let Some(l) = task.links.as_mut() {
+                    *l = links.remove(&(String::from("task"), task.id.clone())).unwrap_or_default();
+                }
+                task
+            })
+            .collect();
+
+        let notes = self
+            .conn
+            .prepare(
+                "SELECT id, project_id, title, content, card_id, has_links, created_at, updated_at
+                 FROM notes ORDER BY seq",
+            )?
+            .query_map([], |r| {
+                let has_links: bool = r.get(5)?;
+                Ok(Note {
+                    id: r.get(0)?,
+                    project_id: r.get(1)?,
+                    title: r.get(2)?,
+                    content: r.get(3)?,
+                    card_id: r.get(4)?,
+                    links: has_links.then(Vec::new),
+                    created_at: r.get(6)?,
+                    updated_at: r.get(7)?,
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<Note>>>()?
+            .into_iter()
+            .map(|mut note| {
+                if let Some(l) = note.links.as_mut() {
+                    *l = links.remove(&(String::from("note"), note.id.clone())).unwrap_or_default();
+                }
+                note
+            })
+            .collect();
+
+        let current_project_id = self
+            .conn
+            .query_row("SELECT value FROM meta WHERE key = 'currentProjectId'", [], |r| {
+                r.get::<_, Option<String>>(0)
+            })
+            .optional()?
+            .flatten();
+
+        Ok(AppData {
+            projects,
+            columns,
+            cards,
+            tasks,
+            notes,
+            current_project_id,
+            ..AppData::default()
+        })
+    }
+
+    fn load_links(&self) -> rusqlite::Result<HashMap<(String, String), Vec<LinkAttachment>>> {
+        let mut links: HashMap<(String, String), Vec<LinkAttachment>> = HashMap::new();
+        let mut stmt = self
+            .conn
+            .prepare("SELECT owner_kind, owner_id, url, title FROM links ORDER BY position")?;
+        let rows = stmt.query_map([], |r| {
+            Ok((
+                (r.get(0)?, r.get(1)?),
+                LinkAttachment {
+                    url: r.get(2)?,
+                    title: r.get(3)?,
+                },
+            ))
+        })?;
+        for row in rows {
+            let (owner, link) = row?;
+            links.entry(owner).or_default().push(link);
+        }
+        Ok(links)
+    }
+
+    fn load_card_links(&self) -> rusqlite::Result<HashMap<(String, &'static str), Vec<String>>> {
+        let mut ids: HashMap<(String, &'static str), Vec<String>> = HashMap::new();
+        let mut stmt = self
+            .conn
+            .prepare("SELECT card_id, target_kind, target_id FROM card_links ORDER BY position")?;
+        let rows = stmt.query_map([], |r| {
+            Ok((r.get::<_, String>(0)?, r.get::<_, String>(1)?, r.get::<_, String>(2)?))
+        })?;
+        for row in rows {
+            let (card_id, kind, target_id) = row?;
+            let kind = if kind == "note" { "note" } else { "task" };
+            ids.entry((card_id, kind)).or_default().push(target_id);
+        }
+        Ok(ids)
+    }
+
+    /// Write the difference between `prev` and `next` in one transaction.
+    /// Ids are primary keys, so `next` can't repeat one within a type.
+    pub fn save(&mut self, prev: &AppData, next: &AppData) -> Result<(), StoreError> {
+        check_unique_ids(next)?;
+        let tx = self.conn.transaction().map_err(db_error)?;
+        write_diff(&tx, prev, next).map_err(db_error)?;
+        tx.commit().map_err(db_error)
+    }
+
+    /// Write `data` into a database that has nothing in it yet.
+    pub fn import(&mut self, data: &AppData) -> Result<(), StoreError> {
+        self.save(&AppData::default(), data)
+    }
+}
+
+fn check_unique_ids(data: &AppData) -> Result<(), StoreError> {
+    fn unique<'a>(entity: EntityKind, mut ids: impl Iterator<Item = &'a str>) -> Result<(), StoreError> {
+        let mut seen = HashSet::new();
+        match ids.find(|id| !seen.insert(*id)) {
+            Some(id) => Err(StoreError::invalid(format!("Duplicate {} id {}", entity, id))),
+            None => Ok(()),
+        }
+    }
+    unique(EntityKind::Project, data.projects.iter().map(|p| p.id.as_str()))?;
+    unique(EntityKind::Column, data.columns.iter().map(|c| c.id.as_str()))?;
+    unique(EntityKind::Card, data.cards.iter().map(|c| c.id.as_str()))?;
+    unique(EntityKind::Task, data.tasks.iter().map(|t| t.id.as_str()))?;
+    unique(EntityKind::Note, data.notes.iter().map(|n| n.id.as_str()))
+}
+
+/// Records in `next` that are new, changed, or moved within their array,
+/// with their new index; and ids in `prev` that are gone from `next`.
+fn diff<'a, T: PartialEq>(
+    prev: &'a [T],
+    next: &'a [T],
+    id: impl Fn(&T) -> &str,
+) -> (Vec<(usize, &'a T)>, Vec<&'a str>) {
+    let before: HashMap<&str, (usize, &T)> = prev.iter().enumerate().map(|(i, r)| (id(r), (i, r))).collect();
+    let after: HashMap<&str, usize> = next.iter().enumerate().map(|(i, r)| (id(r), i)).collect();
+    let changed = next
+        .iter()
+        .enumerate()
+        .filter(|(i, r)| !matches!(before.get(id(r)), Some((j, old)) if j == i && *old == *r))
+        .collect();
+    let removed = prev
+        .iter()
+        .map(&id)
+        .filter(|id| !after.contains_key(id))
+        .collect();
+    (changed, removed)
+}
+
+fn delete_rows(tx: &Transaction, table: &str, ids: &[&str]) -> rusqlite::Result<()> {
+    let mut stmt = tx.prepare(&format!("DELETE FROM {} WHERE id = ?1", table))?;
+    for id in ids {
+        stmt.execute([id])?;
+    }
+    Ok(())
+}
+
+fn write_links(tx: &Transaction, kind: &str, id: &str, links: Option<&Vec<LinkAttachment>>) -> rusqlite::Result<()> {
+    tx.execute("DELETE FROM links WHERE owner_kind = ?1 AND owner_id = ?2", [kind, id])?;
+    for (i, link) in links.into_iter().flatten().enumerate() {
+        tx.execute(
+            "INSERT INTO links (owner_kind, owner_id, position, url, title) VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![kind, id, i as i64, link.url, link.title],
+        )?;
+    }
+    Ok(())
+}
+
+fn write_card_links(tx: &Transaction, card: &KanbanCard) -> rusqlite::Result<()> {
+    tx.execute("DELETE FROM card_links WHERE card_id = ?1", [&card.id])?;
+    for (kind, ids) in [("task", &card.linked_task_ids), ("note", &card.linked_note_ids)] {
+        for (i, target) in ids.iter().flatten().enumerate() {
+            tx.execute(
+                "INSERT INTO card_links (card_id, target_kind, position, target_id) VALUES (?1, ?2, ?3, ?4)",
+                params![card.id, kind, i as i64, target],
+            )?;
+        }
+    }
+    Ok(())
+}
+
+fn write_diff(tx: &Transaction, prev: &AppData, next: &AppData) -> rusqlite::Result<()> {
+    let (changed, removed) = diff(&prev.projects, &next.projects, |p| &p.id);
+    delete_rows(tx, "projects", &removed)?;
+    for (seq, p) in changed {
+        tx.execute(
+            "INSERT OR REPLACE INTO projects (id, seq, name, description, created_at, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![p.id, seq as i64, p.name, p.description, p.created_at, p.updated_at],
+        )?;
+    }
+
+    let (changed, removed) = diff(&prev.columns, &next.columns, |c| &c.id);
+    delete_rows(tx, "columns", &removed)?;
+    for (seq, c) in changed {
+        tx.execute(
+            "INSERT OR REPLACE INTO columns (id, seq, project_id, title, sort_order, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![c.id, seq as i64, c.project_id, c.title, c.order, c.created_at],
+        )?;
+    }
+
+    let (changed, removed) = diff(&prev.cards, &next.cards, |c| &c.id);
+    delete_rows(tx, "cards", &removed)?;
+    for id in &removed {
+        tx.execute("DELETE FROM card_links WHERE card_id = ?1", [id])?;
+    }
+    for (seq, c) in changed {
+        tx.execute(
+            "INSERT OR REPLACE INTO cards (id, seq, title, description, priority, column_id, sort_order,
+                created_at, updated_at, column_changed_at, due_date, archived, archived_at,
+                archive_reason, has_linked_tasks, has_linked_notes)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16)",
+            params![
+                c.id,
+                seq as i64,
+                c.title,
+                c.description,
+                c.priority.as_ref().map(enum_text),
+                c.column_id,
+                c.order,
+                c.created_at,
+                c.updated_at,
+                c.column_changed_at,
+                c.due_date,
+                c.archived,
+                c.archived_at,
+                c.archive_reason.as_ref().map(enum_text),
+                c.linked_task_ids.is_some(),
+                c.linked_note_ids.is_some(),
+            ],
+        )?;
+        write_card_links(tx, c)?;
+    }
+
+    let (changed, removed) = diff(&prev.tasks, &next.tasks, |t| &t.id);
+    delete_rows(tx, "tasks", &removed)?;
+    for id in &removed {
+        write_links(tx, "task", id, None)?;
+    }
+    for (seq, t) in changed {
+        tx.execute(
+            "INSERT OR REPLACE INTO tasks (id, seq, project_id, title, description, status, priority,
+                due_date, card_id, comments, sort_order, has_links, created_at, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)",
+            params![
+                t.id,
+                seq as i64,
+                t.project_id,
+                t.title,
+                t.description,
+                enum_text(&t.status),
+                enum_text(&t.priority),
+                t.due_date,
+                t.card_id,
+                t.comments,
+                t.order,
+                t.links.is_some(),
+                t.created_at,
+                t.updated_at,
+            ],
+        )?;
+        write_links(tx, "task", &t.id, t.links.as_ref())?;
+    }
+
+    let (changed, removed) = diff(&prev.notes, &next.notes, |n| &n.id);
+    delete_rows(tx, "notes", &removed)?;
+    for id in &removed {
+        write_links(tx, "note", id, None)?;
+    }
+    for (seq, n) in changed {
+        tx.execute(
+            "INSERT OR REPLACE INTO notes (id, seq, project_id, title, content, card_id, has_links,
+                created_at, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            params![
+                n.id,
+                seq as i64,
+                n.project_id,
+                n.title,
+                n.content,
+                n.card_id,
+                n.links.is_some(),
+                n.created_at,
+                n.updated_at,
+            ],
+        )?;
+        write_links(tx, "note", &n.id, n.links.as_ref())?;
+    }
+
+    tx.execute(
+        "INSERT OR REPLACE INTO meta (key, value) VALUES ('currentProjectId', ?1)",
+        [&next.current_project_id],
+    )?;
+    tx.execute(
+        "INSERT OR REPLACE INTO meta (key, value) VALUES ('schemaVersion', ?1)",
+        [next.schema_version.to_string()],
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backup::BackupManager;
+    use crate::store::DataStore;
+    use crate::test_util::TempDir;
+
+    fn sample() -> AppData {
+        serde_json::from_value(serde_json::json!({
+            "projects": [
+                { "id": "p2", "name": "Second", "createdAt": 1, "updatedAt": 2 },
+                { "id": "p1", "name": "First", "description": "d", "createdAt": 1, "updatedAt": 2 }
+            ],
+            "columns": [
+                { "id": "c1", "projectId": "p1", "title": "To Do", "order": 0, "createdAt": 1 }
+            ],
+            "cards": [
+                { "id": "k2", "title": "B", "columnId": "c1", "order": 1, "createdAt": 1, "updatedAt": 1,
+                  "columnChangedAt": 1, "priority": "p1", "dueDate": 9 },
+                { "id": "k1", "title": "A", "columnId": "c1", "order": 0, "createdAt": 1, "updatedAt": 1,
+                  "columnChangedAt": 1, "archived": true, "archivedAt": 5, "archiveReason": "deleted",
+                  "linkedTaskIds": ["t1"], "linkedNoteIds": [] }
+            ],
+            "tasks": [
+                { "id": "t1", "projectId": "p1", "title": "T", "status": "in-progress", "priority": "p0",
+                  "comments": "c", "order": 0, "createdAt": 1, "updatedAt": 1,
+                  "links": [{ "url": "https://a" }, { "url": "https://b", "title": "B" }] }
+            ],
+            "notes": [
+                { "id": "n1", "projectId": "p1", "title": "N", "content": "x", "cardId": "k2",
+                  "links": [], "createdAt": 1, "updatedAt": 1 }
+            ],
+            "currentProjectId": "p1"
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn round_trips_and_keeps_array_order() {
+        let dir = TempDir::new("sqlite");
+        let project_dir = dir.as_str();
+        let (mut db, created) = SqliteStore::open(project_dir).unwrap();
+        assert!(created);
+        let data = sample();
+        db.import(&data).unwrap();
+        assert_eq!(db.load().unwrap(), data);
+
+        let mut next = data.clone();
+        next.projects.swap(0, 1);
+        next.cards.remove(0);
+        next.tasks[0].links = None;
+        next.current_project_id = None;
+        db.save(&data, &next).unwrap();
+        drop(db);
+
+        let (db, created) = SqliteStore::open(project_dir).unwrap();
+        assert!(!created);
+        assert_eq!(db.load().unwrap(), next);
+    }
+
+    #[test]
+    fn refuses_duplicate_ids_and_newer_databases() {
+        let dir = TempDir::new("sqlite");
+        let project_dir = dir.as_str();
+        let (mut db, _) = SqliteStore::open(project_dir).unwrap();
+        let data = sample();
+        db.import(&data).unwrap();
+
+        let mut next = data.clone();
+        next.notes.push(next.notes[0].clone());
+        assert_eq!(
+            db.save(&data, &next),
+            Err(StoreError::invalid("Duplicate note id n1"))
+        );
+        assert_eq!(db.load().unwrap(), data);
+
+        db.conn
+            .execute(
+                "UPDATE meta SET value = ?1 WHERE key = 'schemaVersion'",
+                [(CURRENT_VERSION + 1).to_string()],
+            )
+            .unwrap();
+        drop(db);
+        assert!(matches!(SqliteStore::open(project_dir), Err(StoreError::Invalid { .. })));
+    }
+
+    #[test]
+    fn imports_the_json_file_once() {
+        let dir = TempDir::new("sqlite");
+        let project_dir = dir.as_str();
+        let json_path = DataStore::data_path(project_dir);
+        std::fs::create_dir_all(json_path.parent().unwrap()).unwrap();
+        let mut data = sample();
+        // Repaired on import rather than collapsed by the primary key.
+        data.notes.push(data.notes[0].clone());
+        std::fs::write(&json_path, serde_json::to_string(&data).unwrap()).unwrap();
+        let backups = BackupManager::new(project_dir);
+
+        let store = DataStore::open(project_dir, &backups).unwrap();
+        assert_eq!(store.path(), SqliteStore::db_path(project_dir));
+        assert_eq!(store.data().notes.len(), 2);
+        assert_ne!(store.data().notes[0].id, store.data().notes[1].id);
+        let imported = store.data().clone();
+        drop(store);
+
+        // Later changes to the JSON file are not imported again.
+        std::fs::write(&json_path, serde_json::to_string(&AppData::default()).unwrap()).unwrap();
+        let store = DataStore::open(project_dir, &backups).unwrap();
+        assert_eq!(store.data(), &imported);
+        drop(store);
+    }
+}
@@ -0,0 +1,473 @@
+//! Data store — owns `data/app-data.json`. The file is loaded once at startup
+//! and every change is written back with write-then-rename, so a crash or a
+//! full disk mid-save can never leave a truncated file behind.
+//!
+//! Built with the `sqlite` feature, the data lives in `data/app-data.sqlite`
+//! instead (see `sqlite.rs`), imported from the JSON file the first time. The
+//! in-memory document and everything built on it are the same either way.
+
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use crate::audit::{Actor, AuditEntry, AuditLog, CompactionReport};
+use crate::backup::BackupManager;
+use crate::folder_sync::FolderSync;
+use crate::error::StoreError;
+use crate::journal::{HistoryEntry, Journal};
+use crate::logging;
+use crate::migrations;
+use crate::models::{AppData, AppDataPatch, EntityKind};
+use crate::search::{SearchIndex, SearchQuery, SearchResult};
+#[cfg(feature = "sqlite")]
+use crate::sqlite::SqliteStore;
+
+pub const DATA_FILE: &str = "data/app-data.json";
+
+enum Backend {
+    #[cfg_attr(feature = "sqlite", allow(dead_code))]
+    Json(PathBuf),
+    #[cfg(feature = "sqlite")]
+    Sqlite(SqliteStore),
+}
+
+impl Backend {
+    #[cfg_attr(not(feature = "sqlite"), allow(unused_variables))]
+    fn save(&mut self, prev: &AppData, next: &AppData) -> Result<(), StoreError> {
+        match self {
+            Backend::Json(path) => save_data(path, next),
+            #[cfg(feature = "sqlite")]
+            Backend::Sqlite(db) => db.save(prev, next),
+        }
+    }
+}
+
+pub struct DataStore {
+    project_dir: PathBuf,
+    backend: Backend,
+    data: AppData,
+    index: SearchIndex,
+    folder_sync: Option<FolderSync>,
+    journal: Journal,
+    audit: AuditLog,
+}
+
+impl DataStore {
+    pub fn data_path(project_dir: &str) -> PathBuf {
+        Path::new(project_dir).join(DATA_FILE)
+    }
+
+    /// Open the store for a project with whichever backend this build uses.
+    pub fn open(project_dir: &str, backups: &BackupManager) -> Result<Self, StoreError> {
+        #[cfg(feature = "sqlite")]
+        let store = Self::open_sqlite(project_dir, backups)?;
+        #[cfg(not(feature = "sqlite"))]
+        let store = Self::open_json(project_dir, backups)?;
+
+        logging::info(&format!(
+            "Loaded data store from {}: {} projects, {} cards, {} tasks, {} notes",
+            store.path().display(),
+            store.data.projects.len(),
+            store.data.cards.len(),
+            store.data.tasks.len(),
+            store.data.notes.len()
+        ));
+        Ok(store)
+    }
+
+    /// Load the data file for a project, creating it with empty defaults if it
+    /// does not exist yet. Older files are migrated to the current schema
+    /// (after a backup of the original); files from a newer version of the
+    /// app are refused.
+    #[cfg(not(feature = "sqlite"))]
+    fn open_json(project_dir: &str, backups: &BackupManager) -> Result<Self, StoreError> {
+        let path = Self::data_path(project_dir);
+        let (data, migrated) = Self::read_json(&path, backups)?;
+        if migrated || !path.exists() {
+            save_data(&path, &data)?;
+        }
+        Ok(DataStore {
+            project_dir: PathBuf::from(project_dir),
+            backend: Backend::Json(path),
+            index: SearchIndex::build(&data),
+            folder_sync: None,
+            journal: Journal::load(project_dir, &data),
+            audit: AuditLog::open(project_dir),
+            data,
+        })
+    }
+
+    /// Open `data/app-data.sqlite`. A new database is filled from the JSON
+    /// data file (migrated first, like `open_json` would); the JSON file is
+    /// left as it was.
+    #[cfg(feature = "sqlite")]
+    fn open_sqlite(project_dir: &str, backups: &BackupManager) -> Result<Self, StoreError> {
+        let (mut db, created) = SqliteStore::open(project_dir)?;
+        let data = if created {
+            let path = Self::data_path(project_dir);
+            let (mut data, _) = Self::read_json(&path, backups)?;
+            let report = crate::integrity::check(&data);
+            if report
+                .issues
+                .iter()
+                .any(|i| matches!(i, crate::integrity::Issue::DuplicateId { .. }))
+            {
+                crate::integrity::repair(&mut data).log();
+            }
+            db.import(&data)?;
+            logging::info(&format!(
+                "Imported {} into {}",
+                path.display(),
+                db.path().display()
+            ));
+            data
+        } else {
+            db.load()?
+        };
+        Ok(DataStore {
+            project_dir: PathBuf::from(project_dir),
+            backend: Backend::Sqlite(db),
+            index: SearchIndex::build(&data),
+            folder_sync: None,
+            journal: Journal::load(project_dir, &data),
+            audit: AuditLog::open(project_dir),
+            data,
+        })
+    }
+
+    /// Read and migrate the JSON data file. Returns empty data if there is no
+    /// file, and whether a migration changed the document. A file that can't
+    /// be parsed is an error and is left as it is: opening over it would
+    /// replace it, and sync would share the empty data.
+    fn read_json(path: &Path, backups: &BackupManager) -> Result<(AppData, bool), StoreError> {
+        let mut migrated = false;
+        let data = match std::fs::read_to_string(path) {
+            Ok(content) => match serde_json::from_str::<serde_json::Value>(&content) {
+                Ok(doc) => {
+                    let result = migrations::migrate(doc)?;
+                    if result.changed() {
+                        let reason = format!("pre-migration-v{}", result.from);
+                        backups
+                            .snapshot_if_changed(&reason, content.as_bytes())
+                            .map_err(StoreError::io)?;
+                        migrated = true;
+                    }
+                    serde_json::from_value::<AppData>(result.doc)
+                        .map_err(|e| Self::unreadable(path, &e.to_string()))?
+                }
+                Err(e) => return Err(Self::unreadable(path, &e.to_string())),
+            },
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                logging::info(&format!("No data file at {}, creating one", path.display()));
+                AppData::default()
+            }
+            Err(e) => {
+                return Err(StoreError::io(format!(
+                    "Failed to read {}: {}",
+                    path.display(),
+                    e
+                )))
+            }
+        };
+        Ok((data, migrated))
+    }
+
+    fn unreadable(path: &Path, error: &str) -> StoreError {
+        StoreError::invalid(format!(
+            "{} is not valid data ({}). It has been left as it is; fix it or restore a backup, then reopen the app.",
+            path.display(),
+            error
+        ))
+    }
+
+    pub fn project_dir(&self) -> &Path {
+        &self.project_dir
+    }
+
+    /// The file the data is stored in.
+    pub fn path(&self) -> &Path {
+        match &self.backend {
+            Backend::Json(path) => path,
+            #[cfg(feature = "sqlite")]
+            Backend::Sqlite(db) => db.path(),
+        }
+    }
+
+    pub fn data(&self) -> &AppData {
+        &self.data
+    }
+
+    /// Replace the whole document.
+    pub fn replace(&mut self, data: AppData) -> Result<(), StoreError> {
+        self.mutate(|current| {
+            *current = data;
+            Ok(())
+        })
+    }
+
+    /// Replace only the top-level fields present in `patch`.
+    pub fn patch(&mut self, patch: AppDataPatch) -> Result<(), StoreError> {
+        self.mutate(|current| {
+            patch.apply(current);
+            Ok(())
+        })
+    }
+
+    /// Apply a change as a transaction: `change` runs against a copy of the
+    /// document, and the copy only becomes current once it is on disk. An
+    /// error from `change` or from the save leaves the store untouched. The
+    /// search index and the folder sync log are updated with the records
+    /// that changed, and the change is recorded for undo.
+    pub fn mutate<T>(
+        &mut self,
+        change: impl FnOnce(&mut AppData) -> Result<T, StoreError>,
+    ) -> Result<T, StoreError> {
+        let mut next = self.data.clone();
+        let result = change(&mut next)?;
+        let op = self.journal.operation(&self.data, &next);
+        self.write(next, Actor::Local)?;
+        if let Some(op) = op {
+            if let Err(e) = self.journal.push(op) {
+                logging::warn(&format!("Failed to save undo history: {}", e));
+            }
+        }
+        Ok(result)
+    }
+
+    /// Save `next`, then log it for folder sync. A change that fails to save
+    /// is never logged, so other devices can't replay it.
+    fn write(&mut self, next: AppData, actor: Actor) -> Result<(), StoreError> {
+        self.backend.save(&self.data, &next)?;
+        if let Some(sync) = &mut self.folder_sync {
+            // The change is saved either way; it is logged again by the next
+            // launch's catch-up if this fails.
+            if let Err(e) = sync.record(&self.data, &next) {
+                logging::warn(&format!("Folder sync: failed to log change: {}", e));
+            }
+        }
+        self.saved(next, actor);
+        Ok(())
+    }
+
+    fn commit(&mut self, next: AppData, actor: Actor) -> Result<(), StoreError> {
+        self.backend.save(&self.data, &next)?;
+        self.saved(next, actor);
+        Ok(())
+    }
+
+    /// Make `next`, which is already on disk, current.
+    fn saved(&mut self, next: AppData, actor: Actor) {
+        self.index.update(&self.data, &next);
+        if let Err(e) = self.audit.record(&self.data, &next, actor) {
+            logging::warn(&format!("Failed to record change history: {}", e));
+        }
+        self.data = next;
+    }
+
+    /// Revert the latest change that hasn't been undone. Returns it, or
+    /// `None` if there is nothing to undo.
+    pub fn undo(&mut self) -> Result<Option<HistoryEntry>, StoreError> {
+        let Some(op) = self.journal.undo_target().cloned() else {
+            return Ok(None);
+        };
+        if !op.can_undo(&self.data) {
+            self.journal.clear();
+            return Err(StoreError::invalid(format!(
+                "Can't undo \"{}\": its records have changed since (through a sync). The undo history has been cleared.",
+                op.description
+            )));
+        }
+        let mut next = self.data.clone();
+        op.undo(&mut next)?;
+        self.write(next, Actor::Local)?;
+        if let Err(e) = self.journal.mark_undone() {
+            logging::warn(&format!("Failed to save undo history: {}", e));
+        }
+        Ok(Some(op.entry(true)))
+    }
+
+    /// Reapply the change undone last. Returns it, or `None` if there is
+    /// nothing to redo.
+    pub fn redo(&mut self) -> Result<Option<HistoryEntry>, StoreError> {
+        let Some(op) = self.journal.redo_target().cloned() else {
+            return Ok(None);
+        };
+        if !op.can_redo(&self.data) {
+            self.journal.clear();
+            return Err(StoreError::invalid(format!(
+                "Can't redo \"{}\": its records have changed since (through a sync). The undo history has been cleared.",
+                op.description
+            )));
+        }
+        let mut next = self.data.clone();
+        op.redo(&mut next)?;
+        self.write(next, Actor::Local)?;
+        if let Err(e) = self.journal.mark_redone() {
+            logging::warn(&format!("Failed to save undo history: {}", e));
+        }
+        Ok(Some(op.entry(false)))
+    }
+
+    /// Undoable changes, newest first, including undone ones that can be
+    /// redone.
+    pub fn history(&self) -> Vec<HistoryEntry> {
+        self.journal.history()
+    }
+
+    /// Start logging changes to a sync folder, after logging anything the
+    /// folder doesn't know about yet.
+    pub fn attach_folder_sync(&mut self, mut sync: FolderSync) -> Result<(), StoreError> {
+        sync.catch_up(&self.data).map_err(StoreError::io)?;
+        self.folder_sync = Some(sync);
+        Ok(())
+    }
+
+    /// Read new changes from other devices. Returns the merged data if it
+    /// differs from the current data; apply it with `apply_synced`.
+    pub fn poll_folder_sync(&mut self) -> Result<Option<AppData>, StoreError> {
+        let Some(sync) = &mut self.folder_sync else {
+            return Ok(None);
+        };
+        sync.poll().map_err(StoreError::io)?;
+        let merged = sync.materialize(self.data.current_project_id.clone());
+        Ok((merged != self.data).then_some(merged))
+    }
+
+    /// Make data merged with a git remote current. It is logged for folder
+    /// sync like any change, but isn't undoable.
+    pub fn apply_merged(&mut self, data: AppData) -> Result<(), StoreError> {
+        self.write(data, Actor::Sync)
+    }
+
+    /// Make data that came from other devices current, without logging it
+    /// as a change of this device's.
+    pub fn apply_synced(&mut self, data: AppData) -> Result<(), StoreError> {
+        self.commit(data, Actor::Sync)
+    }
+
+    /// Field-level changes to a card, task or note, oldest first.
+    pub fn entity_history(&self, entity: EntityKind, id: &str) -> Result<Vec<AuditEntry>, StoreError> {
+        self.audit.history(entity, id).map_err(StoreError::io)
+    }
+
+    /// Field-level changes to every record of one kind, oldest first.
+    pub fn all_history(&self, entity: EntityKind) -> Result<Vec<AuditEntry>, StoreError> {
+        self.audit.entries(entity).map_err(StoreError::io)
+    }
+
+    pub fn compact_history(&self, older_than_days: u32) -> Result<CompactionReport, StoreError> {
+        self.audit.compact(older_than_days).map_err(StoreError::io)
+    }
+
+    pub fn search(&self, query: &SearchQuery) -> Vec<SearchResult> {
+        self.index.search(query)
+    }
+
+    /// The data serialized as it is in `app-data.json`, for backups.
+    pub fn contents(&self) -> Result<Vec<u8>, StoreError> {
+        serialize(&self.data).map(String::into_bytes)
+    }
+}
+
+fn serialize(data: &AppData) -> Result<String, StoreError> {
+    serde_json::to_string_pretty(data)
+        .map_err(|e| StoreError::io(format!("Failed to serialize data: {}", e)))
+}
+
+/// Write the document to a temp file next to the target, flush it to disk,
+/// then rename it over the target.
+fn save_data(path: &Path, data: &AppData) -> Result<(), StoreError> {
+    let json = serialize(data)?;
+    write_atomic(path, json.as_bytes())?;
+    Ok(())
+}
+
+pub fn write_atomic(path: &Path, bytes: &[u8]) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+    }
+    let file_name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let tmp = path.with_file_name(format!("{}.tmp", file_name));
+    let mut file = std::fs::File::create(&tmp)
+        .map_err(|e| format!("Failed to create {}: {}", tmp.display(), e))?;
+    file.write_all(bytes)
+        .and_then(|_| file.sync_all())
+        .map_err(|e| format!("Failed to write {}: {}", tmp.display(), e))?;
+    drop(file);
+    std::fs::rename(&tmp, path)
+        .map_err(|e| format!("Failed to replace {}: {}", path.display(), e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::folder_sync::FolderSyncConfig;
+    use crate::models::Project;
+    use crate::test_util::TempDir;
+
+    #[test]
+    fn refuses_data_it_cannot_load() {
+        let dir = TempDir::new("store");
+        let project_dir = dir.as_str();
+        let path = DataStore::data_path(project_dir);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        let backups = BackupManager::new(project_dir);
+
+        let wrong_shape = format!(
+            "{{\"schemaVersion\": {}, \"projects\": [{{\"id\": 7}}]}}",
+            migrations::CURRENT_VERSION
+        );
+        for content in ["{\"projects\": [", wrong_shape.as_str()] {
+            std::fs::write(&path, content).unwrap();
+            let err = DataStore::open(project_dir, &backups).err().unwrap();
+            assert!(matches!(err, StoreError::Invalid { .. }), "{:?}", err);
+            assert_eq!(std::fs::read_to_string(&path).unwrap(), content);
+            let names: Vec<String> = std::fs::read_dir(path.parent().unwrap())
+                .unwrap()
+                .map(|e| e.unwrap().file_name().to_string_lossy().to_string())
+                .collect();
+            assert!(!names.iter().any(|n| n.starts_with("app-data.json.")), "{:?}", names);
+        }
+    }
+
+    #[test]
+    fn failed_saves_are_not_shared() {
+        let dir = TempDir::new("store-sync");
+        let project_dir = dir.join("laptop");
+        let project_dir = project_dir.to_str().unwrap();
+        let config = FolderSyncConfig {
+            folder: dir.join("shared").to_string_lossy().to_string(),
+        };
+        let mut store = DataStore::open(project_dir, &BackupManager::new(project_dir)).unwrap();
+        store.attach_folder_sync(FolderSync::open(project_dir, &config).unwrap()).unwrap();
+
+        // A data file whose directory is a file can't be saved.
+        let blocked = dir.join("blocked");
+        std::fs::write(&blocked, "").unwrap();
+        store.backend = Backend::Json(blocked.join("app-data.json"));
+        let err = store
+            .mutate(|d| {
+                d.projects.push(Project {
+                    id: "p1".to_string(),
+                    name: "Unsaved".to_string(),
+                    description: None,
+                    created_at: 0,
+                    updated_at: 0,
+                });
+                Ok(())
+            })
+            .err()
+            .unwrap();
+        assert!(matches!(err, StoreError::Io { .. }), "{:?}", err);
+        assert!(store.data().projects.is_empty());
+        assert_eq!(store.poll_folder_sync().unwrap(), None);
+
+        let other_dir = dir.join("desktop");
+        let mut other = FolderSync::open(other_dir.to_str().unwrap(), &config).unwrap();
+        other.poll().unwrap();
+        assert!(other.materialize(None).projects.is_empty());
+    }
+}
@@ -0,0 +1,129 @@
+//! Restart policy for the Next.js server once the app is up. The launcher
+//! watches the child process and health-checks its port; each crash or hang
+//! is a failure, restarts back off exponentially, and the launcher gives up
+//! after `max_failures` failures in a row.
+//!
+//! A server that stays healthy for `stable_after` is considered recovered,
+//! so a crash days later starts again from the shortest delay.
+
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RestartPolicy {
+    pub max_failures: u32,
+    pub initial_delay: Duration,
+    pub max_delay: Duration,
+    pub stable_after: Duration,
+}
+
+impl Default for RestartPolicy {
+    fn default() -> Self {
+        RestartPolicy {
+            max_failures: 5,
+            initial_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(30),
+            stable_after: Duration::from_secs(120),
+        }
+    }
+}
+
+/// Consecutive-failure count for the running server.
+#[derive(Debug)]
+pub struct Backoff {
+    policy: RestartPolicy,
+    failures: u32,
+    healthy_since: Option<Instant>,
+}
+
+impl Backoff {
+    pub fn new(policy: RestartPolicy) -> Self {
+        Backoff {
+            policy,
+            failures: 0,
+            healthy_since: None,
+        }
+    }
+
+    pub fn failures(&self) -> u32 {
+        self.failures
+    }
+
+    /// The server came up and answered.
+    pub fn healthy(&mut self, now: Instant) {
+        self.healthy_since = Some(now);
+    }
+
+    /// Record a crash, hang or failed restart. Returns how long to wait
+    /// before the next restart, or `None` to give up.
+    pub fn failed(&mut self, now: Instant) -> Option<Duration> {
+        let recovered = self
+            .healthy_since
+            .is_some_and(|since| now.duration_since(since) >= self.policy.stable_after);
+        if recovered {
+            self.failures = 0;
+        }
+        self.healthy_since = None;
+        self.failures += 1;
+        if self.failures > self.policy.max_failures {
+            return None;
+        }
+        let delay = self
+            .policy
+            .initial_delay
+            .saturating_mul(1 << (self.failures - 1).min(16));
+        Some(delay.min(self.policy.max_delay))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy() -> RestartPolicy {
+        RestartPolicy {
+            max_failures: 4,
+            initial_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(5),
+            stable_after: Duration::from_secs(60),
+        }
+    }
+
+    #[test]
+    fn backs_off_then_gives_up() {
+        let mut backoff = Backoff::new(policy());
+        let now = Instant::now();
+        let delays: Vec<_> = (0..5).map(|_| backoff.failed(now)).collect();
+        assert_eq!(
+            delays,
+            [
+                Some(Duration::from_secs(1)),
+                Some(Duration::from_secs(2)),
+                Some(Duration::from_secs(4)),
+                Some(Duration::from_secs(5)),
+                None,
+            ]
+        );
+    }
+
+    #[test]
+    fn a_stable_server_starts_over() {
+        let mut backoff = Backoff::new(policy());
+        let start = Instant::now();
+        backoff.failed(start);
+        backoff.failed(start);
+
+        // Back up, but crashed again before it counted as recovered.
+        backoff.healthy(start);
+        assert_eq!(
+            backoff.failed(start + Duration::from_secs(30)),
+            Some(Duration::from_secs(4))
+        );
+
+        backoff.healthy(start);
+        assert_eq!(
+            backoff.failed(start + Duration::from_secs(90)),
+            Some(Duration::from_secs(1))
+        );
+        assert_eq!(backoff.failures(), 1);
+    }
+}
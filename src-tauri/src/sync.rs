@@ -0,0 +1,408 @@
+//! Git-backed sync of the app data between machines. The data is committed
+//! as `app-data.json` to a branch of a remote repository — a dedicated
+//! branch of the code repo or a separate repo, per `sync-config.json` — from
+//! a clone in `data/sync/` that is separate from the code checkout.
+//!
+//! The sync clone's `HEAD` is always the last state known to be on the
+//! remote, which makes it the common ancestor for the next sync: local data
+//! and the remote branch are merged record by record (see `merge.rs`) rather
+//! than textually, and the result is committed on top of the remote and
+//! pushed. Like `run_git_pull`, this shells out to the user's `git`, so it
+//! uses their credentials.
+
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use crate::logging;
+use crate::merge::{self, Conflict};
+use crate::models::AppData;
+
+pub const CONFIG_FILE: &str = "sync-config.json";
+pub const SYNC_DIR: &str = "data/sync";
+const DATA_FILE: &str = "app-data.json";
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncConfig {
+    /// Anything `git remote add` accepts: a URL, or a path to a bare repo.
+    pub remote: String,
+    #[serde(default = "default_branch")]
+    pub branch: String,
+    /// Minutes between background syncs; 0 syncs only on launch and on
+    /// request.
+    #[serde(default = "default_interval")]
+    pub interval_minutes: u64,
+    /// Author of sync commits. Defaults to the host name.
+    #[serde(default)]
+    pub device_name: Option<String>,
+}
+
+fn default_branch() -> String {
+    String::from("tpm-data")
+}
+
+fn default_interval() -> u64 {
+    15
+}
+
+impl SyncConfig {
+    /// `None` if sync isn't configured for this machine.
+    pub fn load(project_dir: &str) -> Option<Self> {
+        let path = Path::new(project_dir).join(CONFIG_FILE);
+        let content = std::fs::read_to_string(&path).ok()?;
+        match serde_json::from_str(&content) {
+            Ok(config) => Some(config),
+            Err(e) => {
+                logging::warn(&format!("Ignoring invalid {}: {}", CONFIG_FILE, e));
+                None
+            }
+        }
+    }
+}
+
+/// What a sync did, for the caller to apply and report.
+#[derive(Debug, Clone)]
+pub struct SyncOutcome {
+    /// The merged data. Already committed; the caller should make it current.
+    pub data: AppData,
+    pub conflicts: Vec<Conflict>,
+    /// Whether the remote had changes this machine didn't.
+    pub pulled: bool,
+    pub pushed: bool,
+    pub commit: Option<String>,
+    /// Set if the merge was committed but couldn't be pushed; it will be
+    /// retried on the next sync.
+    pub push_error: Option<String>,
+}
+
+/// Result of the last sync, as shown to the webview.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncReport {
+    pub finished_at: i64,
+    pub pulled: bool,
+    pub pushed: bool,
+    /// Whether this machine's data changed.
+    pub changed: bool,
+    pub commit: Option<String>,
+    pub conflicts: Vec<Conflict>,
+    pub error: Option<String>,
+}
+
+impl SyncReport {
+    pub fn failed(error: String) -> Self {
+        SyncReport {
+            finished_at: crate::mutations::now_millis(),
+            pulled: false,
+            pushed: false,
+            changed: false,
+            commit: None,
+            conflicts: Vec::new(),
+            error: Some(error),
+        }
+    }
+}
+
+pub struct GitSync {
+    dir: PathBuf,
+    config: SyncConfig,
+    device: String,
+}
+
+impl GitSync {
+    pub fn new(project_dir: &str, config: SyncConfig) -> Self {
+        let device = config.device_name.clone().unwrap_or_else(host_name);
+        GitSync {
+            dir: Path::new(project_dir).join(SYNC_DIR),
+            config,
+            device,
+        }
+    }
+
+    fn git(&self, args: &[&str]) -> Result<String, String> {
+        let output = Command::new("git")
+            .args(args)
+            .current_dir(&self.dir)
+            .env("PATH", crate::get_enhanced_path())
+            // Fail instead of waiting on a credential prompt nobody can see.
+            .env("GIT_TERMINAL_PROMPT", "0")
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .output()
+            .map_err(|e| format!("git {} failed to execute: {}", args[0], e))?;
+        if output.status.success() {
+            Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+        } else {
+            Err(format!(
+                "git {} failed: {}",
+                args[0],
+                String::from_utf8_lossy(&output.stderr).trim()
+            ))
+        }
+    }
+
+    fn remote_ref(&self) -> String {
+        format!("refs/remotes/origin/{}", self.config.branch)
+    }
+
+    /// Create the sync clone on first use, and keep its remote in line with
+    /// the config.
+    fn ensure_repo(&self) -> Result<(), String> {
+        if !self.dir.join(".git").exists() {
+            std::fs::create_dir_all(&self.dir)
+                .map_err(|e| format!("Failed to create {}: {}", self.dir.display(), e))?;
+            self.git(&["init", "--quiet"])?;
+            self.git(&["remote", "add", "origin", &self.config.remote])?;
+            logging::info(&format!("Created sync repo in {}", self.dir.display()));
+        } else {
+            self.git(&["remote", "set-url", "origin", &self.config.remote])?;
+        }
+        let head = format!("refs/heads/{}", self.config.branch);
+        self.git(&["symbolic-ref", "HEAD", &head])?;
+        Ok(())
+    }
+
+    fn rev(&self, rev: &str) -> Option<String> {
+        self.git(&["rev-parse", "--verify", "--quiet", &format!("{}^{{commit}}", rev)])
+            .ok()
+            .filter(|s| !s.is_empty())
+    }
+
+    fn read_at(&self, rev: &str) -> Result<AppData, String> {
+        let content = self.git(&["show", &format!("{}:{}", rev, DATA_FILE)])?;
+        let doc = serde_json::from_str(&content)
+            .map_err(|e| format!("Synced data at {} is invalid: {}", rev, e))?;
+        let migrated = crate::migrations::migrate(doc).map_err(|e| e.to_string())?;
+        serde_json::from_value(migrated.doc).map_err(|e| format!("Synced data at {} is invalid: {}", rev, e))
+    }
+
+    /// Fetch the remote branch. Returns its commit, or `None` if the branch
+    /// doesn't exist yet.
+    fn fetch(&self) -> Result<Option<String>, String> {
+        let branch = &self.config.branch;
+        let heads = self.git(&["ls-remote", "--heads", "origin", branch])?;
+        if heads.is_empty() {
+            return Ok(None);
+        }
+        let refspec = format!("+refs/heads/{}:{}", branch, self.remote_ref());
+        self.git(&["fetch", "--quiet", "origin", &refspec])?;
+        Ok(self.rev(&self.remote_ref()))
+    }
+
+    /// Merge `local` with the remote branch, commit the result on top of it
+    /// and push.
+    pub fn sync(&self, local: &AppData) -> Result<SyncOutcome, String> {
+        self.ensure_repo()?;
+        let head = self.rev("HEAD");
+        let remote = self.fetch()?;
+
+        let base = match &head {
+            Some(head) => self.read_at(head)?,
+            None => AppData::default(),
+        };
+        // Local data that is empty when the last sync wasn't means the data
+        // file was lost or reset; merging it would delete every record on
+        // every machine.
+        let local = if local.is_empty() && !base.is_empty() {
+            logging::warn("Sync: local data is empty, taking the synced data instead of deleting it");
+            &base
+        } else {
+            local
+        };
+        let (data, conflicts) = match &remote {
+            Some(tip) => {
+                let result = merge::merge(&base, local, &self.read_at(tip)?);
+                (result.data, result.conflicts)
+            }
+            None => (local.clone(), Vec::new()),
+        };
+        let pulled = remote.is_some() && remote != head;
+        for conflict in &conflicts {
+            logging::info(&format!(
+                "Sync conflict on {} {}: kept {:?} version",
+                conflict.entity, conflict.id, conflict.kept
+            ));
+        }
+
+        // Build the commit on top of the remote tip. Only `app-data.json` is
+        // tracked, so writing it and re-adding it defines the whole index.
+        if let Some(tip) = &remote {
+            self.git(&["update-ref", "HEAD", tip])?;
+        }
+        let json = serde_json::to_string_pretty(&data).map_err(|e| format!("Failed to serialize data: {}", e))?;
+        crate::store::write_atomic(&self.dir.join(DATA_FILE), json.as_bytes())?;
+        self.git(&["add", DATA_FILE])?;
+        let unchanged = remote.is_some() && self.git(&["diff", "--cached", "--quiet", "HEAD"]).is_ok();
+        if unchanged {
+            logging::info("Sync: nothing to push");
+            return Ok(SyncOutcome {
+                data,
+                conflicts,
+                pulled,
+                pushed: false,
+                commit: remote,
+                push_error: None,
+            });
+        }
+
+        let name = format!("user.name={}", self.device);
+        let email = format!("user.email=total-tpm@{}", self.device);
+        let message = format!("Sync from {}", self.device);
+        self.git(&["-c", &name, "-c", &email, "commit", "--quiet", "-m", &message])?;
+        let commit = self.rev("HEAD");
+
+        let target = format!("HEAD:refs/heads/{}", self.config.branch);
+        let push_error = match self.git(&["push", "--quiet", "origin", &target]) {
+            Ok(_) => {
+                logging::info(&format!("Sync: pushed {}", commit.as_deref().unwrap_or("?")));
+                None
+            }
+            Err(e) => {
+                // HEAD must stay the last state both sides have, or the next
+                // merge would treat this commit's changes as the base.
+                logging::warn(&format!("Sync push failed: {}", e));
+                match &remote {
+                    Some(tip) => self.git(&["update-ref", "HEAD", tip])?,
+                    None => self.git(&["update-ref", "-d", "HEAD"])?,
+                };
+                Some(e)
+            }
+        };
+
+        Ok(SyncOutcome {
+            data,
+            conflicts,
+            pulled,
+            pushed: push_error.is_none(),
+            commit,
+            push_error,
+        })
+    }
+}
+
+fn host_name() -> String {
+    Command::new("hostname")
+        .output()
+        .ok()
+        .map(|o| String::from_utf8_lossy(&o.stdout).trim().to_string())
+        .filter(|h| !h.is_empty())
+        .unwrap_or_else(|| String::from("unknown"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::Project;
+    use crate::test_util::TempDir;
+
+    fn device(tmp: &TempDir, name: &str) -> GitSync {
+        let remote = tmp.join("remote.git").to_string_lossy().to_string();
+        if !Path::new(&remote).exists() {
+            let status = Command::new("git")
+                .args(["init", "--bare", "--quiet", &remote])
+                .status()
+                .unwrap();
+            assert!(status.success());
+        }
+        let config = SyncConfig {
+            remote,
+            branch: default_branch(),
+            interval_minutes: 0,
+            device_name: Some(name.to_string()),
+        };
+        GitSync::new(&tmp.join(name).to_string_lossy(), config)
+    }
+
+    fn project(id: &str, name: &str, updated_at: i64) -> Project {
+        Project {
+            id: id.to_string(),
+            name: name.to_string(),
+            description: None,
+            created_at: 0,
+            updated_at,
+        }
+    }
+
+    fn names(data: &AppData) -> Vec<&str> {
+        data.projects.iter().map(|p| p.name.as_str()).collect()
+    }
+
+    #[test]
+    fn edits_on_two_machines_merge() {
+        let tmp = TempDir::new("sync-merge");
+        let laptop = device(&tmp, "laptop");
+        let desktop = device(&tmp, "desktop");
+
+        let mut laptop_data = AppData::default();
+        laptop_data.projects.push(project("a", "A", 1));
+        let first = laptop.sync(&laptop_data).unwrap();
+        assert!(first.pushed && !first.pulled);
+
+        // The desktop starts empty and picks up the laptop's project.
+        let mut desktop_data = desktop.sync(&AppData::default()).unwrap().data;
+        assert_eq!(names(&desktop_data), ["A"]);
+
+        // Both edit different records before syncing again.
+        laptop_data.projects.push(project("b", "B", 2));
+        desktop_data.projects[0].name = "A renamed".into();
+        desktop_data.projects[0].updated_at = 3;
+        desktop.sync(&desktop_data).unwrap();
+        let merged = laptop.sync(&laptop_data).unwrap();
+
+        assert!(merged.pulled && merged.pushed);
+        assert!(merged.conflicts.is_empty());
+        assert_eq!(names(&merged.data), ["A renamed", "B"]);
+        assert_eq!(names(&desktop.sync(&desktop_data).unwrap().data), ["A renamed", "B"]);
+    }
+
+    #[test]
+    fn reset_data_does_not_delete_everything() {
+        let tmp = TempDir::new("sync-reset");
+        let laptop = device(&tmp, "laptop");
+        let desktop = device(&tmp, "desktop");
+
+        let mut data = AppData::default();
+        data.projects.push(project("a", "A", 1));
+        laptop.sync(&data).unwrap();
+        desktop.sync(&data).unwrap();
+
+        let outcome = laptop.sync(&AppData::default()).unwrap();
+        assert_eq!(names(&outcome.data), ["A"]);
+        assert!(!outcome.pushed);
+        assert_eq!(names(&desktop.sync(&data).unwrap().data), ["A"]);
+    }
+
+    #[test]
+    fn same_record_edited_twice_is_a_conflict() {
+        let tmp = TempDir::new("sync-conflict");
+        let laptop = device(&tmp, "laptop");
+        let desktop = device(&tmp, "desktop");
+
+        let mut data = AppData::default();
+        data.projects.push(project("a", "A", 1));
+        laptop.sync(&data).unwrap();
+        desktop.sync(&data).unwrap();
+
+        let mut on_laptop = data.clone();
+        on_laptop.projects[0] = project("a", "Laptop", 5);
+        let mut on_desktop = data.clone();
+        on_desktop.projects[0] = project("a", "Desktop", 4);
+        laptop.sync(&on_laptop).unwrap();
+        let outcome = desktop.sync(&on_desktop).unwrap();
+
+        assert_eq!(outcome.conflicts.len(), 1);
+        assert_eq!(names(&outcome.data), ["Laptop"]);
+    }
+
+    #[test]
+    fn unchanged_data_is_not_committed() {
+        let tmp = TempDir::new("sync-noop");
+        let laptop = device(&tmp, "laptop");
+        let data = AppData::default();
+        let first = laptop.sync(&data).unwrap();
+        let second = laptop.sync(&data).unwrap();
+        assert!(!second.pushed);
+        assert_eq!(first.commit, second.commit);
+    }
+}
@@ -0,0 +1,374 @@
+//! Bulk task creation from a CSV file. The first row is the header; the user
+//! maps CSV columns to task fields (a mapping is suggested from the header
+//! names), every row is validated, and the tasks are only created if all
+//! rows are valid, in a single write.
+//!
+//! Accepted values, case-insensitively:
+//! - status: `todo`, `to do`, `in-progress`, `in progress`, `doing`, `done`
+//!   (or the `TaskStatus` spellings); empty is `todo`.
+//! - priority: `p0` to `p4`, or `0` to `4`; empty is `p2`.
+//! - due date: `YYYY-MM-DD`, optionally followed by a time, which is ignored.
+
+use serde::{Deserialize, Serialize};
+
+use crate::csv::Table;
+use crate::error::StoreError;
+use crate::models::{TaskPriority, TaskStatus};
+use crate::mutations::NewTask;
+
+/// Rows shown in the preview.
+const PREVIEW_ROWS: usize = 20;
+/// Row errors returned by a validation; the rest are only counted.
+const MAX_ERRORS: usize = 100;
+
+/// Which CSV column (by index) fills each task field. Only `title` is
+/// required.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ColumnMapping {
+    pub title: Option<usize>,
+    pub description: Option<usize>,
+    pub status: Option<usize>,
+    pub priority: Option<usize>,
+    pub due_date: Option<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RowError {
+    /// 1-based, counting the header as row 1, as spreadsheets number them.
+    pub row: usize,
+    pub field: String,
+    pub value: String,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CsvPreview {
+    pub headers: Vec<String>,
+    /// The first rows after the header.
+    pub rows: Vec<Vec<String>>,
+    pub total_rows: usize,
+    pub delimiter: String,
+    /// The mapping that was validated: the one given, or one guessed from
+    /// the headers.
+    pub mapping: ColumnMapping,
+    pub valid_rows: usize,
+    pub errors: Vec<RowError>,
+    pub error_count: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CsvImportReport {
+    pub project_id: String,
+    pub created: usize,
+    pub task_ids: Vec<String>,
+}
+
+/// A mapping guessed from header names.
+pub fn suggest_mapping(headers: &[String]) -> ColumnMapping {
+    let find = |names: &[&str]| {
+        headers.iter().position(|h| {
+            let h: String = h
+                .to_lowercase()
+                .chars()
+                .filter(|c| c.is_alphanumeric())
+                .collect();
+            names.contains(&h.as_str())
+        })
+    };
+    ColumnMapping {
+        title: find(&["title", "name", "task", "summary", "taskname"]),
+        description: find(&["description", "details", "notes", "body"]),
+        status: find(&["status", "state"]),
+        priority: find(&["priority", "prio"]),
+        due_date: find(&["duedate", "due", "deadline", "duedateutc"]),
+    }
+}
+
+/// Check every row against `mapping`. Returns the tasks to create for
+/// `project_id`, or the errors (at most `MAX_ERRORS`) and their total count.
+pub fn validate(
+    table: &Table,
+    mapping: &ColumnMapping,
+    project_id: &str,
+) -> Result<Vec<NewTask>, (Vec<RowError>, usize)> {
+    let width = table.rows.first().map_or(0, Vec::len);
+    let mut errors = Errors::default();
+
+    let Some(title_column) = mapping.title else {
+        errors.push(1, "title", "", "Map a column to the task title".to_string());
+        return Err(errors.into_parts());
+    };
+    for (field, column) in [
+        ("title", Some(title_column)),
+        ("description", mapping.description),
+        ("status", mapping.status),
+        ("priority", mapping.priority),
+        ("dueDate", mapping.due_date),
+    ] {
+        if let Some(column) = column.filter(|c| *c >= width) {
+            errors.push(
+                1,
+                field,
+                "",
+                format!(
+                    "Column {} does not exist; the file has {}",
+                    column + 1,
+                    width
+                ),
+            );
+        }
+    }
+    if errors.count > 0 {
+        return Err(errors.into_parts());
+    }
+
+    let mut tasks = Vec::new();
+    for (i, row) in table.rows.iter().enumerate().skip(1) {
+        let number = i + 1;
+        let cell = |column: Option<usize>| column.and_then(|c| row.get(c)).map_or("", |v| v.trim());
+
+        let title = cell(Some(title_column));
+        if title.is_empty() {
+            errors.push(number, "title", title, "Title is required".to_string());
+        }
+        let status = parse_status(cell(mapping.status));
+        if let Err(message) = &status {
+            errors.push(number, "status", cell(mapping.status), message.clone());
+        }
+        let priority = parse_priority(cell(mapping.priority));
+        if let Err(message) = &priority {
+            errors.push(number, "priority", cell(mapping.priority), message.clone());
+        }
+        let due_date = parse_date(cell(mapping.due_date));
+        if let Err(message) = &due_date {
+            errors.push(number, "dueDate", cell(mapping.due_date), message.clone());
+        }
+
+        if let (false, Ok(status), Ok(priority), Ok(due_date)) =
+            (title.is_empty(), status, priority, due_date)
+        {
+            let description = cell(mapping.description);
+            tasks.push(NewTask {
+                project_id: project_id.to_string(),
+                title: title.to_string(),
+                description: (!description.is_empty()).then(|| description.to_string()),
+                status,
+                priority,
+                due_date,
+                card_id: None,
+                links: None,
+                comments: None,
+            });
+        }
+    }
+    if errors.count > 0 {
+        return Err(errors.into_parts());
+    }
+    Ok(tasks)
+}
+
+/// Row errors, keeping the first `MAX_ERRORS`.
+#[derive(Default)]
+struct Errors {
+    list: Vec<RowError>,
+    count: usize,
+}
+
+impl Errors {
+    fn push(&mut self, row: usize, field: &str, value: &str, message: String) {
+        self.count += 1;
+        if self.list.len() < MAX_ERRORS {
+            self.list.push(RowError {
+                row,
+                field: field.to_string(),
+                value: value.to_string(),
+                message,
+            });
+        }
+    }
+
+    fn into_parts(self) -> (Vec<RowError>, usize) {
+        (self.list, self.count)
+    }
+}
+
+/// The header and first rows of `table`, validated against `mapping` (or a
+/// suggested one).
+pub fn preview(table: &Table, mapping: Option<ColumnMapping>) -> CsvPreview {
+    let headers = table.rows.first().cloned().unwrap_or_default();
+    let mapping = mapping.unwrap_or_else(|| suggest_mapping(&headers));
+    let total_rows = table.rows.len().saturating_sub(1);
+    let (valid_rows, errors, error_count) = match validate(table, &mapping, "") {
+        Ok(tasks) => (tasks.len(), Vec::new(), 0),
+        // A mapping error (reported on the header row) stops validation
+        // before any row is looked at.
+        Err((errors, count)) if errors.iter().any(|e| e.row == 1) => (0, errors, count),
+        Err((errors, count)) => {
+            let bad_rows: std::collections::HashSet<usize> = errors.iter().map(|e| e.row).collect();
+            (total_rows.saturating_sub(bad_rows.len()), errors, count)
+        }
+    };
+    CsvPreview {
+        rows: table
+            .rows
+            .iter()
+            .skip(1)
+            .take(PREVIEW_ROWS)
+            .cloned()
+            .collect(),
+        headers,
+        total_rows,
+        delimiter: table.delimiter.to_string(),
+        mapping,
+        valid_rows,
+        errors,
+        error_count,
+    }
+}
+
+pub fn read(path: &str) -> Result<Table, StoreError> {
+    let text = std::fs::read_to_string(path)
+        .map_err(|e| StoreError::invalid(format!("Failed to read {}: {}", path, e)))?;
+    let table = crate::csv::parse(&text).map_err(StoreError::invalid)?;
+    if table.rows.is_empty() {
+        return Err(StoreError::invalid(format!("{} is empty", path)));
+    }
+    Ok(table)
+}
+
+fn parse_status(value: &str) -> Result<Option<TaskStatus>, String> {
+    let normalized: String = value
+        .to_lowercase()
+        .chars()
+        .filter(|c| c.is_alphanumeric())
+        .collect();
+    match normalized.as_str() {
+        "" => Ok(None),
+        "todo" => Ok(Some(TaskStatus::Todo)),
+        "inprogress" | "doing" => Ok(Some(TaskStatus::InProgress)),
+        "done" => Ok(Some(TaskStatus::Done)),
+        _ => Err(format!(
+            "Unknown status \"{}\"; use todo, in-progress or done",
+            value
+        )),
+    }
+}
+
+fn parse_priority(value: &str) -> Result<Option<TaskPriority>, String> {
+    let lower = value.to_lowercase();
+    let digit = lower.strip_prefix('p').unwrap_or(&lower);
+    match digit {
+        "" if value.is_empty() => Ok(None),
+        "0" => Ok(Some(TaskPriority::P0)),
+        "1" => Ok(Some(TaskPriority::P1)),
+        "2" => Ok(Some(TaskPriority::P2)),
+        "3" => Ok(Some(TaskPriority::P3)),
+        "4" => Ok(Some(TaskPriority::P4)),
+        _ => Err(format!("Unknown priority \"{}\"; use p0 to p4", value)),
+    }
+}
+
+/// `YYYY-MM-DD` as noon UTC that day, the way the date pickers store dates.
+pub fn parse_date(value: &str) -> Result<Option<i64>, String> {
+    if value.is_empty() {
+        return Ok(None);
+    }
+    let invalid = || format!("Invalid date \"{}\"; use YYYY-MM-DD", value);
+    let date = value.get(..10).ok_or_else(invalid)?;
+    if value.len() > 10 && !value[10..].starts_with(['T', ' ']) {
+        return Err(invalid());
+    }
+    let parts: Vec<&str> = date.split('-').collect();
+    let [y, m, d] = parts[..] else {
+        return Err(invalid());
+    };
+    if y.len() != 4 || m.len() != 2 || d.len() != 2 {
+        return Err(invalid());
+    }
+    let (Ok(y), Ok(m), Ok(d)) = (y.parse::<i64>(), m.parse::<i64>(), d.parse::<i64>()) else {
+        return Err(invalid());
+    };
+    let days = crate::backup::days_from_civil(y, m, d);
+    // Reject dates like 2026-02-30 that would roll over.
+    let (cy, cm, cd) = crate::backup::civil_from_days(days);
+    if !(1..=12).contains(&m) || (cy, i64::from(cm), i64::from(cd)) != (y, m, d) {
+        return Err(invalid());
+    }
+    Ok(Some(days * 86_400_000 + 12 * 3_600_000))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table(text: &str) -> Table {
+        crate::csv::parse(text).unwrap()
+    }
+
+    #[test]
+    fn suggests_mapping_from_headers() {
+        let headers: Vec<String> = ["Task Name", "Status", "Due Date", "Priority", "Details"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        assert_eq!(
+            suggest_mapping(&headers),
+            ColumnMapping {
+                title: Some(0),
+                description: Some(4),
+                status: Some(1),
+                priority: Some(3),
+                due_date: Some(2),
+            }
+        );
+    }
+
+    #[test]
+    fn validates_every_row() {
+        let t = table(
+            "Title,Status,Priority,Due\n\
+             Ok,In Progress,P1,2026-01-14\n\
+             ,done,p9,2026-02-30\n\
+             Defaults,,,\n",
+        );
+        let mapping = suggest_mapping(&t.rows[0]);
+        let (errors, count) = validate(&t, &mapping, "p").unwrap_err();
+        assert_eq!(count, 3);
+        let fields: Vec<(usize, &str)> = errors.iter().map(|e| (e.row, e.field.as_str())).collect();
+        assert_eq!(fields, [(3, "title"), (3, "priority"), (3, "dueDate")]);
+
+        let preview = preview(&t, None);
+        assert_eq!(
+            (preview.total_rows, preview.valid_rows, preview.error_count),
+            (3, 2, 3)
+        );
+    }
+
+    #[test]
+    fn builds_tasks() {
+        let t = table("Title;Status;Priority;Due\nShip;in-progress;2;2026-01-14T09:00\nPlan;;;\n");
+        let tasks = validate(&t, &suggest_mapping(&t.rows[0]), "p").unwrap();
+        assert_eq!(tasks.len(), 2);
+        assert_eq!(tasks[0].status, Some(TaskStatus::InProgress));
+        assert_eq!(tasks[0].priority, Some(TaskPriority::P2));
+        assert_eq!(tasks[0].due_date, Some(1_768_392_000_000));
+        assert_eq!(tasks[1].status, None);
+        assert_eq!(tasks[1].due_date, None);
+    }
+
+    #[test]
+    fn requires_a_title_column() {
+        let t = table("a,b\n1,2\n");
+        let mapping = ColumnMapping {
+            title: Some(5),
+            ..ColumnMapping::default()
+        };
+        let (errors, _) = validate(&t, &mapping, "p").unwrap_err();
+        assert!(errors[0].message.contains("does not exist"));
+        assert!(validate(&t, &ColumnMapping::default(), "p").is_err());
+    }
+}
@@ -4,6 +4,30 @@ import path from 'path';
 
 const DATA_FILE_PATH = path.join(process.cwd(), 'data', 'app-data.json');
 
+// When the desktop app serves the data (its `http-server.json`), e.g.
+// TOTAL_TPM_API_URL=http://127.0.0.1:3001, requests are forwarded there so
+// the browser and the desktop app write through the same store.
+const API_URL = process.env.TOTAL_TPM_API_URL;
+
+async function forward(method: 'GET' | 'PUT', body?: string): Promise<NextResponse> {
+  try {
+    const response = await fetch(new URL('/api/data', API_URL), {
+      method,
+      body,
+      headers: body ? { 'Content-Type': 'application/json' } : undefined,
+      cache: 'no-store',
+    });
+    return new NextResponse(await response.text(), {
+      status: response.status,
+      headers: { 'Content-Type': 'application/json' },
+    });
+  } catch (error) {
+    // Don't fall back to the local file: it may be older than the store.
+    console.error('Error reaching the desktop data server:', error);
+    return NextResponse.json({ error: 'Data server unavailable' }, { status: 502 });
+  }
+}
+
 interface AppData {
   projects: unknown[];
   columns: unknown[];
@@ -33,6 +57,9 @@ async function ensureDataFile(): Promise<void> {
 }
 
 export async function GET() {
+  if (API_URL) {
+    return forward('GET');
+  }
   try {
     await ensureDataFile();
     const fileContent = await fs.readFile(DATA_FILE_PATH, 'utf-8');
@@ -45,6 +72,9 @@ export async function GET() {
 }
 
 export async function PUT(request: NextRequest) {
+  if (API_URL) {
+    return forward('PUT', await request.text());
+  }
   try {
     const data = await request.json();
 
@@ -1,7 +1,7 @@
 'use client';
 
 import { useState, useEffect, useCallback, useRef } from 'react';
-import { loadData, saveData, type AppData } from '@/lib/api';
+import { loadData, onDataChanged, saveData, type AppData } from '@/lib/api';
 
 const DEFAULT_DATA: AppData = {
   projects: [],
@@ -49,6 +49,18 @@ export function useApiStorage() {
     };
   }, []);
 
+  // Reload when the desktop app changes the data behind our back. A pending
+  // save would overwrite those changes with stale data, so it is dropped.
+  useEffect(() => {
+    return onDataChanged(async () => {
+      if (saveTimeoutRef.current) {
+        clearTimeout(saveTimeoutRef.current);
+      }
+      pendingDataRef.current = null;
+      setData(await loadData());
+    });
+  }, []);
+
   // Debounced save function
   const debouncedSave = useCallback(async (newData: AppData) => {
     // Store the latest data to save
@@ -1,3 +1,5 @@
+import { invoke, isTauri } from '@tauri-apps/api/core';
+import { listen } from '@tauri-apps/api/event';
 import type { Project, KanbanColumn, KanbanCard, Task, Note } from '@/types';
 
 export interface AppData {
@@ -18,13 +20,22 @@ const DEFAULT_DATA: AppData = {
   currentProjectId: null,
 };
 
+// In the desktop app the Rust data store owns app-data.json; the browser
+// version falls back to the Next.js API route.
+async function fetchData(): Promise<Partial<AppData>> {
+  if (isTauri()) {
+    return invoke<AppData>('get_data');
+  }
+  const response = await fetch('/api/data');
+  if (!response.ok) {
+    throw new Error('Failed to load data');
+  }
+  return response.json();
+}
+
 export async function loadData(): Promise<AppData> {
   try {
-    const response = await fetch('/api/data');
-    if (!response.ok) {
-      throw new Error('Failed to load data');
-    }
-    const data = await response.json();
+    const data = await fetchData();
     return {
       projects: data.projects ?? [],
       columns: data.columns ?? [],
@@ -41,6 +52,10 @@ export async function loadData(): Promise<AppData> {
 
 export async function saveData(data: AppData): Promise<boolean> {
   try {
+    if (isTauri()) {
+      await invoke('put_data', { data });
+      return true;
+    }
     const response = await fetch('/api/data', {
       method: 'PUT',
       headers: {
@@ -57,3 +72,15 @@ export async function saveData(data: AppData): Promise<boolean> {
     return false;
   }
 }
+
+// Fired by the desktop app when the stored data changes outside the webview
+// (e.g. after a sync). Returns an unsubscribe function.
+export function onDataChanged(callback: () => void): () => void {
+  if (!isTauri()) {
+    return () => {};
+  }
+  const unlisten = listen('data-changed', callback);
+  return () => {
+    unlisten.then((fn) => fn());
+  };
+}
@@ -1,5 +1,6 @@
 import { clsx, type ClassValue } from 'clsx';
 import { twMerge } from 'tailwind-merge';
+import { invoke, isTauri } from '@tauri-apps/api/core';
 
 export function cn(...inputs: ClassValue[]) {
   return twMerge(clsx(inputs));
@@ -44,7 +45,16 @@ export function reorder<T>(list: T[], startIndex: number, endIndex: number): T[]
 }
 
 export async function openExternalUrl(url: string): Promise<void> {
-  // Try server-side open (works in both Tauri and browser with local server)
+  // The desktop app opens URLs itself; there may be no server to ask.
+  if (isTauri()) {
+    try {
+      await invoke('open_url', { url });
+      return;
+    } catch {
+      // fall through to browser fallback
+    }
+  }
+  // Try server-side open (works in a browser with the local server)
   try {
     const res = await fetch('/api/open-url', {
       method: 'POST',